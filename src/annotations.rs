@@ -0,0 +1,183 @@
+use crate::{
+    config::{quoted, ConfigFormat},
+    Error,
+};
+use std::{collections::HashMap, error, fmt, io, path::Path};
+
+const ADMIN_PORT: &str = "config.linkerd.io/admin-port";
+const INBOUND_PORT: &str = "config.linkerd.io/inbound-port";
+const OUTBOUND_PORT: &str = "config.linkerd.io/outbound-port";
+const INJECT: &str = "linkerd.io/inject";
+const NATIVE_SIDECAR: &str = "config.alpha.linkerd.io/proxy-enable-native-sidecar";
+
+/// A pod's annotations, as written to a file by the downward API, e.g.
+///
+/// ```text
+/// config.linkerd.io/admin-port="4192"
+/// linkerd.io/inject="enabled"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PodAnnotations {
+    annotations: HashMap<String, String>,
+    admin_port: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidAnnotations {
+    line: usize,
+    reason: &'static str,
+}
+
+// === impl PodAnnotations ===
+
+impl PodAnnotations {
+    /// Reads the annotations from a downward API volume's file.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::AnnotationsFile(path.to_path_buf(), e))?;
+        contents.parse().map_err(|e| {
+            Error::AnnotationsFile(
+                path.to_path_buf(),
+                io::Error::new(io::ErrorKind::InvalidData, e),
+            )
+        })
+    }
+
+    /// Returns the value of an annotation.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.annotations.get(key).map(String::as_str)
+    }
+
+    /// The proxy's admin server port, as set by the
+    /// `config.linkerd.io/admin-port` annotation.
+    pub fn admin_port(&self) -> Option<u16> {
+        self.admin_port
+    }
+
+    /// The proxy's inbound listener's port, as set by the
+    /// `config.linkerd.io/inbound-port` annotation.
+    pub fn inbound_port(&self) -> Option<u16> {
+        self.get(INBOUND_PORT).and_then(|port| port.parse().ok())
+    }
+
+    /// The proxy's outbound listener's port, as set by the
+    /// `config.linkerd.io/outbound-port` annotation.
+    pub fn outbound_port(&self) -> Option<u16> {
+        self.get(OUTBOUND_PORT).and_then(|port| port.parse().ok())
+    }
+
+    /// Returns the reason Linkerd is disabled when the pod is annotated with
+    /// `linkerd.io/inject: disabled`.
+    pub fn disabled_reason(&self) -> Option<String> {
+        (self.get(INJECT) == Some("disabled"))
+            .then(|| format!("the pod is annotated with {}: disabled", INJECT))
+    }
+
+    /// Whether the proxy runs as a native sidecar, which Kubernetes shuts down
+    /// once the pod's other containers complete.
+    pub fn native_sidecar(&self) -> bool {
+        self.get(NATIVE_SIDECAR) == Some("true")
+    }
+}
+
+impl std::str::FromStr for PodAnnotations {
+    type Err = InvalidAnnotations;
+
+    /// Parses `key="value"` lines, with values quoted as by Go's `%q`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut annotations = HashMap::new();
+        let mut admin_port = None;
+        for (index, text) in s.lines().enumerate() {
+            let invalid = |reason| InvalidAnnotations {
+                line: index + 1,
+                reason,
+            };
+            if text.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| invalid("expected key=\"value\""))?;
+            let (value, rest) = quoted(value, ConfigFormat::Toml).map_err(invalid)?;
+            if !rest.trim().is_empty() {
+                return Err(invalid("unexpected characters after value"));
+            }
+            if key == ADMIN_PORT {
+                let port = value
+                    .parse()
+                    .map_err(|_| invalid("invalid config.linkerd.io/admin-port"))?;
+                admin_port = Some(port);
+            }
+            annotations.insert(key.to_string(), value);
+        }
+        Ok(Self {
+            annotations,
+            admin_port,
+        })
+    }
+}
+
+// === impl InvalidAnnotations ===
+
+impl fmt::Display for InvalidAnnotations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl error::Error for InvalidAnnotations {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotations() {
+        let annotations = r#"config.alpha.linkerd.io/proxy-enable-native-sidecar="true"
+config.linkerd.io/admin-port="4192"
+config.linkerd.io/inbound-port="4243"
+kubectl.kubernetes.io/last-applied-configuration="{\"kind\":\"Pod\"}\n"
+linkerd.io/inject="enabled"
+"#
+        .parse::<PodAnnotations>()
+        .unwrap();
+        assert_eq!(annotations.admin_port(), Some(4192));
+        assert_eq!(annotations.inbound_port(), Some(4243));
+        assert_eq!(annotations.outbound_port(), None);
+        assert_eq!(annotations.disabled_reason(), None);
+        assert!(annotations.native_sidecar());
+        assert_eq!(
+            annotations.get("kubectl.kubernetes.io/last-applied-configuration"),
+            Some("{\"kind\":\"Pod\"}\n")
+        );
+
+        let annotations = "linkerd.io/inject=\"disabled\"\n"
+            .parse::<PodAnnotations>()
+            .unwrap();
+        assert_eq!(annotations.admin_port(), None);
+        assert_eq!(
+            annotations.disabled_reason().as_deref(),
+            Some("the pod is annotated with linkerd.io/inject: disabled")
+        );
+        assert!(!annotations.native_sidecar());
+
+        assert_eq!("".parse::<PodAnnotations>(), Ok(PodAnnotations::default()));
+        for (annotations, line, reason) in [
+            (
+                "a=\"b\"\nconfig.linkerd.io/admin-port=\"admin\"",
+                2,
+                "invalid config.linkerd.io/admin-port",
+            ),
+            ("linkerd.io/inject", 1, "expected key=\"value\""),
+            ("linkerd.io/inject=enabled", 1, "expected a quoted string"),
+            ("linkerd.io/inject=\"enabled", 1, "unterminated string"),
+        ] {
+            assert_eq!(
+                annotations.parse::<PodAnnotations>(),
+                Err(InvalidAnnotations { line, reason }),
+                "{}",
+                annotations
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::time;
+
+/// Determines how long to wait after a failed readiness check.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Backoff {
+    pub strategy: BackoffStrategy,
+    pub base: time::Duration,
+    pub max: Option<time::Duration>,
+
+    /// The factor by which the exponential backoff grows after each failure.
+    pub multiplier: f64,
+
+    /// The percentage of each delay that is randomly skipped, so that many
+    /// pods starting at once don't check in lockstep.
+    pub jitter: u8,
+}
+
+/// Describes how the time between failed readiness checks grows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BackoffStrategy {
+    /// Always waits the base backoff.
+    Constant,
+    /// Waits the base backoff multiplied by the number of failures.
+    Linear,
+    /// Multiplies the time waited by the multiplier (2 by default) after
+    /// each failure.
+    Exponential,
+}
+
+impl Backoff {
+    /// The multiplier used unless one is configured.
+    pub const DEFAULT_MULTIPLIER: f64 = 2.0;
+
+    /// Returns the time to wait after the given number of consecutive failed
+    /// checks.
+    pub fn delay(&self, failures: u32) -> time::Duration {
+        let failures = failures.max(1);
+        let delay = match self.strategy {
+            BackoffStrategy::Constant => self.base,
+            BackoffStrategy::Linear => self.base.saturating_mul(failures),
+            BackoffStrategy::Exponential => self.exponential(failures - 1),
+        };
+        let delay = match self.max {
+            Some(max) => delay.min(max),
+            None => delay,
+        };
+        if self.jitter == 0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - f64::from(self.jitter.min(100)) / 100.0 * random())
+    }
+
+    /// Returns the base backoff multiplied `exponent` times.
+    fn exponential(&self, exponent: u32) -> time::Duration {
+        // Whole multipliers are applied exactly.
+        if self.multiplier.fract() == 0.0 && self.multiplier <= f64::from(u32::MAX) {
+            return (self.multiplier as u32)
+                .checked_pow(exponent)
+                .map_or(time::Duration::MAX, |factor| {
+                    self.base.saturating_mul(factor)
+                });
+        }
+        let exponent = i32::try_from(exponent).unwrap_or(i32::MAX);
+        time::Duration::try_from_secs_f64(self.base.as_secs_f64() * self.multiplier.powi(exponent))
+            .unwrap_or(time::Duration::MAX)
+    }
+}
+
+/// Returns a number in `[0, 1)` that is random enough to spread out retries,
+/// using the randomly-keyed hasher from the standard library rather than a
+/// dependency.
+fn random() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_strategy() {
+        use time::Duration;
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Linear,
+            base: Duration::from_secs(1),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 0,
+        };
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(3));
+
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Exponential,
+            ..backoff
+        };
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(2), Duration::from_secs(2));
+        assert_eq!(backoff.delay(4), Duration::from_secs(8));
+        assert_eq!(backoff.delay(100), Duration::MAX);
+
+        let backoff = Backoff {
+            max: Some(Duration::from_secs(5)),
+            ..backoff
+        };
+        assert_eq!(backoff.delay(4), Duration::from_secs(5));
+        assert_eq!(backoff.delay(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_max() {
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Constant,
+            base: time::Duration::from_secs(2),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 0,
+        };
+        assert_eq!(backoff.delay(1), time::Duration::from_secs(2));
+        assert_eq!(backoff.delay(100), time::Duration::from_secs(2));
+
+        let backoff = Backoff {
+            max: Some(time::Duration::from_secs(1)),
+            ..backoff
+        };
+        assert_eq!(backoff.delay(1), time::Duration::from_secs(1));
+        assert_eq!(backoff.delay(100), time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_backoff_multiplier() {
+        use time::Duration;
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Exponential,
+            base: Duration::from_millis(100),
+            max: None,
+            multiplier: 3.0,
+            jitter: 0,
+        };
+        assert_eq!(backoff.delay(3), Duration::from_millis(900));
+
+        let backoff = Backoff {
+            multiplier: 1.5,
+            ..backoff
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(3).as_millis(), 225);
+        assert_eq!(backoff.delay(u32::MAX), Duration::MAX);
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Constant,
+            base: time::Duration::from_secs(10),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 20,
+        };
+        let delays = (0..100).map(|_| backoff.delay(1)).collect::<Vec<_>>();
+        assert!(delays
+            .iter()
+            .all(|d| *d > time::Duration::from_secs(8) && *d <= time::Duration::from_secs(10)));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+}
@@ -0,0 +1,165 @@
+//! A synchronous build of linkerd-await that supports its core flags without
+//! an async runtime, producing the smallest possible binary.
+
+#![deny(warnings, rust_2018_idioms)]
+
+use clap::Parser;
+use linkerd_await::{
+    admin_uri, blocking, linkerd_disabled_reason, parse_duration, process, Backoff,
+    BackoffStrategy, Error, EX_OSERR,
+};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Parser)]
+#[clap(about, version)]
+/// Wait for linkerd to become ready before running a program.
+struct Args {
+    #[clap(
+        short = 'p',
+        long = "port",
+        default_value = "4191",
+        help = "The port of the local Linkerd proxy admin server"
+    )]
+    port: u16,
+
+    #[clap(
+        short = 'b',
+        long = "backoff",
+        default_value = "1s",
+        value_parser = parse_duration,
+        help = "Time to wait after a failed readiness check",
+    )]
+    backoff: Duration,
+
+    #[clap(
+        long = "backoff-strategy",
+        value_enum,
+        default_value = "constant",
+        help = "How the time between failed readiness checks grows"
+    )]
+    backoff_strategy: BackoffStrategy,
+
+    #[clap(
+        long = "backoff-max",
+        value_parser = parse_duration,
+        help = "The maximum time to wait between readiness checks"
+    )]
+    backoff_max: Option<Duration>,
+
+    #[clap(
+        short = 'S',
+        long = "shutdown",
+        help = "Forks the program and triggers proxy shutdown on completion",
+        requires("CMD")
+    )]
+    shutdown: bool,
+
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        help = "Causes linkerd-await to print an error message when disabled",
+        env = "LINKERD_AWAIT_VERBOSE"
+    )]
+    verbose: bool,
+
+    #[clap(
+        short = 't',
+        long = "timeout",
+        value_parser = parse_duration,
+        help = "Causes linked-await to fail when the timeout elapses before the proxy becomes ready"
+    )]
+    timeout: Option<Duration>,
+
+    #[clap(
+        long = "initial-delay",
+        value_parser = parse_duration,
+        help = "Time to wait before the first readiness check"
+    )]
+    initial_delay: Option<Duration>,
+
+    #[clap(
+        long,
+        help = "Controls whether a readiness timeout failure prevents CMD from running",
+        default_value("true"),
+        default_missing_value("true"),
+        num_args(0..=1),
+        require_equals(true),
+        action = clap::ArgAction::Set,
+        requires("CMD")
+    )]
+    timeout_fatal: bool,
+
+    #[clap(name = "CMD", help = "The command to run after linkerd is ready")]
+    cmd: Option<String>,
+
+    #[clap(name = "ARGS", help = "Arguments to pass to CMD if specified")]
+    args: Vec<String>,
+}
+
+fn main() {
+    let code = match run(Args::parse()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            error.exit_code()
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Runs linkerd-await, returning the exit code of the process.
+fn run(args: Args) -> Result<i32, Error> {
+    let Args {
+        port,
+        backoff,
+        backoff_strategy,
+        backoff_max,
+        shutdown,
+        verbose,
+        timeout,
+        initial_delay,
+        timeout_fatal,
+        cmd,
+        args,
+    } = args;
+
+    match linkerd_disabled_reason() {
+        Some(reason) => {
+            if verbose {
+                eprintln!("Linkerd readiness check skipped: {}", reason);
+            }
+        }
+        None => {
+            let backoff = Backoff {
+                strategy: backoff_strategy,
+                base: backoff,
+                max: backoff_max,
+                multiplier: Backoff::DEFAULT_MULTIPLIER,
+                jitter: 0,
+            };
+            let uri = admin_uri(http::uri::Scheme::HTTP, "localhost", port, "/ready")?;
+            if let Err(error) = blocking::await_ready(&uri, &backoff, initial_delay, timeout) {
+                if timeout_fatal {
+                    return Err(error);
+                }
+                eprintln!("{}", error);
+            }
+
+            if let (true, Some(cmd)) = (shutdown, cmd.as_ref()) {
+                let ex = blocking::supervise(cmd.clone(), args);
+                blocking::send_shutdown(admin_uri(
+                    http::uri::Scheme::HTTP,
+                    "localhost",
+                    port,
+                    "/shutdown",
+                )?);
+                return Ok(ex?.code().unwrap_or(EX_OSERR));
+            }
+        }
+    }
+
+    match cmd {
+        Some(cmd) => Err(process::exec(cmd, args)),
+        None => Ok(0),
+    }
+}
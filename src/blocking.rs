@@ -0,0 +1,270 @@
+//! A synchronous implementation of linkerd-await's core behavior, used by the
+//! `linkerd-await-minimal` binary.
+//!
+//! Requests use blocking sockets and retries sleep the calling thread, so no
+//! async runtime is needed. Only readiness checks of the admin server and
+//! proxy shutdown are supported; HTTP proxies, plugins, and `--background`
+//! mode require the full implementation.
+
+use crate::{client, process, Backoff, CheckError, Error};
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    process::{Command, ExitStatus},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Bounds each check attempt so that a hung admin server is retried.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends a request and returns the response status, failing if the server
+/// doesn't respond within `timeout`.
+pub fn request(
+    req: http::Request<Vec<u8>>,
+    timeout: Duration,
+) -> Result<http::StatusCode, client::Error> {
+    let authority =
+        client::authority(req.uri()).ok_or_else(|| client::Error::InvalidUri(req.uri().clone()))?;
+    let host = authority
+        .host()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = authority.port_u16().unwrap_or(80);
+
+    let mut io = connect(host, port, timeout).map_err(client::Error::Connect)?;
+    io.set_read_timeout(Some(timeout))
+        .map_err(client::Error::Io)?;
+    io.set_write_timeout(Some(timeout))
+        .map_err(client::Error::Io)?;
+    io.write_all(&client::encode(&req, false, false))
+        .map_err(client::Error::Io)?;
+
+    let mut buf = Vec::with_capacity(128);
+    loop {
+        if let Some(end) = buf.windows(2).position(|w| w == b"\r\n") {
+            return client::parse_status(&buf[..end]).ok_or(client::Error::InvalidResponse);
+        }
+        if buf.len() >= client::MAX_STATUS_LINE {
+            return Err(client::Error::InvalidResponse);
+        }
+        let mut chunk = [0; 128];
+        match io.read(&mut chunk).map_err(client::Error::Io)? {
+            0 => return Err(client::Error::InvalidResponse),
+            n => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+/// Connects to the first of the host's addresses that accepts a connection.
+/// As in the async implementation, `localhost` is always both loopback
+/// addresses.
+fn connect(host: &str, port: u16, timeout: Duration) -> io::Result<TcpStream> {
+    let addrs = if host.eq_ignore_ascii_case("localhost") {
+        vec![
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port),
+            SocketAddr::new(Ipv6Addr::LOCALHOST.into(), port),
+        ]
+    } else {
+        (host, port).to_socket_addrs()?.collect()
+    };
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "host has no addresses");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(io) => return Ok(io),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// Checks the proxy's readiness endpoint until it reports ready, failing if
+/// the timeout elapses first or if the endpoint is served by something other
+/// than the proxy. A zero timeout never elapses.
+///
+/// As in the async implementation, the initial delay counts against the
+/// timeout.
+pub fn await_ready(
+    uri: &http::Uri,
+    backoff: &Backoff,
+    initial_delay: Option<Duration>,
+    timeout: Option<Duration>,
+) -> Result<(), Error> {
+    let timeout = timeout.filter(|t| !t.is_zero());
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let remaining = || deadline.map(|d| d.saturating_duration_since(Instant::now()));
+
+    if let Some(delay) = initial_delay {
+        thread::sleep(remaining().map_or(delay, |r| delay.min(r)));
+    }
+
+    // As in the async implementation, failures are counted per class so
+    // that a proxy that starts listening doesn't inherit a long backoff.
+    let mut connect_failures = 0;
+    let mut failures = 0;
+    loop {
+        let attempt = match remaining() {
+            Some(remaining) if remaining.is_zero() => {
+                return Err(Error::Timeout(uri.to_string(), timeout.unwrap_or_default()))
+            }
+            Some(remaining) => remaining.min(ATTEMPT_TIMEOUT),
+            None => ATTEMPT_TIMEOUT,
+        };
+        let req = http::Request::get(uri.clone())
+            .body(Vec::new())
+            .map_err(Error::InvalidRequest)?;
+        let delay = match request(req, attempt) {
+            Ok(status) if status.is_success() => return Ok(()),
+            // The proxy's admin server always serves `/ready`.
+            Ok(http::StatusCode::NOT_FOUND) => {
+                return Err(Error::Unrecoverable(CheckError::unrecoverable(format!(
+                    "GET {}: server responded with {}",
+                    uri,
+                    http::StatusCode::NOT_FOUND
+                ))))
+            }
+            Err(e) if e.is_connect() => {
+                failures = 0;
+                connect_failures += 1;
+                backoff.delay(connect_failures)
+            }
+            Ok(_) | Err(_) => {
+                connect_failures = 0;
+                failures += 1;
+                backoff.delay(failures)
+            }
+        };
+        thread::sleep(remaining().map_or(delay, |r| delay.min(r)));
+    }
+}
+
+/// Asks the proxy to shut down via its admin server. Failures are ignored.
+pub fn send_shutdown(uri: http::Uri) {
+    if let Ok(req) = http::Request::post(uri).body(Vec::new()) {
+        let _ = request(req, ATTEMPT_TIMEOUT);
+    }
+}
+
+/// Runs the command to completion, relaying requests to terminate to it.
+pub fn supervise(cmd: String, args: Vec<String>) -> Result<ExitStatus, Error> {
+    // Handle termination requests before spawning the process so that the
+    // child isn't orphaned by one.
+    process::relay_termination(None).map_err(Error::Signal)?;
+
+    // In an interactive shell, CMD is given the terminal so that job control
+    // reaches it.
+    let mut terminal = process::Terminal::foreground();
+    let mut command = Command::new(&cmd);
+    command.args(args);
+    if let Some(terminal) = terminal.as_ref() {
+        terminal.configure(&mut command);
+    }
+    let mut child = command.spawn().map_err(|e| Error::Spawn(cmd, e))?;
+    process::relay_termination(Some(child.id())).map_err(Error::Signal)?;
+    if let Some(terminal) = terminal.as_mut() {
+        if let Err(e) = relay_stops(terminal, child.id()) {
+            eprintln!("Failed to relay job control to child process: {}", e);
+        }
+    }
+    let status = child.wait().map_err(Error::Wait);
+    drop(terminal);
+    process::relay_termination(None).map_err(Error::Signal)?;
+    status
+}
+
+/// Gives the process the terminal and handles each time it stops until it
+/// exits.
+fn relay_stops(terminal: &mut process::Terminal, pid: u32) -> std::io::Result<()> {
+    terminal.attach(pid)?;
+    while process::wait_stopped(pid)? {
+        terminal.suspend()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BackoffStrategy;
+    use std::net::TcpListener;
+
+    fn serve(responses: &'static [&'static str]) -> http::Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for rsp in responses {
+                let (mut io, _) = listener.accept().unwrap();
+                let _ = io.read(&mut [0; 1024]).unwrap();
+                io.write_all(rsp.as_bytes()).unwrap();
+            }
+        });
+        uri.parse().unwrap()
+    }
+
+    fn backoff() -> Backoff {
+        Backoff {
+            strategy: BackoffStrategy::Constant,
+            base: Duration::from_millis(10),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 0,
+        }
+    }
+
+    #[test]
+    fn test_await_ready() {
+        let uri = serve(&[
+            "HTTP/1.1 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        await_ready(&uri, &backoff(), None, Some(Duration::from_secs(10))).unwrap();
+    }
+
+    #[test]
+    fn test_await_ready_unrecoverable() {
+        let uri = serve(&["HTTP/1.1 404 Not Found\r\n\r\n"]);
+        let error = await_ready(&uri, &backoff(), None, None).unwrap_err();
+        assert!(matches!(error, Error::Unrecoverable(_)), "{}", error);
+    }
+
+    #[test]
+    fn test_await_ready_initial_delay() {
+        let uri = serve(&["HTTP/1.1 200 OK\r\n\r\n"]);
+        let start = Instant::now();
+        await_ready(&uri, &backoff(), Some(Duration::from_millis(50)), None).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // The delay is bounded by the timeout.
+        let start = Instant::now();
+        let error = await_ready(
+            &uri,
+            &backoff(),
+            Some(Duration::from_secs(10)),
+            Some(Duration::from_millis(50)),
+        )
+        .unwrap_err();
+        assert!(matches!(error, Error::Timeout(..)), "{}", error);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_await_ready_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        drop(listener);
+        let error = await_ready(
+            &uri.parse().unwrap(),
+            &backoff(),
+            None,
+            Some(Duration::from_millis(50)),
+        )
+        .unwrap_err();
+        assert!(matches!(error, Error::Timeout(..)), "{}", error);
+    }
+
+    #[test]
+    fn test_supervise() {
+        let status = supervise("sh".to_string(), vec!["-c".into(), "exit 3".into()]).unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+}
@@ -0,0 +1,859 @@
+//! Readiness checks that are retried until they succeed.
+//!
+//! Each check type implements [`Check`], which performs a single attempt. The
+//! retry loop, backoff, and timeouts are shared by all checks and live in
+//! [`await_ready`](crate::await_ready).
+
+use std::{
+    error, fmt,
+    future::{self, Future},
+    pin::Pin,
+    sync::Mutex,
+    task::Poll,
+};
+// Limits are measured with tokio's clock so that they follow the retry
+// loop's when time is paused in tests.
+#[cfg(feature = "runtime")]
+use tokio::time::{self, Duration, Instant};
+
+#[cfg(feature = "runtime")]
+mod command;
+#[cfg(feature = "runtime")]
+mod db;
+#[cfg(feature = "runtime")]
+mod dns;
+#[cfg(feature = "runtime")]
+mod file;
+#[cfg(feature = "runtime")]
+mod grpc;
+#[cfg(feature = "runtime")]
+mod http;
+#[cfg(feature = "k8s")]
+mod k8s;
+#[cfg(feature = "runtime")]
+mod metrics;
+#[cfg(feature = "runtime")]
+mod net;
+#[cfg(feature = "plugins")]
+mod plugin;
+#[cfg(feature = "runtime")]
+mod port;
+#[cfg(feature = "runtime")]
+mod tcp;
+#[cfg(feature = "runtime")]
+mod unix;
+#[cfg(feature = "runtime")]
+mod vault;
+
+#[cfg(feature = "k8s")]
+pub use self::k8s::{LeaseCheck, ObjectCheck, WorkloadCheck};
+#[cfg(feature = "plugins")]
+pub use self::plugin::PluginCheck;
+#[cfg(feature = "runtime")]
+pub use self::{
+    command::CommandCheck,
+    db::{Database, DatabaseCheck, DatabaseUrl, InvalidDatabaseUrl},
+    dns::DnsCheck,
+    file::FileCheck,
+    grpc::GrpcCheck,
+    http::{
+        HttpCheck, IdentityCertCheck, InvalidStatusSet, PreResolveCheck, ProxyIdentityCheck,
+        StatusSet,
+    },
+    metrics::{
+        InvalidMetricCondition, InvalidProxyVersion, MetricCheck, MetricCondition, ProxyVersion,
+        ProxyVersionCheck,
+    },
+    net::{InterfaceCheck, InvalidRoute, Route, RouteCheck},
+    port::PortFreeCheck,
+    tcp::TcpCheck,
+    unix::UnixSocketCheck,
+    vault::VaultTokenCheck,
+};
+
+pub type BoxError = Box<dyn error::Error + Send + Sync>;
+
+/// The result of a single check attempt.
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<(), CheckError>> + 'a>>;
+
+/// A condition that must hold before the command is run.
+///
+/// The `Display` implementation describes the check in log messages.
+pub trait Check: fmt::Display {
+    /// Attempts the check once.
+    fn check(&self) -> CheckFuture<'_>;
+}
+
+/// Succeeds once every check succeeds. Checks are attempted in order, by
+/// default one at a time. Once a check fails, no more checks are started,
+/// but those that are running complete, so that a slow check isn't canceled
+/// by a fast one's failure on every attempt. Only a failure that can't be
+/// retried, e.g. an unrecoverable one, cancels them.
+pub struct All {
+    checks: Vec<Box<dyn Check>>,
+    concurrency: usize,
+}
+
+/// Succeeds once any check succeeds. Checks are attempted in order, by
+/// default one at a time, and an attempt stops at the first success.
+pub struct Any {
+    checks: Vec<Box<dyn Check>>,
+    concurrency: usize,
+}
+
+/// Succeeds once each of several stages has succeeded in turn. A stage isn't
+/// attempted until the previous stages have succeeded, and isn't attempted
+/// again once it has.
+pub struct Stages {
+    stages: Vec<Box<dyn Check>>,
+    passed: Mutex<usize>,
+}
+
+/// Applies a check's own timeouts, backoff, and attempt limit on top of the
+/// retry loop that attempts it, e.g. so that a remote database is checked
+/// less often, and for longer, than a local sidecar, or so that a hung target
+/// is retried without holding up other checks.
+///
+/// While the check is backing off, attempts fail with its last error without
+/// checking again, so its backoff only has an effect when it's longer than
+/// the retry loop's.
+#[cfg(feature = "runtime")]
+pub struct Limited<C> {
+    check: C,
+    timeout: Option<Duration>,
+    attempt_timeout: Option<Duration>,
+    backoff: Option<Duration>,
+    max_attempts: Option<u32>,
+    state: Mutex<LimitedState>,
+}
+
+#[cfg(feature = "runtime")]
+#[derive(Debug, Default)]
+struct LimitedState {
+    started: Option<Instant>,
+    failures: u32,
+    retry_at: Option<Instant>,
+    last: Option<CheckError>,
+}
+
+/// Logs a check's status to stderr whenever it changes, so that the status of
+/// each of several endpoints is visible while waiting.
+pub struct Reported<C> {
+    check: C,
+    last: Mutex<Option<String>>,
+}
+
+/// Describes why a check attempt failed, which determines how (and whether)
+/// it is retried.
+#[derive(Debug)]
+pub enum CheckError {
+    /// The target could not be reached, e.g. because nothing is listening yet.
+    Connect(BoxError),
+
+    /// The target was reached but is not ready.
+    NotReady(BoxError),
+
+    /// The check can never succeed, so it should not be retried.
+    Unrecoverable(BoxError),
+
+    /// The check did not succeed within its own timeout, so it should not be
+    /// retried.
+    Timeout(BoxError),
+}
+
+/// A shared check, e.g. one that is also used after it succeeds.
+impl<C: Check + ?Sized> Check for std::sync::Arc<C> {
+    fn check(&self) -> CheckFuture<'_> {
+        (**self).check()
+    }
+}
+
+/// A boxed check, e.g. one of several kinds that are handled alike.
+impl<C: Check + ?Sized> Check for Box<C> {
+    fn check(&self) -> CheckFuture<'_> {
+        (**self).check()
+    }
+}
+
+// === impl All ===
+
+impl All {
+    pub fn new(checks: Vec<Box<dyn Check>>) -> Self {
+        Self {
+            checks,
+            concurrency: 1,
+        }
+    }
+
+    /// Attempts up to `limit` checks at once, so that an attempt takes as
+    /// long as its slowest check rather than the sum of its checks.
+    pub fn with_concurrency(self, limit: usize) -> Self {
+        Self {
+            concurrency: limit.max(1),
+            ..self
+        }
+    }
+}
+
+impl Check for All {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            // A failure that can't be retried ends the wait, so it's reported
+            // over the others.
+            let terminal = |e: &CheckError| e.is_unrecoverable() || e.is_timeout();
+            let results = attempt(&self.checks, self.concurrency, |result| match result {
+                Ok(()) => Next::Continue,
+                Err(e) if terminal(e) => Next::Stop,
+                Err(_) => Next::Finish,
+            })
+            .await;
+            let mut failures = self
+                .checks
+                .iter()
+                .zip(results)
+                .filter_map(|(check, result)| Some((check, result?.err()?)))
+                .collect::<Vec<_>>();
+            if failures.is_empty() {
+                return Ok(());
+            }
+            let i = failures.iter().position(|(_, e)| terminal(e)).unwrap_or(0);
+            let (check, error) = failures.swap_remove(i);
+            Err(error.context(&**check))
+        })
+    }
+}
+
+impl fmt::Display for All {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_group(f, "all", &self.checks)
+    }
+}
+
+// === impl Any ===
+
+impl Any {
+    pub fn new(checks: Vec<Box<dyn Check>>) -> Self {
+        Self {
+            checks,
+            concurrency: 1,
+        }
+    }
+
+    /// Attempts up to `limit` checks at once.
+    pub fn with_concurrency(self, limit: usize) -> Self {
+        Self {
+            concurrency: limit.max(1),
+            ..self
+        }
+    }
+}
+
+impl Check for Any {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let results = attempt(&self.checks, self.concurrency, |result| {
+                if result.is_ok() {
+                    Next::Stop
+                } else {
+                    Next::Continue
+                }
+            })
+            .await;
+            if results.iter().any(|r| matches!(r, Some(Ok(())))) {
+                return Ok(());
+            }
+            let errors = self
+                .checks
+                .iter()
+                .zip(results)
+                .filter_map(|(check, result)| Some(result?.err()?.context(&**check)))
+                .collect::<Vec<_>>();
+            // The group is retried unless none of its checks can succeed, and
+            // times out once the last of them that might have has. It is only
+            // considered unreachable if none of its targets could be reached.
+            let msg = errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ");
+            if errors.iter().all(CheckError::is_unrecoverable) {
+                Err(CheckError::unrecoverable(msg))
+            } else if errors
+                .iter()
+                .all(|e| e.is_unrecoverable() || e.is_timeout())
+            {
+                Err(CheckError::timeout(msg))
+            } else if errors.iter().all(|e| matches!(e, CheckError::Connect(_))) {
+                Err(CheckError::connect(msg))
+            } else {
+                Err(CheckError::not_ready(msg))
+            }
+        })
+    }
+}
+
+impl fmt::Display for Any {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_group(f, "any", &self.checks)
+    }
+}
+
+// === impl Stages ===
+
+impl Stages {
+    pub fn new(stages: Vec<Box<dyn Check>>) -> Self {
+        Self {
+            stages,
+            passed: Mutex::new(0),
+        }
+    }
+
+    fn passed(&self) -> usize {
+        *self.passed.lock().expect("stage lock must not be poisoned")
+    }
+}
+
+impl Check for Stages {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            // Once a stage succeeds, the next one is attempted right away.
+            while let Some(stage) = self.stages.get(self.passed()) {
+                stage.check().await?;
+                *self.passed.lock().expect("stage lock must not be poisoned") += 1;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Describes the stage that is being attempted, so that errors identify it.
+impl fmt::Display for Stages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let passed = self.passed();
+        if passed < self.stages.len() {
+            write!(f, "stage {} of {}", passed + 1, self.stages.len())
+        } else {
+            write!(f, "{} stages", self.stages.len())
+        }
+    }
+}
+
+/// How an attempt of several checks proceeds once one of them completes.
+enum Next {
+    /// The remaining checks are started as slots free up.
+    Continue,
+    /// No more checks are started, but those that are running complete.
+    Finish,
+    /// The checks that are running are canceled.
+    Stop,
+}
+
+/// Attempts checks, up to `limit` at a time and starting them in order, until
+/// every check that was started has completed or `next` stops the attempt.
+/// Results are returned in the order of the checks; checks that weren't
+/// started, or were canceled, have no result.
+async fn attempt(
+    checks: &[Box<dyn Check>],
+    limit: usize,
+    next: impl Fn(&Result<(), CheckError>) -> Next,
+) -> Vec<Option<Result<(), CheckError>>> {
+    let mut results = checks.iter().map(|_| None).collect::<Vec<_>>();
+    let mut running = Vec::<(usize, CheckFuture<'_>)>::with_capacity(limit);
+    let mut start = 0;
+    let mut finishing = false;
+    future::poll_fn(|cx| loop {
+        while !finishing && running.len() < limit && start < checks.len() {
+            running.push((start, checks[start].check()));
+            start += 1;
+        }
+        if running.is_empty() {
+            return Poll::Ready(());
+        }
+
+        let mut completed = false;
+        let mut i = 0;
+        while i < running.len() {
+            match running[i].1.as_mut().poll(cx) {
+                Poll::Ready(result) => {
+                    let (index, _) = running.remove(i);
+                    let next = next(&result);
+                    results[index] = Some(result);
+                    match next {
+                        Next::Continue => {}
+                        Next::Finish => finishing = true,
+                        Next::Stop => return Poll::Ready(()),
+                    }
+                    completed = true;
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+        // Once a check completes, the next one may start in its slot.
+        if !completed {
+            return Poll::Pending;
+        }
+    })
+    .await;
+    results
+}
+
+/// Describes a group of checks as it's written in a `--require` expression,
+/// e.g. `any(tcp://db-a:5432, tcp://db-b:5432)`.
+fn fmt_group(f: &mut fmt::Formatter<'_>, name: &str, checks: &[Box<dyn Check>]) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    for (i, check) in checks.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        check.fmt(f)?;
+    }
+    f.write_str(")")
+}
+
+// === impl Limited ===
+
+#[cfg(feature = "runtime")]
+impl<C: Check> Limited<C> {
+    pub fn new(check: C) -> Self {
+        Self {
+            check,
+            timeout: None,
+            attempt_timeout: None,
+            backoff: None,
+            max_attempts: None,
+            state: Mutex::new(LimitedState::default()),
+        }
+    }
+
+    /// Fails, without retrying, once the check hasn't succeeded within
+    /// `timeout` of its first attempt.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Fails an attempt that hasn't completed within `timeout`, so that a hung
+    /// target is retried.
+    pub fn with_attempt_timeout(self, timeout: Duration) -> Self {
+        Self {
+            attempt_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Waits at least `backoff` after a failure before checking again.
+    pub fn with_backoff(self, backoff: Duration) -> Self {
+        Self {
+            backoff: Some(backoff),
+            ..self
+        }
+    }
+
+    /// Fails, without retrying, once the check has failed `max` times in a
+    /// row.
+    pub fn with_max_attempts(self, max: u32) -> Self {
+        Self {
+            max_attempts: Some(max),
+            ..self
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LimitedState> {
+        self.state.lock().expect("limit lock must not be poisoned")
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl<C: Check> Check for Limited<C> {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let now = Instant::now();
+            {
+                let mut state = self.state();
+                let started = *state.started.get_or_insert(now);
+                let last = || {
+                    state
+                        .last
+                        .as_ref()
+                        .map_or_else(|| "not checked".to_string(), ToString::to_string)
+                };
+                if let Some(timeout) = self.timeout {
+                    if now.saturating_duration_since(started) >= timeout {
+                        return Err(CheckError::timeout(format!(
+                            "not ready within {:?}: {}",
+                            timeout,
+                            last()
+                        )));
+                    }
+                }
+                if let (Some(retry_at), Some(last)) = (state.retry_at, state.last.as_ref()) {
+                    if now < retry_at {
+                        return Err(last.duplicate());
+                    }
+                }
+            }
+
+            let result = match self.attempt_timeout {
+                Some(timeout) => time::timeout(timeout, self.check.check())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(CheckError::not_ready(format!(
+                            "no response within {:?}",
+                            timeout
+                        )))
+                    }),
+                None => self.check.check().await,
+            };
+            let mut state = self.state();
+            match result {
+                Ok(()) => {
+                    state.failures = 0;
+                    state.retry_at = None;
+                    state.last = None;
+                    Ok(())
+                }
+                Err(error) => {
+                    state.failures += 1;
+                    if self.max_attempts.is_some_and(|max| state.failures >= max) {
+                        return Err(CheckError::unrecoverable(format!(
+                            "failed {} attempts: {}",
+                            state.failures, error
+                        )));
+                    }
+                    state.retry_at = self.backoff.map(|backoff| Instant::now() + backoff);
+                    state.last = Some(error.duplicate());
+                    Err(error)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl<C: Check> fmt::Display for Limited<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.check.fmt(f)
+    }
+}
+
+// === impl Reported ===
+
+impl<C: Check> Reported<C> {
+    pub fn new(check: C) -> Self {
+        Self {
+            check,
+            last: Mutex::new(None),
+        }
+    }
+}
+
+impl<C: Check> Check for Reported<C> {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let result = self.check.check().await;
+            let status = match &result {
+                Ok(()) => "ready".to_string(),
+                Err(error) => error.to_string(),
+            };
+            let mut last = self.last.lock().expect("status lock must not be poisoned");
+            if last.as_deref() != Some(status.as_str()) {
+                eprintln!("{}: {}", self.check, status);
+                *last = Some(status);
+            }
+            result
+        })
+    }
+}
+
+impl<C: Check> fmt::Display for Reported<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.check.fmt(f)
+    }
+}
+
+// === impl CheckError ===
+
+impl CheckError {
+    pub fn connect(error: impl Into<BoxError>) -> Self {
+        Self::Connect(error.into())
+    }
+
+    pub fn not_ready(error: impl Into<BoxError>) -> Self {
+        Self::NotReady(error.into())
+    }
+
+    pub fn unrecoverable(error: impl Into<BoxError>) -> Self {
+        Self::Unrecoverable(error.into())
+    }
+
+    pub fn timeout(error: impl Into<BoxError>) -> Self {
+        Self::Timeout(error.into())
+    }
+
+    pub fn is_unrecoverable(&self) -> bool {
+        matches!(self, Self::Unrecoverable(_))
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout(_))
+    }
+
+    /// Copies the error's class and message, e.g. so that it can be reported
+    /// again.
+    #[cfg(feature = "runtime")]
+    fn duplicate(&self) -> Self {
+        let msg = || -> BoxError { self.to_string().into() };
+        match self {
+            Self::Connect(_) => Self::Connect(msg()),
+            Self::NotReady(_) => Self::NotReady(msg()),
+            Self::Unrecoverable(_) => Self::Unrecoverable(msg()),
+            Self::Timeout(_) => Self::Timeout(msg()),
+        }
+    }
+
+    /// Prefixes the error with a description of the check that failed,
+    /// preserving its class.
+    fn context(self, check: &dyn Check) -> Self {
+        let wrap = |e: BoxError| -> BoxError { format!("{}: {}", check, e).into() };
+        match self {
+            Self::Connect(e) => Self::Connect(wrap(e)),
+            Self::NotReady(e) => Self::NotReady(wrap(e)),
+            Self::Unrecoverable(e) => Self::Unrecoverable(wrap(e)),
+            Self::Timeout(e) => Self::Timeout(wrap(e)),
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(e) | Self::NotReady(e) | Self::Unrecoverable(e) | Self::Timeout(e) => {
+                e.fmt(f)
+            }
+        }
+    }
+}
+
+impl error::Error for CheckError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Connect(e) | Self::NotReady(e) | Self::Unrecoverable(e) | Self::Timeout(e) => {
+                Some(&**e)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_all() {
+        let ok = All::new(vec![
+            Box::new(CommandCheck::new("true", vec![])),
+            Box::new(CommandCheck::new("true", vec![])),
+        ]);
+        assert!(ok.check().await.is_ok());
+
+        let fails = All::new(vec![
+            Box::new(CommandCheck::new("true", vec![])),
+            Box::new(CommandCheck::new("false", vec![])),
+            Box::new(CommandCheck::new(
+                "/nonexistent/linkerd-await-check",
+                vec![],
+            )),
+        ]);
+        match fails.check().await {
+            Err(CheckError::NotReady(e)) => assert_eq!(
+                e.to_string(),
+                "command false: command failed: exit status: 1"
+            ),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_any() {
+        let ok = Any::new(vec![
+            Box::new(CommandCheck::new("false", vec![])),
+            Box::new(CommandCheck::new("true", vec![])),
+        ]);
+        assert!(ok.check().await.is_ok());
+        assert_eq!(ok.to_string(), "any(command false, command true)");
+
+        let fails = Any::new(vec![
+            Box::new(CommandCheck::new("false", vec![])),
+            Box::new(CommandCheck::new(
+                "/nonexistent/linkerd-await-check",
+                vec![],
+            )),
+        ]);
+        match fails.check().await {
+            Err(CheckError::NotReady(e)) => assert!(e
+                .to_string()
+                .starts_with("command false: command failed: exit status: 1; ")),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let never = Any::new(vec![Box::new(CommandCheck::new(
+            "/nonexistent/linkerd-await-check",
+            vec![],
+        ))]);
+        assert!(matches!(
+            never.check().await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stages() {
+        let dir = std::env::temp_dir().join(format!("linkerd-await-stages-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stages = Stages::new(vec![
+            Box::new(FileCheck::new(dir.join("first"))),
+            Box::new(FileCheck::new(dir.join("second"))),
+        ]);
+        assert_eq!(stages.to_string(), "stage 1 of 2");
+
+        // The second stage isn't attempted until the first succeeds.
+        std::fs::write(dir.join("second"), b"ready").unwrap();
+        assert!(matches!(stages.check().await, Err(CheckError::NotReady(_))));
+        assert_eq!(stages.to_string(), "stage 1 of 2");
+
+        std::fs::write(dir.join("first"), b"ready").unwrap();
+        std::fs::remove_file(dir.join("second")).unwrap();
+        assert!(matches!(stages.check().await, Err(CheckError::NotReady(_))));
+        assert_eq!(stages.to_string(), "stage 2 of 2");
+
+        // A stage that has succeeded isn't attempted again.
+        std::fs::remove_file(dir.join("first")).unwrap();
+        std::fs::write(dir.join("second"), b"ready").unwrap();
+        assert!(stages.check().await.is_ok());
+        assert_eq!(stages.to_string(), "2 stages");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_limited() {
+        let path =
+            std::env::temp_dir().join(format!("linkerd-await-limited-{}", std::process::id()));
+        let check = Limited::new(FileCheck::new(&path)).with_backoff(Duration::from_secs(60));
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        // The file isn't checked again until the backoff elapses.
+        std::fs::write(&path, b"ready").unwrap();
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => assert_eq!(e.to_string(), "file does not exist"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let check = Limited::new(CommandCheck::new("false", vec![])).with_max_attempts(2);
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        match check.check().await {
+            Err(CheckError::Unrecoverable(e)) => assert_eq!(
+                e.to_string(),
+                "failed 2 attempts: command failed: exit status: 1"
+            ),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let check = Limited::new(CommandCheck::new("false", vec![])).with_timeout(Duration::ZERO);
+        match check.check().await {
+            Err(CheckError::Timeout(e)) => {
+                assert_eq!(e.to_string(), "not ready within 0ns: not checked")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // A hung attempt is cut short and retried.
+        let check = Limited::new(CommandCheck::new("sleep", vec!["10".into()]))
+            .with_attempt_timeout(Duration::from_millis(10));
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => assert_eq!(e.to_string(), "no response within 10ms"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limited_paused() {
+        use tokio::time;
+
+        let path = std::env::temp_dir().join(format!(
+            "linkerd-await-limited-paused-{}",
+            std::process::id()
+        ));
+        let check = Limited::new(FileCheck::new(&path))
+            .with_backoff(Duration::from_secs(10))
+            .with_timeout(Duration::from_secs(30));
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        std::fs::write(&path, b"ready").unwrap();
+
+        // The backoff holds until just before it elapses.
+        time::advance(Duration::from_millis(9_999)).await;
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        time::advance(Duration::from_millis(1)).await;
+        assert!(check.check().await.is_ok());
+        std::fs::remove_file(&path).unwrap();
+
+        // The timeout is measured from the first attempt.
+        let check =
+            Limited::new(CommandCheck::new("false", vec![])).with_timeout(Duration::from_secs(30));
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        time::advance(Duration::from_millis(29_999)).await;
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        time::advance(Duration::from_millis(1)).await;
+        assert!(check.check().await.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_concurrency() {
+        let sleep = || Box::new(CommandCheck::new("sleep", vec!["0.2".into()])) as Box<dyn Check>;
+
+        let start = std::time::Instant::now();
+        let all = All::new((0..4).map(|_| sleep()).collect()).with_concurrency(4);
+        assert!(all.check().await.is_ok());
+        assert!(start.elapsed() < std::time::Duration::from_millis(600));
+
+        // A check that fails right away doesn't cancel a slower one, which
+        // would otherwise never complete when the group is retried.
+        let all = All::new(vec![sleep(), Box::new(CommandCheck::new("false", vec![]))])
+            .with_concurrency(2);
+        let start = std::time::Instant::now();
+        assert!(matches!(all.check().await, Err(CheckError::NotReady(_))));
+        assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+
+        // No more checks are started once one has failed.
+        let all = All::new(vec![Box::new(CommandCheck::new("false", vec![])), sleep()]);
+        let start = std::time::Instant::now();
+        assert!(matches!(all.check().await, Err(CheckError::NotReady(_))));
+        assert!(start.elapsed() < std::time::Duration::from_millis(200));
+
+        // A check that can never succeed cancels the others, and is reported
+        // over their failures.
+        let all = All::new(vec![
+            Box::new(CommandCheck::new("false", vec![])),
+            sleep(),
+            Box::new(CommandCheck::new(
+                "/nonexistent/linkerd-await-check",
+                vec![],
+            )),
+        ])
+        .with_concurrency(3);
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            all.check().await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+        assert!(start.elapsed() < std::time::Duration::from_millis(200));
+
+        let any = Any::new(vec![sleep(), Box::new(CommandCheck::new("true", vec![]))])
+            .with_concurrency(2);
+        let start = std::time::Instant::now();
+        assert!(any.check().await.is_ok());
+        assert!(start.elapsed() < std::time::Duration::from_millis(200));
+    }
+}
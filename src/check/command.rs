@@ -0,0 +1,113 @@
+use super::{Check, CheckError, CheckFuture};
+use std::{fmt, io, process::Stdio, time::Duration};
+use tokio::{process::Command, time};
+
+/// Checks that a command exits successfully.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandCheck {
+    cmd: String,
+    args: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Failed(std::process::ExitStatus);
+
+// === impl CommandCheck ===
+
+impl CommandCheck {
+    pub fn new(cmd: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            cmd: cmd.into(),
+            args,
+            timeout: None,
+        }
+    }
+
+    /// Kills the command, failing the attempt, if it runs longer than
+    /// `timeout`.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+impl Check for CommandCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let status = Command::new(&self.cmd)
+                .args(&self.args)
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .status();
+            let status = match self.timeout {
+                // The command is killed when its future is dropped.
+                Some(timeout) => time::timeout(timeout, status).await.map_err(|_| {
+                    CheckError::not_ready(format!("command did not exit within {:?}", timeout))
+                })?,
+                None => status.await,
+            };
+            match status {
+                Ok(status) if status.success() => Ok(()),
+                Ok(status) => Err(CheckError::not_ready(Failed(status))),
+                // A command that doesn't exist won't appear later.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Err(CheckError::unrecoverable(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for CommandCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command {}", self.cmd)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+// === impl Failed ===
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for Failed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_check() {
+        assert!(CommandCheck::new("true", vec![]).check().await.is_ok());
+        assert!(matches!(
+            CommandCheck::new("false", vec![]).check().await,
+            Err(CheckError::NotReady(_))
+        ));
+        assert!(matches!(
+            CommandCheck::new("/nonexistent/linkerd-await-check", vec![])
+                .check()
+                .await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_command_check_timeout() {
+        let check =
+            CommandCheck::new("sleep", vec!["10".into()]).with_timeout(Duration::from_millis(10));
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => {
+                assert_eq!(e.to_string(), "command did not exit within 10ms")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
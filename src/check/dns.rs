@@ -0,0 +1,118 @@
+use super::{Check, CheckError, CheckFuture};
+use crate::Resolver;
+use std::{error, fmt, net::IpAddr};
+
+/// Checks that a host name resolves to at least a minimum number of
+/// addresses.
+///
+/// Names are resolved as they are for connections, so `--resolve` overrides
+/// and `--dns-server` apply.
+#[derive(Clone, Debug)]
+pub struct DnsCheck {
+    resolver: Resolver,
+    name: String,
+    min_addrs: usize,
+}
+
+#[derive(Debug)]
+struct TooFewAddrs {
+    addrs: usize,
+    min_addrs: usize,
+}
+
+// === impl DnsCheck ===
+
+impl DnsCheck {
+    pub fn new(resolver: Resolver, name: impl Into<String>) -> Self {
+        Self {
+            resolver,
+            name: name.into(),
+            min_addrs: 1,
+        }
+    }
+
+    /// Requires the name to resolve to at least `min_addrs` addresses.
+    pub fn with_min_addrs(self, min_addrs: usize) -> Self {
+        Self { min_addrs, ..self }
+    }
+}
+
+impl Check for DnsCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let mut ips = self
+                .resolver
+                .resolve(&self.name, 0)
+                .await
+                .map_err(CheckError::not_ready)?
+                .into_iter()
+                .map(|addr| addr.ip())
+                .collect::<Vec<IpAddr>>();
+            ips.sort();
+            ips.dedup();
+            if ips.len() < self.min_addrs {
+                return Err(CheckError::not_ready(TooFewAddrs {
+                    addrs: ips.len(),
+                    min_addrs: self.min_addrs,
+                }));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for DnsCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dns {}", self.name)
+    }
+}
+
+// === impl TooFewAddrs ===
+
+impl fmt::Display for TooFewAddrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resolved to {} addresses; expected at least {}",
+            self.addrs, self.min_addrs
+        )
+    }
+}
+
+impl error::Error for TooFewAddrs {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dns_check() {
+        let resolver = Resolver {
+            overrides: vec![
+                ("db.example".to_string(), [10, 0, 0, 1].into()),
+                ("db.example".to_string(), [10, 0, 0, 2].into()),
+            ],
+            dns_server: None,
+        };
+        assert!(DnsCheck::new(resolver.clone(), "db.example")
+            .check()
+            .await
+            .is_ok());
+        assert!(DnsCheck::new(resolver.clone(), "db.example")
+            .with_min_addrs(2)
+            .check()
+            .await
+            .is_ok());
+        match DnsCheck::new(resolver, "db.example")
+            .with_min_addrs(3)
+            .check()
+            .await
+        {
+            Err(CheckError::NotReady(e)) => assert_eq!(
+                e.to_string(),
+                "resolved to 2 addresses; expected at least 3"
+            ),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
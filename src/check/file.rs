@@ -0,0 +1,56 @@
+use super::{Check, CheckError, CheckFuture};
+use std::{fmt, io, path::PathBuf};
+
+/// Checks that a file exists and is not empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileCheck {
+    path: PathBuf,
+}
+
+// === impl FileCheck ===
+
+impl FileCheck {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Check for FileCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            match std::fs::metadata(&self.path) {
+                Ok(meta) if meta.len() > 0 => Ok(()),
+                Ok(_) => Err(CheckError::not_ready("file is empty")),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    Err(CheckError::not_ready("file does not exist"))
+                }
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for FileCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "file {}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_check() {
+        let path = std::env::temp_dir().join(format!("linkerd-await-test-{}", std::process::id()));
+        let check = FileCheck::new(&path);
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        std::fs::write(&path, b"ready").unwrap();
+        assert!(check.check().await.is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use super::{http::UnexpectedStatus, Check, CheckError, CheckFuture};
+use crate::{client, proxy};
+use std::{error, fmt};
+
+/// The health checking protocol's `Check` method.
+const HEALTH_CHECK: &str = "/grpc.health.v1.Health/Check";
+
+/// The `SERVING` value of a `HealthCheckResponse`'s status.
+const SERVING: u64 = 1;
+
+/// Checks that a gRPC server reports a service as `SERVING` through the
+/// health checking protocol (`grpc.health.v1.Health/Check`).
+///
+/// Requests are sent with cleartext HTTP/2 (h2c), on a new connection each
+/// attempt.
+#[derive(Debug)]
+pub struct GrpcCheck {
+    uri: http::Uri,
+    service: String,
+    client: client::Persistent,
+}
+
+/// A `HealthCheckResponse` whose status isn't `SERVING`.
+#[derive(Debug)]
+struct NotServing(u64);
+
+/// The response didn't carry a `HealthCheckResponse`, e.g. because the server
+/// doesn't implement the health service or doesn't know the service.
+#[derive(Copy, Clone, Debug)]
+struct InvalidResponse(&'static str);
+
+// === impl GrpcCheck ===
+
+impl GrpcCheck {
+    /// Returns the URI of the health checking service of the server at the
+    /// authority.
+    pub fn uri(authority: http::uri::Authority) -> Result<http::Uri, http::Error> {
+        http::Uri::builder()
+            .scheme(http::uri::Scheme::HTTP)
+            .authority(authority)
+            .path_and_query(HEALTH_CHECK)
+            .build()
+    }
+
+    /// Creates a check, through the health checking service at `uri` (see
+    /// [`GrpcCheck::uri`]), of a server's overall health or, if `service` is
+    /// not empty, of one of its services.
+    pub fn new(connector: proxy::Connector, uri: http::Uri, service: impl Into<String>) -> Self {
+        Self {
+            client: client::Persistent::new(connector.with_http2(), uri.clone()),
+            uri,
+            service: service.into(),
+        }
+    }
+}
+
+impl Check for GrpcCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let req = http::Request::post(self.uri.clone())
+                .header(http::header::CONTENT_TYPE, "application/grpc")
+                .header(http::header::TE, "trailers")
+                .body(encode_request(&self.service))
+                .map_err(CheckError::unrecoverable)?;
+            match self.client.fetch(req).await {
+                Ok(rsp) if rsp.status() == http::StatusCode::OK => {
+                    match decode_response(rsp.body()) {
+                        Ok(SERVING) => Ok(()),
+                        Ok(status) => Err(CheckError::not_ready(NotServing(status))),
+                        Err(e) => Err(CheckError::not_ready(e)),
+                    }
+                }
+                Ok(rsp) => Err(CheckError::not_ready(UnexpectedStatus(rsp.status()))),
+                Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for GrpcCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let authority = self.uri.authority().expect("URI must have an authority");
+        write!(f, "grpc://{}", authority)?;
+        if !self.service.is_empty() {
+            write!(f, "/{}", self.service)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a length-prefixed `HealthCheckRequest` message.
+fn encode_request(service: &str) -> Vec<u8> {
+    let mut msg = Vec::new();
+    // An empty service is the default value, which isn't encoded.
+    if !service.is_empty() {
+        // Field 1, length-delimited.
+        msg.push(0x0a);
+        push_varint(&mut msg, service.len() as u64);
+        msg.extend_from_slice(service.as_bytes());
+    }
+    let mut buf = vec![0];
+    buf.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&msg);
+    buf
+}
+
+/// Decodes the status of a length-prefixed `HealthCheckResponse` message.
+fn decode_response(body: &[u8]) -> Result<u64, InvalidResponse> {
+    // Errors, e.g. `UNIMPLEMENTED`, are reported in trailers, without a
+    // message.
+    if body.is_empty() {
+        return Err(InvalidResponse(
+            "server did not respond with a health status",
+        ));
+    }
+    let malformed = InvalidResponse("malformed health check response");
+    let (prefix, rest) = body.split_at_checked(5).ok_or(malformed)?;
+    if prefix[0] != 0 {
+        return Err(InvalidResponse("compressed responses are not supported"));
+    }
+    let len = u32::from_be_bytes([prefix[1], prefix[2], prefix[3], prefix[4]]) as usize;
+    let mut msg = rest.get(..len).ok_or(malformed)?;
+    // An omitted status is the default value, `UNKNOWN`.
+    let mut status = 0;
+    while !msg.is_empty() {
+        let key = decode_varint(&mut msg).ok_or(malformed)?;
+        let skip = match key & 0x7 {
+            0 => {
+                let value = decode_varint(&mut msg).ok_or(malformed)?;
+                if key >> 3 == 1 {
+                    status = value;
+                }
+                0
+            }
+            1 => 8,
+            2 => decode_varint(&mut msg).ok_or(malformed)? as usize,
+            5 => 4,
+            _ => return Err(malformed),
+        };
+        msg = msg.get(skip..).ok_or(malformed)?;
+    }
+    Ok(status)
+}
+
+fn push_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn decode_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (i, b) in buf.iter().enumerate().take(10) {
+        value |= u64::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            *buf = &buf[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+// === impl NotServing ===
+
+impl fmt::Display for NotServing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "server reported UNKNOWN"),
+            2 => write!(f, "server reported NOT_SERVING"),
+            3 => write!(f, "server reported SERVICE_UNKNOWN"),
+            status => write!(f, "server reported status {}", status),
+        }
+    }
+}
+
+impl error::Error for NotServing {}
+
+// === impl InvalidResponse ===
+
+impl fmt::Display for InvalidResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl error::Error for InvalidResponse {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_encode_request() {
+        assert_eq!(encode_request(""), [0, 0, 0, 0, 0]);
+        let mut expected = vec![0, 0, 0, 0, 9, 0x0a, 7];
+        expected.extend_from_slice(b"orders1");
+        assert_eq!(encode_request("orders1"), expected);
+    }
+
+    #[test]
+    fn test_decode_response() {
+        assert_eq!(decode_response(&[0, 0, 0, 0, 2, 0x08, 1]).ok(), Some(1));
+        assert_eq!(decode_response(&[0, 0, 0, 0, 2, 0x08, 2]).ok(), Some(2));
+        // An omitted status, and an unknown field before the status.
+        assert_eq!(decode_response(&[0, 0, 0, 0, 0]).ok(), Some(0));
+        assert_eq!(
+            decode_response(&[0, 0, 0, 0, 5, 0x12, 1, b'x', 0x08, 1]).ok(),
+            Some(1)
+        );
+
+        for body in [
+            &[][..],
+            &[0, 0, 0],
+            &[1, 0, 0, 0, 2, 0x08, 1],
+            &[0, 0, 0, 0, 3, 0x08, 1],
+            &[0, 0, 0, 0, 2, 0x08, 0x80],
+            &[0, 0, 0, 0, 2, 0x12, 4],
+            &[0, 0, 0, 0, 2, 0x0b, 1],
+        ] {
+            assert!(decode_response(body).is_err(), "{:?}", body);
+        }
+    }
+
+    /// Serves a single HTTP/2 response with a `HealthCheckResponse` whose
+    /// status is `status`.
+    async fn serve(status: u8) -> http::Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut frames = Vec::new();
+            // Settings, then `:status: 200`, the message, and empty trailers.
+            for (kind, flags, stream, payload) in [
+                (0x4, 0x0, 0, &[][..]),
+                (0x1, 0x4, 1, &[0x88]),
+                (0x0, 0x0, 1, &[0, 0, 0, 0, 2, 0x08, status]),
+                (0x1, 0x5, 1, &[]),
+            ] {
+                frames.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+                frames.extend_from_slice(&[kind, flags]);
+                frames.extend_from_slice(&(stream as u32).to_be_bytes());
+                frames.extend_from_slice(payload);
+            }
+            io.write_all(&frames).await.unwrap();
+            // Keep the connection open until the client is done with it.
+            let _ = io.read_to_end(&mut Vec::new()).await;
+        });
+        GrpcCheck::uri(addr.to_string().parse().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_grpc_check() {
+        let check = GrpcCheck::new(proxy::Connector::new(false), serve(1).await, "");
+        assert!(check.check().await.is_ok());
+
+        let check = GrpcCheck::new(proxy::Connector::new(false), serve(2).await, "orders");
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => {
+                assert_eq!(e.to_string(), "server reported NOT_SERVING")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(check.to_string().ends_with("/orders"));
+    }
+}
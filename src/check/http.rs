@@ -0,0 +1,614 @@
+use super::{Check, CheckError, CheckFuture};
+use crate::{client, proxy};
+use std::{
+    error, fmt,
+    net::SocketAddr,
+    ops::RangeInclusive,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Checks that an HTTP endpoint responds with a successful status and,
+/// optionally, a body that contains some text.
+///
+/// The connection is kept alive between attempts so that frequent checks
+/// don't reconnect each time.
+#[derive(Debug)]
+pub struct HttpCheck {
+    uri: http::Uri,
+    client: client::Persistent,
+    expected: StatusSet,
+    expected_body: Option<String>,
+    headers: http::HeaderMap,
+    fail_fast_on_not_found: bool,
+}
+
+/// The response statuses that indicate readiness, e.g. `200,204` or
+/// `200-299,503`. By default, any 2xx status is expected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusSet(Vec<RangeInclusive<u16>>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidStatusSet(String);
+
+/// Checks that an admin server is a Linkerd proxy's by looking for the
+/// proxy's build info in its metrics, so that readiness reported by another
+/// server listening on the admin port isn't trusted.
+///
+/// Once the server is verified, it isn't checked again.
+#[derive(Debug)]
+pub struct ProxyIdentityCheck {
+    uri: http::Uri,
+    client: client::Persistent,
+    verified: AtomicBool,
+    headers: http::HeaderMap,
+}
+
+/// Checks that a Linkerd proxy has been issued its identity certificate, by
+/// looking for the certificate's expiration time in the proxy's metrics, so
+/// that mTLS connections can be established as soon as CMD runs.
+#[derive(Debug)]
+pub struct IdentityCertCheck {
+    uri: http::Uri,
+    client: client::Persistent,
+    headers: http::HeaderMap,
+}
+
+/// Resolves a destination through the proxy by sending it a `HEAD` request
+/// through the proxy's outbound listener, so that the proxy's discovery cache
+/// is warm before the application's first request. The proxy routes the
+/// request by its `Host`, the destination's authority.
+///
+/// Any response except a 503 or 504, which the proxy returns while it can't
+/// resolve or reach the destination, indicates that the destination was
+/// resolved, whatever the server made of the request.
+#[derive(Debug)]
+pub struct PreResolveCheck {
+    uri: http::Uri,
+    outbound: SocketAddr,
+    client: client::Persistent,
+}
+
+#[derive(Debug)]
+pub(super) struct UnexpectedStatus(pub(super) http::StatusCode);
+
+#[derive(Debug)]
+struct NotAProxy;
+
+#[derive(Debug)]
+struct NoIdentityCert;
+
+#[derive(Debug)]
+struct UnexpectedBody(String);
+
+/// A metric that every Linkerd proxy exports.
+const BUILD_INFO: &[u8] = b"proxy_build_info";
+
+/// A metric that a Linkerd proxy exports once it has a certificate.
+const CERT_EXPIRATION: &str = "identity_cert_expiration_timestamp_seconds";
+
+// === impl HttpCheck ===
+
+impl HttpCheck {
+    pub fn new(connector: proxy::Connector, uri: http::Uri) -> Self {
+        Self {
+            client: client::Persistent::new(connector, uri.clone()),
+            uri,
+            expected: StatusSet::default(),
+            expected_body: None,
+            headers: http::HeaderMap::new(),
+            fail_fast_on_not_found: false,
+        }
+    }
+
+    /// Sends the headers with each request.
+    pub fn with_headers(self, headers: http::HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+
+    /// Fails without retrying when the endpoint responds with a 404, as the
+    /// Linkerd proxy's admin server always serves `/ready`. Other endpoints
+    /// may respond with a 404 while they start, e.g. until routes register.
+    pub fn fail_fast_on_not_found(self) -> Self {
+        Self {
+            fail_fast_on_not_found: true,
+            ..self
+        }
+    }
+
+    /// Accepts only responses with the given statuses.
+    pub fn with_expected_status(self, expected: StatusSet) -> Self {
+        Self { expected, ..self }
+    }
+
+    /// Accepts only responses whose body contains the text.
+    pub fn with_expected_body(self, text: impl Into<String>) -> Self {
+        Self {
+            expected_body: Some(text.into()),
+            ..self
+        }
+    }
+
+    /// Checks that the response body contains the expected text, if any.
+    fn check_body(&self, body: &[u8]) -> Result<(), CheckError> {
+        match self.expected_body.as_deref() {
+            Some(text) if !String::from_utf8_lossy(body).contains(text) => {
+                Err(CheckError::not_ready(UnexpectedBody(text.to_string())))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Check for HttpCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let mut req = http::Request::get(self.uri.clone())
+                .body(Vec::new())
+                .map_err(CheckError::unrecoverable)?;
+            *req.headers_mut() = self.headers.clone();
+            // The body is only read into memory when it's matched.
+            let rsp = match self.expected_body {
+                Some(_) => self.client.fetch(req).await,
+                None => self.client.send(req).await.map(|status| {
+                    let mut rsp = http::Response::new(Vec::new());
+                    *rsp.status_mut() = status;
+                    rsp
+                }),
+            };
+            match rsp {
+                Ok(rsp) if self.expected.contains(rsp.status()) => self.check_body(rsp.body()),
+                // The proxy's admin server always serves `/ready`, so a 404
+                // indicates that a different server is listening on the admin
+                // port.
+                Ok(rsp)
+                    if self.fail_fast_on_not_found
+                        && rsp.status() == http::StatusCode::NOT_FOUND =>
+                {
+                    Err(CheckError::unrecoverable(UnexpectedStatus(
+                        http::StatusCode::NOT_FOUND,
+                    )))
+                }
+                Ok(rsp) => Err(CheckError::not_ready(UnexpectedStatus(rsp.status()))),
+                Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for HttpCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GET {}", self.uri)
+    }
+}
+
+// === impl StatusSet ===
+
+impl StatusSet {
+    pub fn contains(&self, status: http::StatusCode) -> bool {
+        self.0.iter().any(|r| r.contains(&status.as_u16()))
+    }
+}
+
+impl Default for StatusSet {
+    fn default() -> Self {
+        Self(vec![200..=299])
+    }
+}
+
+impl FromStr for StatusSet {
+    type Err = InvalidStatusSet;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidStatusSet(s.to_string());
+        let status = |s: &str| match s.trim().parse::<u16>() {
+            Ok(status) if (100..=599).contains(&status) => Ok(status),
+            _ => Err(invalid()),
+        };
+        s.split(',')
+            .map(|part| match part.split_once('-') {
+                Some((lo, hi)) => match (status(lo)?, status(hi)?) {
+                    (lo, hi) if lo <= hi => Ok(lo..=hi),
+                    _ => Err(invalid()),
+                },
+                None => status(part).map(|s| s..=s),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for InvalidStatusSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid statuses {:?}; expected statuses or ranges, e.g. 200-299,503",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidStatusSet {}
+
+// === impl ProxyIdentityCheck ===
+
+impl ProxyIdentityCheck {
+    /// Creates a check of the admin server's metrics endpoint, e.g.
+    /// `http://localhost:4191/metrics`.
+    pub fn new(connector: proxy::Connector, uri: http::Uri) -> Self {
+        Self {
+            client: client::Persistent::new(connector, uri.clone()),
+            uri,
+            verified: AtomicBool::new(false),
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Sends the headers with each request.
+    pub fn with_headers(self, headers: http::HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+}
+
+impl Check for ProxyIdentityCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            if self.verified.load(Ordering::Acquire) {
+                return Ok(());
+            }
+            let mut req = http::Request::get(self.uri.clone())
+                .body(Vec::new())
+                .map_err(CheckError::unrecoverable)?;
+            *req.headers_mut() = self.headers.clone();
+            match self.client.fetch(req).await {
+                Ok(rsp) if rsp.status().is_success() => {
+                    if !rsp
+                        .body()
+                        .windows(BUILD_INFO.len())
+                        .any(|w| w == BUILD_INFO)
+                    {
+                        return Err(CheckError::unrecoverable(NotAProxy));
+                    }
+                    self.verified.store(true, Ordering::Release);
+                    Ok(())
+                }
+                Ok(rsp) if rsp.status() == http::StatusCode::NOT_FOUND => {
+                    Err(CheckError::unrecoverable(NotAProxy))
+                }
+                Ok(rsp) => Err(CheckError::not_ready(UnexpectedStatus(rsp.status()))),
+                Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for ProxyIdentityCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GET {}", self.uri)
+    }
+}
+
+// === impl IdentityCertCheck ===
+
+impl IdentityCertCheck {
+    /// Creates a check of the admin server's metrics endpoint, e.g.
+    /// `http://localhost:4191/metrics`.
+    pub fn new(connector: proxy::Connector, uri: http::Uri) -> Self {
+        Self {
+            client: client::Persistent::new(connector, uri.clone()),
+            uri,
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Sends the headers with each request.
+    pub fn with_headers(self, headers: http::HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+}
+
+impl Check for IdentityCertCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let mut req = http::Request::get(self.uri.clone())
+                .body(Vec::new())
+                .map_err(CheckError::unrecoverable)?;
+            *req.headers_mut() = self.headers.clone();
+            match self.client.fetch(req).await {
+                Ok(rsp) if rsp.status().is_success() => {
+                    if has_cert_expiration(&String::from_utf8_lossy(rsp.body())) {
+                        Ok(())
+                    } else {
+                        Err(CheckError::not_ready(NoIdentityCert))
+                    }
+                }
+                Ok(rsp) => Err(CheckError::not_ready(UnexpectedStatus(rsp.status()))),
+                Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for IdentityCertCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "identity certificate {}", self.uri)
+    }
+}
+
+/// Determines whether the metrics include a certificate's expiration time,
+/// which is 0 until a certificate has been issued.
+fn has_cert_expiration(metrics: &str) -> bool {
+    metrics.lines().any(|line| {
+        let value = match line.strip_prefix(CERT_EXPIRATION) {
+            Some(rest) if rest.starts_with([' ', '{']) => rest.rsplit(' ').next(),
+            _ => None,
+        };
+        value
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .is_some_and(|v| v > 0.0)
+    })
+}
+
+// === impl PreResolveCheck ===
+
+impl PreResolveCheck {
+    /// Creates a check of a destination's root, e.g.
+    /// `http://web.emojivoto.svc.cluster.local:80/`, through the outbound
+    /// listener, e.g. `127.0.0.1:4140`.
+    pub fn new(connector: proxy::Connector, outbound: SocketAddr, uri: http::Uri) -> Self {
+        let listener = http::Uri::builder()
+            .scheme(http::uri::Scheme::HTTP)
+            .authority(outbound.to_string())
+            .path_and_query("/")
+            .build()
+            .expect("socket addresses must be valid authorities");
+        Self {
+            client: client::Persistent::new(connector, listener),
+            outbound,
+            uri,
+        }
+    }
+}
+
+impl Check for PreResolveCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let req = http::Request::head(self.uri.clone())
+                .body(Vec::new())
+                .map_err(CheckError::unrecoverable)?;
+            match self.client.send(req).await {
+                Ok(
+                    status @ (http::StatusCode::SERVICE_UNAVAILABLE
+                    | http::StatusCode::GATEWAY_TIMEOUT),
+                ) => Err(CheckError::not_ready(UnexpectedStatus(status))),
+                Ok(_) => Ok(()),
+                Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+                Err(e) => Err(CheckError::not_ready(e)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for PreResolveCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HEAD {} through {}", self.uri, self.outbound)
+    }
+}
+
+// === impl NotAProxy ===
+
+impl fmt::Display for NotAProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server is not a Linkerd proxy's admin server")
+    }
+}
+
+impl error::Error for NotAProxy {}
+
+// === impl NoIdentityCert ===
+
+impl fmt::Display for NoIdentityCert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proxy has not been issued an identity certificate")
+    }
+}
+
+impl error::Error for NoIdentityCert {}
+
+// === impl UnexpectedBody ===
+
+impl fmt::Display for UnexpectedBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "response body does not contain {:?}", self.0)
+    }
+}
+
+impl error::Error for UnexpectedBody {}
+
+// === impl UnexpectedStatus ===
+
+impl fmt::Display for UnexpectedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server responded with {}", self.0)
+    }
+}
+
+impl error::Error for UnexpectedStatus {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_http_check_reuses_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // The first connection serves two requests and is then closed
+            // without notice, so the third request needs a new connection.
+            let (mut io, _) = listener.accept().await.unwrap();
+            for rsp in ["503 Service Unavailable", "200 OK"] {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let rsp = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", rsp);
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+            drop(io);
+
+            let (mut io, _) = listener.accept().await.unwrap();
+            let _ = io.read(&mut [0; 1024]).await.unwrap();
+            io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let check = HttpCheck::new(proxy::Connector::new(false), uri.parse().unwrap());
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(check.check().await.is_ok());
+        assert!(check.check().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_http_check_expected_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            for body in ["starting\n", "ready\n"] {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let rsp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let check = HttpCheck::new(proxy::Connector::new(false), uri.parse().unwrap())
+            .with_expected_body("ready\n");
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => {
+                assert_eq!(e.to_string(), "response body does not contain \"ready\\n\"")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(check.check().await.is_ok());
+    }
+
+    #[test]
+    fn test_status_set() {
+        let set = "200,204".parse::<StatusSet>().unwrap();
+        assert!(set.contains(http::StatusCode::OK));
+        assert!(set.contains(http::StatusCode::NO_CONTENT));
+        assert!(!set.contains(http::StatusCode::CREATED));
+
+        let set = "200-299, 503".parse::<StatusSet>().unwrap();
+        assert!(set.contains(http::StatusCode::ACCEPTED));
+        assert!(set.contains(http::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!set.contains(http::StatusCode::NOT_FOUND));
+
+        assert!(StatusSet::default().contains(http::StatusCode::NO_CONTENT));
+        assert!("".parse::<StatusSet>().is_err());
+        assert!("299-200".parse::<StatusSet>().is_err());
+        assert!("200,".parse::<StatusSet>().is_err());
+        assert!("600".parse::<StatusSet>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_identity_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // Each check has its own connection.
+            for body in [
+                "proxy_build_info{version=\"v2\"} 1\n",
+                "http_requests_total 7\n",
+            ] {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let rsp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let check = ProxyIdentityCheck::new(proxy::Connector::new(false), uri.parse().unwrap());
+        assert!(check.check().await.is_ok());
+        // Once verified, the server isn't requested again.
+        assert!(check.check().await.is_ok());
+
+        let check = ProxyIdentityCheck::new(proxy::Connector::new(false), uri.parse().unwrap());
+        assert!(matches!(
+            check.check().await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+    }
+
+    #[test]
+    fn test_has_cert_expiration() {
+        assert!(has_cert_expiration(
+            "# TYPE identity_cert_expiration_timestamp_seconds gauge\nidentity_cert_expiration_timestamp_seconds 1760659200\n"
+        ));
+        assert!(has_cert_expiration(
+            "identity_cert_expiration_timestamp_seconds{id=\"web\"} 1.76e9\n"
+        ));
+        assert!(!has_cert_expiration(
+            "identity_cert_expiration_timestamp_seconds 0\n"
+        ));
+        assert!(!has_cert_expiration(
+            "identity_cert_expiration_timestamp_seconds_total 1\nproxy_build_info 1\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pre_resolve_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let outbound = listener.local_addr().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // Responses to HEAD requests have no body, whatever their
+            // content-length.
+            let (mut io, _) = listener.accept().await.unwrap();
+            for rsp in ["504 Gateway Timeout", "405 Method Not Allowed"] {
+                let mut buf = [0; 1024];
+                let n = io.read(&mut buf).await.unwrap();
+                tx.send(String::from_utf8_lossy(&buf[..n]).into_owned())
+                    .unwrap();
+                let rsp = format!("HTTP/1.1 {}\r\ncontent-length: 12\r\n\r\n", rsp);
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        // The destination needn't resolve: the outbound listener routes the
+        // request by its authority.
+        let uri = "http://web.emojivoto.svc.cluster.local:80/"
+            .parse()
+            .unwrap();
+        let check = PreResolveCheck::new(proxy::Connector::new(false), outbound, uri);
+        assert_eq!(
+            check.to_string(),
+            format!(
+                "HEAD http://web.emojivoto.svc.cluster.local:80/ through {}",
+                outbound
+            )
+        );
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(check.check().await.is_ok());
+        let req = rx.recv().await.unwrap();
+        assert!(
+            req.starts_with("HEAD / HTTP/1.1\r\nHost: web.emojivoto.svc.cluster.local:80\r\n"),
+            "{}",
+            req
+        );
+    }
+}
@@ -0,0 +1,921 @@
+use super::{Check, CheckError, CheckFuture};
+use crate::{
+    k8s::{self, ObjectKind, ObjectRef, Workload, WorkloadKind},
+    timestamp,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::time::Instant;
+
+/// Checks that a Kubernetes workload reports that it's ready, as `kubectl
+/// rollout status` would.
+#[derive(Debug)]
+pub struct WorkloadCheck {
+    client: Arc<k8s::Client>,
+    workload: Workload,
+    namespace: String,
+}
+
+/// Checks that a ConfigMap or Secret exists and, optionally, that it has a
+/// key.
+#[derive(Debug)]
+pub struct ObjectCheck {
+    client: Arc<k8s::Client>,
+    object: ObjectRef,
+    namespace: String,
+}
+
+/// Checks that a coordination.k8s.io Lease is free or, when acquiring it,
+/// that it is held by this process.
+///
+/// An acquired lease must be renewed with [`LeaseCheck::hold`] or it expires
+/// after its duration elapses.
+///
+/// As with client-go's leader election, a lease expires once it hasn't
+/// changed for its duration as measured by the local monotonic clock, so
+/// clock skew between nodes or a wall-clock step can't expire a lease early.
+#[derive(Debug)]
+pub struct LeaseCheck {
+    client: Arc<k8s::Client>,
+    name: String,
+    namespace: String,
+    acquire: Option<Acquire>,
+    observed: Mutex<Option<Observed>>,
+}
+
+#[derive(Debug)]
+struct Acquire {
+    holder: String,
+    duration: Duration,
+}
+
+/// A lease's spec and when it was first seen.
+#[derive(Debug)]
+struct Observed {
+    spec: String,
+    at: Instant,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Object {
+    metadata: Metadata,
+    spec: Spec,
+    status: Status,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Metadata {
+    generation: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Spec {
+    replicas: i32,
+    update_strategy: UpdateStrategy,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct UpdateStrategy {
+    #[serde(rename = "type")]
+    kind: String,
+    rolling_update: RollingUpdate,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RollingUpdate {
+    partition: i32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Status {
+    observed_generation: i64,
+    replicas: i32,
+    updated_replicas: i32,
+    ready_replicas: i32,
+    available_replicas: i32,
+    current_revision: String,
+    update_revision: String,
+    phase: String,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Condition {
+    #[serde(rename = "type")]
+    kind: String,
+    status: String,
+    reason: String,
+}
+
+/// The data of a ConfigMap or Secret. Values are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Data {
+    data: HashMap<String, serde::de::IgnoredAny>,
+    binary_data: HashMap<String, serde::de::IgnoredAny>,
+}
+
+#[derive(Debug)]
+struct UnexpectedStatus(http::StatusCode);
+
+// === impl WorkloadCheck ===
+
+impl WorkloadCheck {
+    pub fn new(client: Arc<k8s::Client>, workload: Workload, namespace: String) -> Self {
+        Self {
+            client,
+            workload,
+            namespace,
+        }
+    }
+}
+
+impl Check for WorkloadCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let rsp = match self.client.get(&self.workload.path(&self.namespace)).await {
+                Ok(rsp) => rsp,
+                Err(e) if e.is_connect() => return Err(CheckError::connect(e)),
+                Err(e @ k8s::Error::Token(_)) => return Err(CheckError::unrecoverable(e)),
+                Err(e) => return Err(CheckError::not_ready(e)),
+            };
+            match rsp.status() {
+                status if status.is_success() => {}
+                // The workload may not have been created yet.
+                http::StatusCode::NOT_FOUND => return Err(CheckError::not_ready("not found")),
+                // The service account isn't allowed to read the workload.
+                status @ (http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN) => {
+                    return Err(CheckError::unrecoverable(UnexpectedStatus(status)))
+                }
+                status => return Err(CheckError::not_ready(UnexpectedStatus(status))),
+            }
+            let object =
+                serde_json::from_slice::<Object>(rsp.body()).map_err(CheckError::not_ready)?;
+            match self.workload.kind {
+                WorkloadKind::Deployment => deployment_ready(&object),
+                WorkloadKind::StatefulSet => statefulset_ready(&object),
+                WorkloadKind::Pod => pod_ready(&object.status),
+            }
+        })
+    }
+}
+
+impl fmt::Display for WorkloadCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in namespace {}", self.workload, self.namespace)
+    }
+}
+
+// === impl ObjectCheck ===
+
+impl ObjectCheck {
+    pub fn new(client: Arc<k8s::Client>, object: ObjectRef, namespace: String) -> Self {
+        Self {
+            client,
+            object,
+            namespace,
+        }
+    }
+}
+
+impl Check for ObjectCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let body = get_existing(&self.client, &self.object.path(&self.namespace)).await?;
+            let key = match self.object.key.as_ref() {
+                Some(key) => key,
+                None => return Ok(()),
+            };
+            let data = serde_json::from_slice::<Data>(&body).map_err(CheckError::not_ready)?;
+            let found = match self.object.kind {
+                ObjectKind::ConfigMap => {
+                    data.data.contains_key(key) || data.binary_data.contains_key(key)
+                }
+                ObjectKind::Secret => data.data.contains_key(key),
+            };
+            if !found {
+                return Err(CheckError::not_ready(format!("key {} not found", key)));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for ObjectCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in namespace {}", self.object, self.namespace)
+    }
+}
+
+// === impl LeaseCheck ===
+
+impl LeaseCheck {
+    /// Creates a check that waits for the lease to be free.
+    pub fn new(client: Arc<k8s::Client>, name: String, namespace: String) -> Self {
+        Self {
+            client,
+            name,
+            namespace,
+            acquire: None,
+            observed: Mutex::new(None),
+        }
+    }
+
+    /// Creates a check that acquires the lease, as `holder`, once it is free.
+    pub fn acquire(
+        client: Arc<k8s::Client>,
+        name: String,
+        namespace: String,
+        holder: String,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            acquire: Some(Acquire { holder, duration }),
+            ..Self::new(client, name, namespace)
+        }
+    }
+
+    /// Renews an acquired lease until the returned future is dropped.
+    /// Failures are reported on stderr.
+    pub async fn hold(&self) -> Infallible {
+        let acquire = match self.acquire.as_ref() {
+            Some(acquire) => acquire,
+            None => return futures::future::pending().await,
+        };
+        loop {
+            tokio::time::sleep(acquire.duration / 3).await;
+            match self.renew(acquire).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    eprintln!("Warning: {} was acquired by another holder", self);
+                    return futures::future::pending().await;
+                }
+                Err(error) => eprintln!("Warning: failed to renew {}: {}", self, error),
+            }
+        }
+    }
+
+    /// Releases an acquired lease so that another holder needn't wait for it
+    /// to expire. Failures are reported on stderr.
+    pub async fn release(&self) {
+        let acquire = match self.acquire.as_ref() {
+            Some(acquire) => acquire,
+            None => return,
+        };
+        let released = async {
+            let mut lease = match self.get().await? {
+                Some(lease) if holder(&lease) == Some(acquire.holder.as_str()) => lease,
+                _ => return Ok(()),
+            };
+            lease["spec"]["holderIdentity"] = Value::Null;
+            self.update(&lease).await.map(|_| ())
+        };
+        if let Err(error) = released.await {
+            eprintln!("Warning: failed to release {}: {}", self, error);
+        }
+    }
+
+    /// Returns the holder of a lease if it is held by someone other than `me`
+    /// and has not expired.
+    fn held_by<'l>(&self, lease: &'l Value, me: Option<&str>) -> Option<&'l str> {
+        let holder = holder(lease)?;
+        if Some(holder) == me {
+            return None;
+        }
+        let duration = lease["spec"]["leaseDurationSeconds"]
+            .as_u64()
+            .map(Duration::from_secs)?;
+
+        // The holder renews the lease by changing its spec, so the lease has
+        // expired once its spec has been unchanged for its duration.
+        let spec = lease["spec"].to_string();
+        let now = Instant::now();
+        let mut observed = self.observed.lock().expect("lock must not be poisoned");
+        match observed.as_ref() {
+            Some(observed) if observed.spec == spec => {
+                if observed.at + duration <= now {
+                    return None;
+                }
+            }
+            _ => *observed = Some(Observed { spec, at: now }),
+        }
+        Some(holder)
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/apis/coordination.k8s.io/v1/namespaces/{}/leases/{}",
+            self.namespace, self.name
+        )
+    }
+
+    async fn get(&self) -> Result<Option<Value>, CheckError> {
+        match get(&self.client, &self.path()).await? {
+            Some(body) => serde_json::from_slice(&body)
+                .map(Some)
+                .map_err(CheckError::not_ready),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates the lease, held by this process. Returns false if it was
+    /// created concurrently.
+    async fn create(&self, acquire: &Acquire) -> Result<bool, CheckError> {
+        let now = timestamp::format_time(SystemTime::now());
+        let lease = json!({
+            "apiVersion": "coordination.k8s.io/v1",
+            "kind": "Lease",
+            "metadata": {"name": self.name, "namespace": self.namespace},
+            "spec": {
+                "holderIdentity": acquire.holder,
+                "leaseDurationSeconds": acquire.duration.as_secs().max(1),
+                "acquireTime": now,
+                "renewTime": now,
+                "leaseTransitions": 0,
+            },
+        });
+        let path = format!(
+            "/apis/coordination.k8s.io/v1/namespaces/{}/leases",
+            self.namespace
+        );
+        let body = serde_json::to_vec(&lease).expect("lease must serialize");
+        let rsp = request(&self.client, http::Method::POST, &path, body).await?;
+        match rsp.status() {
+            status if status.is_success() => Ok(true),
+            http::StatusCode::CONFLICT => Ok(false),
+            status => Err(CheckError::not_ready(UnexpectedStatus(status))),
+        }
+    }
+
+    /// Replaces the lease. Returns false if it was modified concurrently, as
+    /// detected by its `resourceVersion`.
+    async fn update(&self, lease: &Value) -> Result<bool, CheckError> {
+        let body = serde_json::to_vec(lease).expect("lease must serialize");
+        let rsp = request(&self.client, http::Method::PUT, &self.path(), body).await?;
+        match rsp.status() {
+            status if status.is_success() => Ok(true),
+            http::StatusCode::CONFLICT => Ok(false),
+            status => Err(CheckError::not_ready(UnexpectedStatus(status))),
+        }
+    }
+
+    /// Takes over a free lease. Returns false if it was modified concurrently.
+    async fn take(&self, mut lease: Value, acquire: &Acquire) -> Result<bool, CheckError> {
+        let now = timestamp::format_time(SystemTime::now());
+        let is_holder = holder(&lease) == Some(acquire.holder.as_str());
+        let spec = &mut lease["spec"];
+        if !is_holder {
+            let transitions = spec["leaseTransitions"].as_i64().unwrap_or(0);
+            spec["leaseTransitions"] = json!(transitions + 1);
+            spec["acquireTime"] = json!(now);
+        }
+        spec["holderIdentity"] = json!(acquire.holder);
+        spec["leaseDurationSeconds"] = json!(acquire.duration.as_secs().max(1));
+        spec["renewTime"] = json!(now);
+        self.update(&lease).await
+    }
+
+    /// Extends the lease. Returns false if it's no longer held by this
+    /// process.
+    async fn renew(&self, acquire: &Acquire) -> Result<bool, CheckError> {
+        let mut lease = match self.get().await? {
+            Some(lease) if holder(&lease) == Some(acquire.holder.as_str()) => lease,
+            _ => return Ok(false),
+        };
+        lease["spec"]["renewTime"] = json!(timestamp::format_time(SystemTime::now()));
+        // A conflicting update is retried on the next renewal.
+        self.update(&lease).await.map(|_| true)
+    }
+}
+
+impl Check for LeaseCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let lease = self.get().await?;
+            let acquire = match self.acquire.as_ref() {
+                Some(acquire) => acquire,
+                None => {
+                    return match lease.as_ref().and_then(|l| self.held_by(l, None)) {
+                        Some(holder) => Err(CheckError::not_ready(format!("held by {}", holder))),
+                        None => Ok(()),
+                    }
+                }
+            };
+
+            let acquired = match lease {
+                None => self.create(acquire).await?,
+                Some(lease) => match self.held_by(&lease, Some(&acquire.holder)) {
+                    Some(holder) => {
+                        return Err(CheckError::not_ready(format!("held by {}", holder)))
+                    }
+                    None => self.take(lease, acquire).await?,
+                },
+            };
+            if !acquired {
+                return Err(CheckError::not_ready(
+                    "acquired concurrently by another holder",
+                ));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for LeaseCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lease/{} in namespace {}", self.name, self.namespace)
+    }
+}
+
+/// Returns the holder of a lease.
+fn holder(lease: &Value) -> Option<&str> {
+    lease["spec"]["holderIdentity"]
+        .as_str()
+        .filter(|h| !h.is_empty())
+}
+
+/// Gets an object, returning its JSON representation, or `None` if it
+/// doesn't exist.
+async fn get(client: &k8s::Client, path: &str) -> Result<Option<Vec<u8>>, CheckError> {
+    let rsp = request(client, http::Method::GET, path, Vec::new()).await?;
+    match rsp.status() {
+        status if status.is_success() => Ok(Some(rsp.into_body())),
+        http::StatusCode::NOT_FOUND => Ok(None),
+        status => Err(CheckError::not_ready(UnexpectedStatus(status))),
+    }
+}
+
+/// Gets an object that must exist, returning its JSON representation.
+async fn get_existing(client: &k8s::Client, path: &str) -> Result<Vec<u8>, CheckError> {
+    // The object may not have been created yet.
+    get(client, path)
+        .await?
+        .ok_or_else(|| CheckError::not_ready("not found"))
+}
+
+/// Sends a request, failing if the API server can't be used.
+async fn request(
+    client: &k8s::Client,
+    method: http::Method,
+    path: &str,
+    body: Vec<u8>,
+) -> Result<http::Response<Vec<u8>>, CheckError> {
+    let rsp = match client.request(method, path, body).await {
+        Ok(rsp) => rsp,
+        Err(e) if e.is_connect() => return Err(CheckError::connect(e)),
+        Err(e @ k8s::Error::Token(_)) => return Err(CheckError::unrecoverable(e)),
+        Err(e) => return Err(CheckError::not_ready(e)),
+    };
+    match rsp.status() {
+        // The service account isn't allowed to access the object.
+        status @ (http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN) => {
+            Err(CheckError::unrecoverable(UnexpectedStatus(status)))
+        }
+        _ => Ok(rsp),
+    }
+}
+
+fn deployment_ready(deploy: &Object) -> Result<(), CheckError> {
+    let Object {
+        metadata,
+        spec,
+        status,
+    } = deploy;
+    if status.observed_generation < metadata.generation {
+        return Err(CheckError::not_ready(
+            "waiting for the spec update to be observed",
+        ));
+    }
+    if let Some(c) = status.conditions.iter().find(|c| c.kind == "Progressing") {
+        if c.reason == "ProgressDeadlineExceeded" {
+            return Err(CheckError::not_ready(
+                "rollout exceeded its progress deadline",
+            ));
+        }
+    }
+    if status.updated_replicas < spec.replicas {
+        return Err(CheckError::not_ready(format!(
+            "{} of {} replicas have been updated",
+            status.updated_replicas, spec.replicas
+        )));
+    }
+    if status.replicas > status.updated_replicas {
+        return Err(CheckError::not_ready(format!(
+            "{} old replicas are pending termination",
+            status.replicas - status.updated_replicas
+        )));
+    }
+    if status.available_replicas < status.updated_replicas {
+        return Err(CheckError::not_ready(format!(
+            "{} of {} updated replicas are available",
+            status.available_replicas, status.updated_replicas
+        )));
+    }
+    Ok(())
+}
+
+fn statefulset_ready(sts: &Object) -> Result<(), CheckError> {
+    let Object {
+        metadata,
+        spec,
+        status,
+    } = sts;
+    if status.observed_generation < metadata.generation {
+        return Err(CheckError::not_ready(
+            "waiting for the spec update to be observed",
+        ));
+    }
+    if status.ready_replicas < spec.replicas {
+        return Err(CheckError::not_ready(format!(
+            "{} of {} replicas are ready",
+            status.ready_replicas, spec.replicas
+        )));
+    }
+    // Pods aren't updated automatically with the OnDelete strategy.
+    if spec.update_strategy.kind == "OnDelete" {
+        return Ok(());
+    }
+    let partition = spec.update_strategy.rolling_update.partition;
+    if partition > 0 {
+        let expected = spec.replicas - partition;
+        if status.updated_replicas < expected {
+            return Err(CheckError::not_ready(format!(
+                "{} of {} replicas above the partition have been updated",
+                status.updated_replicas, expected
+            )));
+        }
+    } else if status.update_revision != status.current_revision {
+        return Err(CheckError::not_ready(format!(
+            "{} of {} replicas have been updated",
+            status.updated_replicas, spec.replicas
+        )));
+    }
+    Ok(())
+}
+
+fn pod_ready(status: &Status) -> Result<(), CheckError> {
+    // A pod that has terminated will never become ready.
+    if status.phase == "Succeeded" || status.phase == "Failed" {
+        return Err(CheckError::unrecoverable(format!(
+            "pod has terminated ({})",
+            status.phase
+        )));
+    }
+    let ready = status
+        .conditions
+        .iter()
+        .any(|c| c.kind == "Ready" && c.status == "True");
+    if !ready {
+        return Err(CheckError::not_ready("pod is not ready"));
+    }
+    Ok(())
+}
+
+// === impl Spec ===
+
+impl Default for Spec {
+    fn default() -> Self {
+        // Workloads are created with one replica unless otherwise specified.
+        Self {
+            replicas: 1,
+            update_strategy: UpdateStrategy::default(),
+        }
+    }
+}
+
+// === impl UnexpectedStatus ===
+
+impl fmt::Display for UnexpectedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Kubernetes API server responded with {}", self.0)
+    }
+}
+
+impl std::error::Error for UnexpectedStatus {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        time,
+    };
+
+    fn object(json: &str) -> Object {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_deployment_ready() {
+        let ready = r#"{
+            "metadata": {"generation": 2},
+            "spec": {"replicas": 2},
+            "status": {"observedGeneration": 2, "replicas": 2, "updatedReplicas": 2, "availableReplicas": 2}
+        }"#;
+        assert!(deployment_ready(&object(ready)).is_ok());
+
+        for not_ready in [
+            r#"{"metadata": {"generation": 2}, "status": {"observedGeneration": 1}}"#,
+            r#"{"spec": {"replicas": 2}, "status": {"replicas": 2, "updatedReplicas": 1, "availableReplicas": 2}}"#,
+            r#"{"status": {"replicas": 2, "updatedReplicas": 1, "availableReplicas": 1}}"#,
+            r#"{"status": {"replicas": 1, "updatedReplicas": 1, "availableReplicas": 0}}"#,
+        ] {
+            assert!(
+                matches!(
+                    deployment_ready(&object(not_ready)),
+                    Err(CheckError::NotReady(_))
+                ),
+                "{}",
+                not_ready
+            );
+        }
+    }
+
+    #[test]
+    fn test_statefulset_ready() {
+        let ready = r#"{
+            "spec": {"replicas": 3},
+            "status": {"readyReplicas": 3, "updatedReplicas": 3, "currentRevision": "a", "updateRevision": "a"}
+        }"#;
+        assert!(statefulset_ready(&object(ready)).is_ok());
+
+        let updating = r#"{
+            "spec": {"replicas": 3},
+            "status": {"readyReplicas": 3, "updatedReplicas": 1, "currentRevision": "a", "updateRevision": "b"}
+        }"#;
+        assert!(statefulset_ready(&object(updating)).is_err());
+
+        let partitioned = r#"{
+            "spec": {"replicas": 3, "updateStrategy": {"type": "RollingUpdate", "rollingUpdate": {"partition": 2}}},
+            "status": {"readyReplicas": 3, "updatedReplicas": 1, "currentRevision": "a", "updateRevision": "b"}
+        }"#;
+        assert!(statefulset_ready(&object(partitioned)).is_ok());
+
+        let unready = r#"{"spec": {"replicas": 3}, "status": {"readyReplicas": 2}}"#;
+        assert!(statefulset_ready(&object(unready)).is_err());
+    }
+
+    #[test]
+    fn test_pod_ready() {
+        let status = |json: &str| object(json).status;
+        assert!(pod_ready(&status(
+            r#"{"status": {"phase": "Running", "conditions": [{"type": "Ready", "status": "True"}]}}"#
+        ))
+        .is_ok());
+        assert!(matches!(
+            pod_ready(&status(
+                r#"{"status": {"phase": "Running", "conditions": [{"type": "Ready", "status": "False"}]}}"#
+            )),
+            Err(CheckError::NotReady(_))
+        ));
+        assert!(matches!(
+            pod_ready(&status(r#"{"status": {"phase": "Failed"}}"#)),
+            Err(CheckError::Unrecoverable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_workload_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let token =
+            std::env::temp_dir().join(format!("linkerd-await-token-{}", std::process::id()));
+        std::fs::write(&token, "s3cr3t\n").unwrap();
+
+        let requests = tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut requests = Vec::new();
+            for (status, body) in [
+                ("404 Not Found", "{}"),
+                ("200 OK", r#"{"status": {"phase": "Running"}}"#),
+                (
+                    "200 OK",
+                    r#"{"status": {"conditions": [{"type": "Ready", "status": "True"}]}}"#,
+                ),
+                ("403 Forbidden", "{}"),
+            ] {
+                let mut req = vec![0; 1024];
+                let n = io.read(&mut req).await.unwrap();
+                requests.push(String::from_utf8(req[..n].to_vec()).unwrap());
+                let rsp = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let client = k8s::Client::new(
+            crate::proxy::Connector::new(false),
+            server.parse().unwrap(),
+            Some(token.clone()),
+        );
+        let check = WorkloadCheck::new(
+            Arc::new(client),
+            "pod/web-0".parse().unwrap(),
+            "ns".to_string(),
+        );
+        assert_eq!(check.to_string(), "pod/web-0 in namespace ns");
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(check.check().await.is_ok());
+        assert!(matches!(
+            check.check().await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+        std::fs::remove_file(token).unwrap();
+
+        let requests = requests.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v1/namespaces/ns/pods/web-0 HTTP/1.1\r\n"),
+            "{}",
+            requests[0]
+        );
+        assert!(
+            requests[0].contains("\r\nauthorization: Bearer s3cr3t\r\n"),
+            "{}",
+            requests[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_object_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            for (status, body) in [
+                ("404 Not Found", "{}"),
+                ("200 OK", r#"{"data": {"ca.crt": "Y2E="}}"#),
+                (
+                    "200 OK",
+                    r#"{"data": {"ca.crt": "Y2E=", "tls.crt": "Y2VydA=="}}"#,
+                ),
+            ] {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let rsp = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = k8s::Client::new(
+            crate::proxy::Connector::new(false),
+            server.parse().unwrap(),
+            None,
+        );
+        let check = ObjectCheck::new(
+            Arc::new(client),
+            "secret/my-cert:tls.crt".parse().unwrap(),
+            "ns".to_string(),
+        );
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        assert!(check.check().await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_held_by() {
+        let client = k8s::Client::new(
+            crate::proxy::Connector::new(false),
+            "http://127.0.0.1:1".parse().unwrap(),
+            None,
+        );
+        let check = LeaseCheck::new(Arc::new(client), "lock".to_string(), "ns".to_string());
+        let lease = |holder: &str, renewed: &str| {
+            json!({"spec": {
+                "holderIdentity": holder,
+                "leaseDurationSeconds": 15,
+                "renewTime": renewed,
+            }})
+        };
+        // Lease timestamps are written by the holder's clock, so they don't
+        // determine expiry.
+        let stale = lease("a", "2024-05-01T12:00:00.000000Z");
+        assert_eq!(check.held_by(&stale, None), Some("a"));
+        assert_eq!(check.held_by(&stale, Some("a")), None);
+        assert_eq!(
+            check.held_by(&lease("", "2024-05-01T12:00:00.000000Z"), None),
+            None
+        );
+        assert_eq!(check.held_by(&json!({"spec": {}}), None), None);
+
+        time::advance(Duration::from_secs(10)).await;
+        assert_eq!(check.held_by(&stale, None), Some("a"));
+        // Renewing the lease restarts its duration.
+        let renewed = lease("a", "2024-05-01T12:00:10.000000Z");
+        assert_eq!(check.held_by(&renewed, None), Some("a"));
+        time::advance(Duration::from_secs(10)).await;
+        assert_eq!(check.held_by(&renewed, None), Some("a"));
+        time::advance(Duration::from_secs(5)).await;
+        assert_eq!(check.held_by(&renewed, None), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_check_acquire() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let held = json!({
+            "metadata": {"name": "lock", "resourceVersion": "7"},
+            "spec": {
+                "holderIdentity": "other",
+                "leaseDurationSeconds": 15,
+                "renewTime": "2024-05-01T12:00:00.000000Z",
+                "leaseTransitions": 2,
+            },
+        })
+        .to_string();
+        let expired = json!({
+            "metadata": {"name": "lock", "resourceVersion": "8"},
+            "spec": {
+                "holderIdentity": "other",
+                "leaseDurationSeconds": 15,
+                "renewTime": "2024-05-01T12:00:10.000000Z",
+                "leaseTransitions": 2,
+            },
+        })
+        .to_string();
+        let requests = tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut requests = Vec::new();
+            for (status, body) in [
+                ("404 Not Found", "{}"),
+                ("409 Conflict", "{}"),
+                ("200 OK", held.as_str()),
+                ("200 OK", expired.as_str()),
+                ("200 OK", expired.as_str()),
+                ("200 OK", "{}"),
+            ] {
+                let mut req = vec![0; 4096];
+                let n = io.read(&mut req).await.unwrap();
+                requests.push(String::from_utf8(req[..n].to_vec()).unwrap());
+                let rsp = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let client = k8s::Client::new(
+            crate::proxy::Connector::new(false),
+            server.parse().unwrap(),
+            None,
+        );
+        let check = LeaseCheck::acquire(
+            Arc::new(client),
+            "lock".to_string(),
+            "ns".to_string(),
+            "me".to_string(),
+            Duration::from_secs(30),
+        );
+        // Created concurrently.
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        // Held by another process.
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        // Renewed by its holder.
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+        // Not renewed within its duration, so it's taken over.
+        time::advance(Duration::from_secs(15)).await;
+        assert!(check.check().await.is_ok());
+
+        let requests = requests.await.unwrap();
+        assert!(requests[1]
+            .starts_with("POST /apis/coordination.k8s.io/v1/namespaces/ns/leases HTTP/1.1\r\n"));
+        let put = &requests[5];
+        assert!(put.starts_with(
+            "PUT /apis/coordination.k8s.io/v1/namespaces/ns/leases/lock HTTP/1.1\r\n"
+        ));
+        let (_, body) = put.split_once("\r\n\r\n").unwrap();
+        let lease: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(lease["metadata"]["resourceVersion"], "8");
+        assert_eq!(lease["spec"]["holderIdentity"], "me");
+        assert_eq!(lease["spec"]["leaseDurationSeconds"], 30);
+        assert_eq!(lease["spec"]["leaseTransitions"], 3);
+    }
+}
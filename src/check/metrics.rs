@@ -0,0 +1,619 @@
+use super::{http::UnexpectedStatus, Check, CheckError, CheckFuture};
+use crate::{client, proxy};
+use std::{
+    error, fmt,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Checks that a Linkerd proxy is at least a minimum version, according to
+/// the `version` label of its `proxy_build_info` metric.
+///
+/// A proxy that is too old is reported as unrecoverable, since retrying
+/// won't upgrade it. Once the version is verified, it isn't checked again.
+#[derive(Debug)]
+pub struct ProxyVersionCheck {
+    uri: http::Uri,
+    client: client::Persistent,
+    min: ProxyVersion,
+    headers: http::HeaderMap,
+    verified: AtomicBool,
+}
+
+/// Checks that a proxy's metrics satisfy conditions, e.g. that it has
+/// received responses from the control plane. The metrics are fetched once
+/// per attempt, for all of the conditions.
+#[derive(Debug)]
+pub struct MetricCheck {
+    uri: http::Uri,
+    client: client::Persistent,
+    conditions: Vec<MetricCondition>,
+    headers: http::HeaderMap,
+}
+
+/// A condition on a metric, e.g. `name{label="value"} >= 1`, that holds when
+/// any sample of the metric with (at least) the given labels satisfies the
+/// comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricCondition {
+    name: String,
+    labels: Vec<(String, String)>,
+    op: Comparison,
+    value: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidMetricCondition(String);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Comparison {
+    Ge,
+    Le,
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+}
+
+/// A proxy version, e.g. `2.14.10`. A leading `v` or release channel, as in
+/// `v2.224.0` or `stable-2.14.10`, is ignored, as is a pre-release suffix.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProxyVersion([u64; 3]);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidProxyVersion(String);
+
+/// A sample in the Prometheus text exposition format, e.g.
+/// `proxy_build_info{version="v2.224.0"} 1`.
+#[derive(Clone, Debug, PartialEq)]
+struct Sample<'a> {
+    name: &'a str,
+    labels: Vec<(&'a str, String)>,
+    value: f64,
+}
+
+#[derive(Debug)]
+struct OldProxy {
+    version: ProxyVersion,
+    min: ProxyVersion,
+}
+
+#[derive(Debug)]
+struct UnknownVersion;
+
+#[derive(Debug)]
+struct Unmet(MetricCondition);
+
+const BUILD_INFO: &str = "proxy_build_info";
+
+// === impl ProxyVersionCheck ===
+
+impl ProxyVersionCheck {
+    /// Creates a check of the admin server's metrics endpoint, e.g.
+    /// `http://localhost:4191/metrics`.
+    pub fn new(connector: proxy::Connector, uri: http::Uri, min: ProxyVersion) -> Self {
+        Self {
+            client: client::Persistent::new(connector, uri.clone()),
+            uri,
+            min,
+            headers: http::HeaderMap::new(),
+            verified: AtomicBool::new(false),
+        }
+    }
+
+    /// Sends the headers with each request.
+    pub fn with_headers(self, headers: http::HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+}
+
+impl Check for ProxyVersionCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            if self.verified.load(Ordering::Acquire) {
+                return Ok(());
+            }
+            let body = fetch(&self.client, &self.uri, &self.headers).await?;
+            let version = samples(&body)
+                .find(|sample| sample.name == BUILD_INFO)
+                .and_then(|sample| sample.label("version").map(ProxyVersion::from_str))
+                .ok_or_else(|| CheckError::unrecoverable(UnknownVersion))?
+                .map_err(|_| CheckError::unrecoverable(UnknownVersion))?;
+            if version < self.min {
+                return Err(CheckError::unrecoverable(OldProxy {
+                    version,
+                    min: self.min,
+                }));
+            }
+            self.verified.store(true, Ordering::Release);
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for ProxyVersionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proxy version >= {} {}", self.min, self.uri)
+    }
+}
+
+// === impl MetricCheck ===
+
+impl MetricCheck {
+    /// Creates a check of the admin server's metrics endpoint, e.g.
+    /// `http://localhost:4191/metrics`.
+    pub fn new(
+        connector: proxy::Connector,
+        uri: http::Uri,
+        conditions: Vec<MetricCondition>,
+    ) -> Self {
+        Self {
+            client: client::Persistent::new(connector, uri.clone()),
+            uri,
+            conditions,
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Sends the headers with each request.
+    pub fn with_headers(self, headers: http::HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+}
+
+impl Check for MetricCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let body = fetch(&self.client, &self.uri, &self.headers).await?;
+            match self
+                .conditions
+                .iter()
+                .find(|condition| !samples(&body).any(|sample| condition.matches(&sample)))
+            {
+                Some(condition) => Err(CheckError::not_ready(Unmet(condition.clone()))),
+                None => Ok(()),
+            }
+        })
+    }
+}
+
+impl fmt::Display for MetricCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GET {}", self.uri)
+    }
+}
+
+// === impl MetricCondition ===
+
+impl MetricCondition {
+    fn matches(&self, sample: &Sample<'_>) -> bool {
+        sample.name == self.name
+            && self
+                .labels
+                .iter()
+                .all(|(key, value)| sample.label(key) == Some(value.as_str()))
+            && self.op.compare(sample.value, self.value)
+    }
+}
+
+impl FromStr for MetricCondition {
+    type Err = InvalidMetricCondition;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidMetricCondition(s.to_string());
+        let condition = s.trim();
+        let name_end = condition
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(condition.len());
+        let (name, rest) = condition.split_at(name_end);
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let (labels, rest) = match rest.strip_prefix('{') {
+            Some(rest) => parse_labels(rest).ok_or_else(invalid)?,
+            None => (Vec::new(), rest),
+        };
+        let rest = rest.trim_start();
+        let (op, value) = [
+            (">=", Comparison::Ge),
+            ("<=", Comparison::Le),
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            (">", Comparison::Gt),
+            ("<", Comparison::Lt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| rest.strip_prefix(token).map(|value| (op, value)))
+        .ok_or_else(invalid)?;
+        let value = f64::from_str(value.trim()).map_err(|_| invalid())?;
+        Ok(Self {
+            name: name.to_string(),
+            labels: labels
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            op,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for MetricCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.labels.is_empty() {
+            let labels = self
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}={:?}", key, value))
+                .collect::<Vec<_>>();
+            write!(f, "{{{}}}", labels.join(","))?;
+        }
+        write!(f, " {} {}", self.op, self.value)
+    }
+}
+
+impl fmt::Display for InvalidMetricCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid condition {:?}; expected NAME[{{LABEL=\"VALUE\",...}}] OP VALUE, e.g. 'name{{label=\"value\"}} >= 1'",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidMetricCondition {}
+
+// === impl Comparison ===
+
+impl Comparison {
+    fn compare(self, a: f64, b: f64) -> bool {
+        match self {
+            Self::Ge => a >= b,
+            Self::Le => a <= b,
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+            Self::Gt => a > b,
+            Self::Lt => a < b,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Self::Ge => ">=",
+            Self::Le => "<=",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Gt => ">",
+            Self::Lt => "<",
+        };
+        f.write_str(op)
+    }
+}
+
+/// Fetches the metrics.
+async fn fetch(
+    client: &client::Persistent,
+    uri: &http::Uri,
+    headers: &http::HeaderMap,
+) -> Result<String, CheckError> {
+    let mut req = http::Request::get(uri.clone())
+        .body(Vec::new())
+        .map_err(CheckError::unrecoverable)?;
+    *req.headers_mut() = headers.clone();
+    match client.fetch(req).await {
+        Ok(rsp) if rsp.status().is_success() => {
+            Ok(String::from_utf8_lossy(rsp.body()).into_owned())
+        }
+        Ok(rsp) => Err(CheckError::not_ready(UnexpectedStatus(rsp.status()))),
+        Err(e) if e.is_connect() => Err(CheckError::connect(e)),
+        Err(e) => Err(CheckError::not_ready(e)),
+    }
+}
+
+/// Parses the samples in metrics, skipping comments and malformed lines.
+fn samples(metrics: &str) -> impl Iterator<Item = Sample<'_>> {
+    metrics
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(Sample::parse)
+}
+
+// === impl ProxyVersion ===
+
+impl FromStr for ProxyVersion {
+    type Err = InvalidProxyVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidProxyVersion(s.to_string());
+        let version = s.trim().trim_start_matches(|c: char| !c.is_ascii_digit());
+        let end = version
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(version.len());
+        let parts = version[..end]
+            .split('.')
+            .map(u64::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match parts[..] {
+            [major] => Ok(Self([major, 0, 0])),
+            [major, minor] => Ok(Self([major, minor, 0])),
+            [major, minor, patch] => Ok(Self([major, minor, patch])),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ProxyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [major, minor, patch] = self.0;
+        write!(f, "{}.{}.{}", major, minor, patch)
+    }
+}
+
+impl fmt::Display for InvalidProxyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid version {:?}; expected X.Y.Z", self.0)
+    }
+}
+
+impl error::Error for InvalidProxyVersion {}
+
+// === impl Sample ===
+
+impl<'a> Sample<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim();
+        let name_end = line.find(['{', ' '])?;
+        let (name, rest) = line.split_at(name_end);
+        let (labels, rest) = match rest.strip_prefix('{') {
+            Some(rest) => parse_labels(rest)?,
+            None => (Vec::new(), rest),
+        };
+        // A timestamp may follow the value.
+        let value = rest.split_whitespace().next()?;
+        let value = match value {
+            "+Inf" => f64::INFINITY,
+            "-Inf" => f64::NEG_INFINITY,
+            value => f64::from_str(value).ok()?,
+        };
+        Some(Self {
+            name,
+            labels,
+            value,
+        })
+    }
+
+    fn label(&self, key: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses the labels following a `{`, up to and including the closing `}`,
+/// returning them with the rest of the input.
+fn parse_labels(mut s: &str) -> Option<(Vec<(&str, String)>, &str)> {
+    let mut labels = Vec::new();
+    loop {
+        s = s.trim_start_matches([',', ' ']);
+        if let Some(rest) = s.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+        let (key, rest) = s.split_once("=\"")?;
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((key.trim(), value));
+        s = &rest[end + 1..];
+    }
+}
+
+// === impl OldProxy ===
+
+impl fmt::Display for OldProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "proxy version {} is older than the minimum version {}",
+            self.version, self.min
+        )
+    }
+}
+
+impl error::Error for OldProxy {}
+
+// === impl Unmet ===
+
+impl fmt::Display for Unmet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not met", self.0)
+    }
+}
+
+impl error::Error for Unmet {}
+
+// === impl UnknownVersion ===
+
+impl fmt::Display for UnknownVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proxy_build_info does not report a version")
+    }
+}
+
+impl error::Error for UnknownVersion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_parse_proxy_version() {
+        let version = |s: &str| s.parse::<ProxyVersion>().map(|v| v.0);
+        assert_eq!(version("2.14.10"), Ok([2, 14, 10]));
+        assert_eq!(version("v2.224.0"), Ok([2, 224, 0]));
+        assert_eq!(version("stable-2.14"), Ok([2, 14, 0]));
+        assert_eq!(version("v2.224.0-rc.1"), Ok([2, 224, 0]));
+        assert!(version("main").is_err());
+        assert!(version("1.2.3.4").is_err());
+        assert!(version("2..1").is_err());
+        assert!(version("2.9.1").unwrap() < version("2.14.0").unwrap());
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        let sample = Sample::parse(
+            r#"proxy_build_info{git_sha="abc",version="v2.224.0",vendor="li\"nkerd"} 1 1700000000"#,
+        )
+        .unwrap();
+        assert_eq!(sample.name, "proxy_build_info");
+        assert_eq!(sample.label("version"), Some("v2.224.0"));
+        assert_eq!(sample.label("vendor"), Some("li\"nkerd"));
+        assert_eq!(sample.value, 1.0);
+
+        let sample = Sample::parse("process_start_time_seconds 1.7e9").unwrap();
+        assert!(sample.labels.is_empty());
+        assert_eq!(sample.value, 1.7e9);
+
+        assert_eq!(Sample::parse("process_start_time_seconds"), None);
+        assert_eq!(Sample::parse(r#"x{a="b} 1"#), None);
+    }
+
+    #[test]
+    fn test_metric_condition() {
+        let condition =
+            r#"control_response_total{direction="inbound", classification="success"} >= 1"#
+                .parse::<MetricCondition>()
+                .unwrap();
+        assert_eq!(
+            condition.to_string(),
+            r#"control_response_total{direction="inbound",classification="success"} >= 1"#
+        );
+        for (sample, matches) in [
+            (
+                r#"control_response_total{classification="success",direction="inbound",addr="x"} 3"#,
+                true,
+            ),
+            (
+                r#"control_response_total{classification="success",direction="inbound"} 0"#,
+                false,
+            ),
+            (
+                r#"control_response_total{classification="failure",direction="inbound"} 3"#,
+                false,
+            ),
+            (r#"control_response_total{direction="inbound"} 3"#, false),
+        ] {
+            assert_eq!(
+                condition.matches(&Sample::parse(sample).unwrap()),
+                matches,
+                "{}",
+                sample
+            );
+        }
+
+        let condition = "process_cpu_seconds_total<2.5"
+            .parse::<MetricCondition>()
+            .unwrap();
+        assert!(condition.matches(&Sample::parse("process_cpu_seconds_total 1").unwrap()));
+
+        for invalid in [
+            "",
+            "name",
+            "name >",
+            "name => 1",
+            "name ~ 1",
+            r#"name{label="value} > 1"#,
+            "1name > 1",
+        ] {
+            assert!(invalid.parse::<MetricCondition>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metric_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            for count in [0, 2] {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let body = format!(
+                    "# TYPE identity_cert_refresh_count counter\nidentity_cert_refresh_count {}\n",
+                    count
+                );
+                let rsp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let conditions = vec!["identity_cert_refresh_count > 0".parse().unwrap()];
+        let check = MetricCheck::new(
+            proxy::Connector::new(false),
+            uri.parse().unwrap(),
+            conditions,
+        );
+        match check.check().await {
+            Err(CheckError::NotReady(e)) => {
+                assert_eq!(e.to_string(), "identity_cert_refresh_count > 0 is not met")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(check.check().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_version_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for version in ["v2.224.0", "v2.200.3"] {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let body = format!("proxy_build_info{{version=\"{}\"}} 1\n", version);
+                let rsp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let min = "2.210.0".parse().unwrap();
+        let check = ProxyVersionCheck::new(proxy::Connector::new(false), uri.parse().unwrap(), min);
+        assert!(check.check().await.is_ok());
+        // Once verified, the proxy isn't requested again.
+        assert!(check.check().await.is_ok());
+
+        let check = ProxyVersionCheck::new(proxy::Connector::new(false), uri.parse().unwrap(), min);
+        match check.check().await {
+            Err(CheckError::Unrecoverable(e)) => assert_eq!(
+                e.to_string(),
+                "proxy version 2.200.3 is older than the minimum version 2.210.0"
+            ),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
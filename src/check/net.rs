@@ -0,0 +1,311 @@
+//! Checks that the pod's networking is configured, for clusters where the CNI
+//! plugin attaches the network after containers start.
+//!
+//! Interfaces are inspected with `getifaddrs(3)` and routes are read from the
+//! kernel's routing tables in `/proc/net`, so route checks are Linux-only.
+
+use super::{Check, CheckError, CheckFuture};
+use nix::{ifaddrs, net::if_::InterfaceFlags};
+use std::{
+    error, fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// Checks that a network interface is up and has an address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceCheck {
+    name: String,
+}
+
+/// Checks that the kernel has a route for a destination.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteCheck {
+    route: Route,
+}
+
+/// A destination that must be routable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Route {
+    /// A default route, for either address family.
+    Default,
+    /// Any route that covers the address.
+    To(IpAddr),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRoute;
+
+/// An entry in one of the kernel's routing tables.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    dst: IpAddr,
+    prefix_len: u32,
+}
+
+const IPV4_ROUTES: &str = "/proc/net/route";
+const IPV6_ROUTES: &str = "/proc/net/ipv6_route";
+
+const RTF_UP: u32 = 0x0001;
+const RTF_REJECT: u32 = 0x0200;
+
+// === impl InterfaceCheck ===
+
+impl InterfaceCheck {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Check for InterfaceCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let addrs = ifaddrs::getifaddrs()
+                .map_err(|e| CheckError::unrecoverable(io::Error::from(e)))?
+                .filter(|a| a.interface_name == self.name)
+                .collect::<Vec<_>>();
+
+            let flags = addrs
+                .first()
+                .map(|a| a.flags)
+                .ok_or_else(|| CheckError::not_ready("interface does not exist"))?;
+            if !flags.contains(InterfaceFlags::IFF_UP | InterfaceFlags::IFF_RUNNING) {
+                return Err(CheckError::not_ready("interface is down"));
+            }
+
+            // IPv6 link-local addresses are assigned as soon as the link is
+            // up, so they don't indicate that the interface is configured.
+            let configured = addrs.iter().filter_map(|a| a.address.as_ref()).any(|a| {
+                if a.as_sockaddr_in().is_some() {
+                    return true;
+                }
+                a.as_sockaddr_in6()
+                    .map(|a| a.ip().segments()[0] & 0xffc0 != 0xfe80)
+                    .unwrap_or(false)
+            });
+            if !configured {
+                return Err(CheckError::not_ready("interface has no address"));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for InterfaceCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interface {}", self.name)
+    }
+}
+
+// === impl RouteCheck ===
+
+impl RouteCheck {
+    pub fn new(route: Route) -> Self {
+        Self { route }
+    }
+
+    fn is_routable(&self, entries: &[Entry]) -> bool {
+        entries.iter().any(|e| match self.route {
+            Route::Default => e.prefix_len == 0,
+            Route::To(ip) => e.covers(ip),
+        })
+    }
+}
+
+impl Check for RouteCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let mut entries = Vec::new();
+            for (path, parse) in [
+                (IPV4_ROUTES, parse_ipv4_routes as fn(&str) -> Vec<Entry>),
+                (IPV6_ROUTES, parse_ipv6_routes),
+            ] {
+                match std::fs::read_to_string(path) {
+                    Ok(table) => entries.extend(parse(&table)),
+                    // IPv6 may be disabled.
+                    Err(e) if e.kind() == io::ErrorKind::NotFound && path == IPV6_ROUTES => {}
+                    Err(e) => {
+                        return Err(CheckError::unrecoverable(format!(
+                            "failed to read {}: {}",
+                            path, e
+                        )))
+                    }
+                }
+            }
+
+            if !self.is_routable(&entries) {
+                return Err(CheckError::not_ready("no route"));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for RouteCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.route {
+            Route::Default => write!(f, "default route"),
+            Route::To(ip) => write!(f, "route to {}", ip),
+        }
+    }
+}
+
+// === impl Route ===
+
+impl FromStr for Route {
+    type Err = InvalidRoute;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("default") {
+            return Ok(Self::Default);
+        }
+        s.parse().map(Self::To).map_err(|_| InvalidRoute)
+    }
+}
+
+impl fmt::Display for InvalidRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid route; expected `default` or an IP address")
+    }
+}
+
+impl error::Error for InvalidRoute {}
+
+// === impl Entry ===
+
+impl Entry {
+    fn covers(&self, ip: IpAddr) -> bool {
+        match (self.dst, ip) {
+            (IpAddr::V4(dst), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(dst) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(dst), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(dst) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parses `/proc/net/route`, whose addresses are hex-encoded in host byte
+/// order.
+fn parse_ipv4_routes(table: &str) -> Vec<Entry> {
+    let addr = |s: &str| u32::from_str_radix(s, 16).ok().map(u32::to_ne_bytes);
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let dst = addr(fields.get(1)?)?;
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let mask = addr(fields.get(7)?)?;
+            is_usable(flags).then(|| Entry {
+                dst: Ipv4Addr::from(dst).into(),
+                prefix_len: u32::from_ne_bytes(mask).count_ones(),
+            })
+        })
+        .collect()
+}
+
+/// Parses `/proc/net/ipv6_route`, whose addresses are hex-encoded in network
+/// byte order.
+fn parse_ipv6_routes(table: &str) -> Vec<Entry> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let dst = u128::from_str_radix(fields.first()?, 16).ok()?;
+            let prefix_len = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+            let flags = u32::from_str_radix(fields.get(8)?, 16).ok()?;
+            is_usable(flags).then(|| Entry {
+                dst: Ipv6Addr::from(dst).into(),
+                prefix_len,
+            })
+        })
+        .collect()
+}
+
+/// Unreachable routes are installed as rejecting routes, which don't make a
+/// destination routable.
+fn is_usable(flags: u32) -> bool {
+    flags & RTF_UP != 0 && flags & RTF_REJECT == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4: &str = "\
+Iface	Destination	Gateway 	Flags	RefCnt	Use	Metric	Mask		MTU	Window	IRTT
+eth0	00000000	010200C0	0003	0	0	0	00000000	0	0	0
+eth0	000200C0	00000000	0001	0	0	0	00FFFFFF	0	0	0
+";
+
+    const IPV6: &str = "\
+fd000000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo
+";
+
+    #[test]
+    fn test_parse_routes() {
+        if cfg!(target_endian = "little") {
+            assert_eq!(
+                parse_ipv4_routes(IPV4),
+                vec![
+                    Entry {
+                        dst: Ipv4Addr::UNSPECIFIED.into(),
+                        prefix_len: 0
+                    },
+                    Entry {
+                        dst: Ipv4Addr::new(192, 0, 2, 0).into(),
+                        prefix_len: 24
+                    },
+                ]
+            );
+        }
+        assert_eq!(
+            parse_ipv6_routes(IPV6),
+            vec![Entry {
+                dst: "fd00::".parse().unwrap(),
+                prefix_len: 64
+            }]
+        );
+    }
+
+    #[test]
+    fn test_route_check() {
+        let ipv6 = parse_ipv6_routes(IPV6);
+        assert!(!RouteCheck::new(Route::Default).is_routable(&ipv6));
+        assert!(RouteCheck::new("fd00::1".parse().unwrap()).is_routable(&ipv6));
+        assert!(!RouteCheck::new("fd01::1".parse().unwrap()).is_routable(&ipv6));
+        assert!(!RouteCheck::new("192.0.2.1".parse().unwrap()).is_routable(&ipv6));
+
+        let ipv4 = vec![Entry {
+            dst: Ipv4Addr::new(192, 0, 2, 0).into(),
+            prefix_len: 24,
+        }];
+        assert!(RouteCheck::new("192.0.2.1".parse().unwrap()).is_routable(&ipv4));
+        assert!(!RouteCheck::new("192.0.3.1".parse().unwrap()).is_routable(&ipv4));
+        assert!(!RouteCheck::new(Route::Default).is_routable(&ipv4));
+    }
+
+    #[test]
+    fn test_parse_route() {
+        assert_eq!("default".parse(), Ok(Route::Default));
+        assert_eq!(
+            "10.0.0.1".parse(),
+            Ok(Route::To(Ipv4Addr::new(10, 0, 0, 1).into()))
+        );
+        assert_eq!("10.0.0.0/8".parse::<Route>(), Err(InvalidRoute));
+    }
+
+    #[tokio::test]
+    async fn test_interface_check() {
+        assert!(matches!(
+            InterfaceCheck::new("linkerd-await0").check().await,
+            Err(CheckError::NotReady(_))
+        ));
+    }
+}
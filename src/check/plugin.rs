@@ -0,0 +1,130 @@
+//! Readiness checks implemented by external executables.
+//!
+//! A plugin is run once per attempt, with no arguments, and its exit code
+//! determines the outcome:
+//!
+//! - `0`: the check succeeded;
+//! - `2`: the check can never succeed and is not retried;
+//! - any other code (or termination by a signal): the check is retried.
+//!
+//! A plugin may print a JSON object such as `{"message": "..."}` on stdout to
+//! explain why a check failed. The message is reported when the check can't
+//! succeed. Other fields are ignored so that the contract can grow. The admin
+//! server's port is passed to the plugin in the `LINKERD_AWAIT_PORT`
+//! environment variable.
+
+use super::{Check, CheckError, CheckFuture};
+use std::{fmt, path::PathBuf, process::Stdio};
+use tokio::process::Command;
+
+/// Exit code that indicates that the check can never succeed.
+const EXIT_UNRECOVERABLE: i32 = 2;
+
+/// Checks readiness by running an external plugin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginCheck {
+    path: PathBuf,
+    port: u16,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct Output {
+    message: Option<String>,
+}
+
+// === impl PluginCheck ===
+
+impl PluginCheck {
+    pub fn new(path: impl Into<PathBuf>, port: u16) -> Self {
+        Self {
+            path: path.into(),
+            port,
+        }
+    }
+}
+
+impl Check for PluginCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let output = Command::new(&self.path)
+                .env("LINKERD_AWAIT_PORT", self.port.to_string())
+                .stdin(Stdio::null())
+                .stderr(Stdio::inherit())
+                .kill_on_drop(true)
+                .output()
+                .await
+                // A plugin that can't be run won't become runnable later.
+                .map_err(CheckError::unrecoverable)?;
+
+            if output.status.success() {
+                return Ok(());
+            }
+            let message = Output::parse(&output.stdout)
+                .message
+                .unwrap_or_else(|| format!("plugin failed: {}", output.status));
+            match output.status.code() {
+                Some(EXIT_UNRECOVERABLE) => Err(CheckError::unrecoverable(message)),
+                _ => Err(CheckError::not_ready(message)),
+            }
+        })
+    }
+}
+
+impl fmt::Display for PluginCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "plugin {}", self.path.display())
+    }
+}
+
+// === impl Output ===
+
+impl Output {
+    /// Parses the plugin's stdout, ignoring output that isn't a JSON object.
+    fn parse(stdout: &[u8]) -> Self {
+        serde_json::from_slice(stdout).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(name: &str, script: &str) -> PluginCheck {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!(
+            "linkerd-await-plugin-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        PluginCheck::new(path, 4191)
+    }
+
+    #[tokio::test]
+    async fn test_plugin_check() {
+        let ok = plugin("ok", r#"test "$LINKERD_AWAIT_PORT" = 4191"#);
+        assert!(ok.check().await.is_ok());
+
+        let retry = plugin("retry", r#"echo '{"message":"still warming up"}'; exit 1"#);
+        match retry.check().await {
+            Err(CheckError::NotReady(e)) => assert_eq!(e.to_string(), "still warming up"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let fatal = plugin("fatal", "echo not json; exit 2");
+        match fatal.check().await {
+            Err(CheckError::Unrecoverable(e)) => {
+                assert_eq!(e.to_string(), "plugin failed: exit status: 2")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let missing = PluginCheck::new("/nonexistent/linkerd-await-plugin", 4191);
+        assert!(matches!(
+            missing.check().await,
+            Err(CheckError::Unrecoverable(_))
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+use super::{Check, CheckError, CheckFuture};
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Checks that nothing is listening on a local TCP port, so that the command
+/// can bind it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortFreeCheck {
+    port: u16,
+}
+
+// === impl PortFreeCheck ===
+
+impl PortFreeCheck {
+    pub fn new(port: u16) -> Self {
+        Self { port }
+    }
+
+    /// Binds the port on the unspecified address, as a server would. An
+    /// address family that isn't available can't be in use.
+    async fn bind(&self, ip: impl Into<std::net::IpAddr>) -> Result<(), CheckError> {
+        match TcpListener::bind(SocketAddr::new(ip.into(), self.port)).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                Err(CheckError::not_ready("port is in use"))
+            }
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => self.connect().await,
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Privileged ports may not be bindable by linkerd-await even though the
+    /// command can bind them, so a listener is detected by connecting instead.
+    async fn connect(&self) -> Result<(), CheckError> {
+        match TcpStream::connect((Ipv4Addr::LOCALHOST, self.port)).await {
+            Ok(_) => Err(CheckError::not_ready("port is in use")),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Check for PortFreeCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            self.bind(Ipv4Addr::UNSPECIFIED).await?;
+            self.bind(Ipv6Addr::UNSPECIFIED).await
+        })
+    }
+}
+
+impl fmt::Display for PortFreeCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "port {}", self.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_port_free_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let check = PortFreeCheck::new(listener.local_addr().unwrap().port());
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        drop(listener);
+        assert!(check.check().await.is_ok());
+    }
+}
@@ -0,0 +1,53 @@
+use super::{Check, CheckError, CheckFuture};
+use std::fmt;
+use tokio::net::TcpStream;
+
+/// Checks that a TCP server accepts connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcpCheck {
+    addr: String,
+}
+
+// === impl TcpCheck ===
+
+impl TcpCheck {
+    /// Creates a check for a `host:port` address.
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into() }
+    }
+}
+
+impl Check for TcpCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            TcpStream::connect(&self.addr)
+                .await
+                .map(drop)
+                .map_err(CheckError::connect)
+        })
+    }
+}
+
+impl fmt::Display for TcpCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tcp://{}", self.addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tcp_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(TcpCheck::new(addr.to_string()).check().await.is_ok());
+
+        drop(listener);
+        assert!(matches!(
+            TcpCheck::new(addr.to_string()).check().await,
+            Err(CheckError::Connect(_))
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+use super::{Check, CheckError, CheckFuture};
+use std::{fmt, io, path::PathBuf};
+
+/// Checks that a Unix domain socket accepts connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnixSocketCheck {
+    path: PathBuf,
+}
+
+// === impl UnixSocketCheck ===
+
+impl UnixSocketCheck {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Check for UnixSocketCheck {
+    #[cfg(unix)]
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            match tokio::net::UnixStream::connect(&self.path).await {
+                Ok(_) => Ok(()),
+                // The socket is created once its server starts listening.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    Err(CheckError::connect("socket does not exist"))
+                }
+                Err(e) => Err(CheckError::connect(e)),
+            }
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            Err(CheckError::unrecoverable(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix domain sockets are not supported on this platform",
+            )))
+        })
+    }
+}
+
+impl fmt::Display for UnixSocketCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unix://{}", self.path.display())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unix_socket_check() {
+        let path =
+            std::env::temp_dir().join(format!("linkerd-await-test-{}.sock", std::process::id()));
+        let check = UnixSocketCheck::new(&path);
+        assert!(matches!(check.check().await, Err(CheckError::Connect(_))));
+
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        assert!(check.check().await.is_ok());
+
+        // A socket whose server has exited refuses connections.
+        drop(listener);
+        assert!(matches!(check.check().await, Err(CheckError::Connect(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
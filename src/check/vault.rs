@@ -0,0 +1,83 @@
+use super::{Check, CheckError, CheckFuture};
+use std::{fmt, io, path::PathBuf};
+
+/// Checks that a Vault Agent's token sink holds a token, so that the agent
+/// has authenticated and can render secrets.
+///
+/// A sink holds either a token or, when the agent wraps tokens, a JSON
+/// response-wrapping object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultTokenCheck {
+    path: PathBuf,
+}
+
+// === impl VaultTokenCheck ===
+
+impl VaultTokenCheck {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Check for VaultTokenCheck {
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let contents = match std::fs::read_to_string(&self.path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    return Err(CheckError::not_ready("token sink does not exist"))
+                }
+                Err(e) => return Err(CheckError::not_ready(e)),
+            };
+            if is_token(contents.trim()) {
+                Ok(())
+            } else {
+                Err(CheckError::not_ready("token sink does not hold a token"))
+            }
+        })
+    }
+}
+
+impl fmt::Display for VaultTokenCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vault token {}", self.path.display())
+    }
+}
+
+/// Determines whether a sink's contents are a complete token, e.g.
+/// `hvs.CAESI...`, or a response-wrapping object.
+fn is_token(s: &str) -> bool {
+    if s.starts_with('{') {
+        return s.ends_with('}') && s.contains("\"token\"");
+    }
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_token() {
+        assert!(is_token("hvs.CAESIJ2xP3m"));
+        assert!(is_token("s.Jq9MNmHNAmmXYKqYNh3tEbn8"));
+        assert!(is_token(r#"{"token":"hvs.CAES","accessor":"x","ttl":300}"#));
+        assert!(!is_token(""));
+        assert!(!is_token("hvs.CA ES"));
+        assert!(!is_token(r#"{"token":"hvs."#));
+    }
+
+    #[tokio::test]
+    async fn test_vault_token_check() {
+        let path = std::env::temp_dir().join(format!("linkerd-await-vault-{}", std::process::id()));
+        let check = VaultTokenCheck::new(&path);
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        std::fs::write(&path, b"\n").unwrap();
+        assert!(matches!(check.check().await, Err(CheckError::NotReady(_))));
+
+        std::fs::write(&path, b"hvs.CAESIJ2xP3m\n").unwrap();
+        assert!(check.check().await.is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
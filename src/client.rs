@@ -0,0 +1,851 @@
+//! A minimal HTTP/1.1 client.
+//!
+//! linkerd-await only issues a handful of requests, mostly to the local
+//! proxy's admin server, and only cares about the response status. One-off
+//! requests use a new connection that is closed once the response has been
+//! read, while readiness checks reuse a [`Connection`] across attempts via
+//! [`Persistent`].
+//!
+//! With the `tls` feature, `https` URIs may be requested as well. Requests
+//! may also be sent over a Unix domain socket configured on the
+//! [`proxy::Connector`], and cleartext requests may use HTTP/2 instead.
+
+#[cfg(feature = "runtime")]
+mod h2;
+
+#[cfg(feature = "runtime")]
+pub use self::h2::{Http2Error, Reason};
+#[cfg(feature = "runtime")]
+use crate::proxy;
+use std::{error, fmt, io};
+#[cfg(feature = "runtime")]
+use std::{
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+/// Bounds the size of a response's status line.
+pub(crate) const MAX_STATUS_LINE: usize = 1024;
+
+/// Bounds the total size of a response's headers.
+#[cfg(feature = "runtime")]
+const MAX_HEADERS: usize = 16 * 1024;
+
+/// Bounds the size of a response body that is read into memory.
+#[cfg(feature = "runtime")]
+pub const MAX_BODY: usize = 1024 * 1024;
+
+/// A connection over which several requests may be sent, one at a time.
+#[cfg(feature = "runtime")]
+#[derive(Debug)]
+pub struct Connection {
+    io: tokio::io::BufReader<Io>,
+    is_proxied: bool,
+    is_reusable: bool,
+    is_http2: bool,
+}
+
+/// Sends requests to a server over a connection that is kept alive between
+/// requests.
+#[cfg(feature = "runtime")]
+#[derive(Debug)]
+pub struct Persistent {
+    connector: proxy::Connector,
+    uri: http::Uri,
+    idle: Mutex<Option<Connection>>,
+}
+
+/// A connection's transport.
+#[cfg(feature = "runtime")]
+#[derive(Debug)]
+enum Io {
+    Plain(tokio::net::TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::client::TlsStream<tokio::net::TcpStream>>),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+/// Describes why a request failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The URI can't be requested, e.g. because it doesn't use `http`.
+    InvalidUri(http::Uri),
+
+    /// A connection could not be established.
+    Connect(io::Error),
+
+    /// The connection failed after it was established.
+    Io(io::Error),
+
+    /// The server's response could not be parsed.
+    InvalidResponse,
+
+    /// An HTTP/2 request failed.
+    #[cfg(feature = "runtime")]
+    Http2(Http2Error),
+}
+
+/// Sends a request on a new connection and returns the response status.
+#[cfg(feature = "runtime")]
+pub async fn request(
+    connector: &proxy::Connector,
+    req: http::Request<Vec<u8>>,
+) -> Result<http::StatusCode, Error> {
+    let mut conn = connect(connector, req.uri()).await?;
+    conn.is_reusable = false;
+    conn.send(req).await
+}
+
+/// Opens a connection for requests to the given URI's authority.
+#[cfg(feature = "runtime")]
+pub async fn connect(connector: &proxy::Connector, uri: &http::Uri) -> Result<Connection, Error> {
+    if let Some(path) = connector.unix_socket() {
+        return connect_unix(path, connector.is_http2()).await;
+    }
+
+    #[cfg(feature = "tls")]
+    if let (Some(scheme), Some(host)) = (uri.scheme(), uri.host()) {
+        if *scheme == http::uri::Scheme::HTTPS {
+            let (io, _) = connector.connect(uri).await.map_err(Error::Connect)?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let io = connector
+                .tls()
+                .connect(host, io)
+                .await
+                .map_err(Error::Connect)?;
+            return Ok(Connection {
+                io: tokio::io::BufReader::new(Io::Tls(Box::new(io))),
+                is_proxied: false,
+                is_reusable: true,
+                // Only HTTP/1.1 is negotiated.
+                is_http2: false,
+            });
+        }
+    }
+
+    if authority(uri).is_none() {
+        return Err(Error::InvalidUri(uri.clone()));
+    }
+    let (io, is_proxied) = connector.connect(uri).await.map_err(Error::Connect)?;
+    // Proxies are sent HTTP/1.1 requests.
+    let is_http2 = connector.is_http2() && !is_proxied;
+    Ok(Connection {
+        io: tokio::io::BufReader::new(Io::Plain(io)),
+        is_proxied,
+        is_reusable: !is_http2,
+        is_http2,
+    })
+}
+
+#[cfg(all(feature = "runtime", unix))]
+async fn connect_unix(path: &std::path::Path, is_http2: bool) -> Result<Connection, Error> {
+    let io = tokio::net::UnixStream::connect(path).await.map_err(|e| {
+        Error::Connect(io::Error::new(
+            e.kind(),
+            format!("{}: {}", path.display(), e),
+        ))
+    })?;
+    Ok(Connection {
+        io: tokio::io::BufReader::new(Io::Unix(io)),
+        is_proxied: false,
+        is_reusable: !is_http2,
+        is_http2,
+    })
+}
+
+#[cfg(all(feature = "runtime", not(unix)))]
+async fn connect_unix(_: &std::path::Path, _: bool) -> Result<Connection, Error> {
+    Err(Error::Connect(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    )))
+}
+
+/// Returns the authority of an `http` URI.
+pub(crate) fn authority(uri: &http::Uri) -> Option<&http::uri::Authority> {
+    match (uri.scheme(), uri.authority()) {
+        (Some(scheme), Some(authority)) if *scheme == http::uri::Scheme::HTTP => Some(authority),
+        _ => None,
+    }
+}
+
+/// Serializes a request. Requests sent to a proxy use the absolute form of the
+/// URI.
+pub(crate) fn encode(req: &http::Request<Vec<u8>>, is_proxied: bool, keep_alive: bool) -> Vec<u8> {
+    let uri = req.uri();
+    let target = if is_proxied {
+        uri.to_string()
+    } else {
+        uri.path_and_query()
+            .map_or_else(|| "/".to_string(), |pq| pq.to_string())
+    };
+    // A `Host` header, e.g. one required to route through a sidecar,
+    // overrides the URI's authority.
+    let host = req
+        .headers()
+        .get(http::header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_else(|| uri.authority().map_or("", |a| a.as_str()));
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", req.method(), target, host);
+    if !keep_alive {
+        head.push_str("Connection: close\r\n");
+    }
+    for (name, value) in req.headers() {
+        if name == http::header::HOST {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    if !req.body().is_empty() || req.method() == http::Method::POST {
+        head.push_str(&format!("Content-Length: {}\r\n", req.body().len()));
+    }
+    head.push_str("\r\n");
+
+    let mut buf = head.into_bytes();
+    buf.extend_from_slice(req.body());
+    buf
+}
+
+// === impl Connection ===
+
+#[cfg(feature = "runtime")]
+impl Connection {
+    /// Indicates whether another request may be sent on the connection.
+    pub fn is_reusable(&self) -> bool {
+        self.is_reusable
+    }
+
+    /// Sends a request and returns the response status. The response body is
+    /// read and discarded so that the connection may be reused.
+    pub async fn send(&mut self, req: http::Request<Vec<u8>>) -> Result<http::StatusCode, Error> {
+        self.exchange(&req, None).await
+    }
+
+    /// Sends a request and returns the response, including a body of up to
+    /// [`MAX_BODY`] bytes.
+    pub async fn fetch(
+        &mut self,
+        req: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, Error> {
+        let mut body = Vec::new();
+        let status = self.exchange(&req, Some(&mut body)).await?;
+        let mut rsp = http::Response::new(body);
+        *rsp.status_mut() = status;
+        Ok(rsp)
+    }
+
+    /// Sends a request and reads the response, keeping its body if `body` is
+    /// set.
+    async fn exchange(
+        &mut self,
+        req: &http::Request<Vec<u8>>,
+        body: Option<&mut Vec<u8>>,
+    ) -> Result<http::StatusCode, Error> {
+        // HTTP/2 connections carry a single request.
+        if self.is_http2 {
+            self.is_reusable = false;
+            return h2::exchange(&mut self.io, req, body).await;
+        }
+
+        self.write_request(req).await?;
+        self.read_response(req, body).await
+    }
+
+    /// Writes an HTTP/1.1 request.
+    async fn write_request(&mut self, req: &http::Request<Vec<u8>>) -> Result<(), Error> {
+        use tokio::io::AsyncWriteExt;
+
+        let buf = encode(req, self.is_proxied, self.is_reusable);
+        let io = self.io.get_mut();
+        io.write_all(&buf).await.map_err(Error::Io)?;
+        // TLS sessions buffer writes.
+        io.flush().await.map_err(Error::Io)
+    }
+
+    /// Reads the final response to an HTTP/1.1 request, skipping any
+    /// informational (1xx) responses that precede it.
+    async fn read_response(
+        &mut self,
+        req: &http::Request<Vec<u8>>,
+        mut body: Option<&mut Vec<u8>>,
+    ) -> Result<http::StatusCode, Error> {
+        let mut headers_len = 0;
+        let (status, mut keep_alive, content_length, chunked) = loop {
+            let line = self.read_line(MAX_STATUS_LINE).await?;
+            let status = parse_status(line.as_bytes()).ok_or(Error::InvalidResponse)?;
+            // HTTP/1.0 servers close the connection unless asked not to.
+            let mut keep_alive = !line.starts_with("HTTP/1.0 ");
+
+            let mut content_length = None;
+            let mut chunked = false;
+            loop {
+                let line = self.read_line(MAX_HEADERS - headers_len).await?;
+                headers_len += line.len();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').ok_or(Error::InvalidResponse)?;
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length =
+                        Some(value.parse::<u64>().map_err(|_| Error::InvalidResponse)?);
+                } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                    chunked = value.to_ascii_lowercase().ends_with("chunked");
+                } else if name.eq_ignore_ascii_case("connection") {
+                    keep_alive = !value.eq_ignore_ascii_case("close");
+                }
+            }
+
+            // An informational response, e.g. `100 Continue`, is followed by
+            // the final response. A protocol switch ends the exchange.
+            if !status.is_informational() || status == http::StatusCode::SWITCHING_PROTOCOLS {
+                break (status, keep_alive, content_length, chunked);
+            }
+        };
+
+        // Responses to HEAD requests describe a body that isn't sent.
+        let has_body = !(req.method() == http::Method::HEAD
+            || status.is_informational()
+            || status == http::StatusCode::NO_CONTENT
+            || status == http::StatusCode::NOT_MODIFIED);
+        if has_body {
+            match (chunked, content_length) {
+                (true, _) => self.read_chunked(body).await?,
+                (false, Some(len)) => self.read_body(len, body).await?,
+                // The body is delimited by the connection closing.
+                (false, None) => {
+                    keep_alive = false;
+                    if let Some(body) = body.as_mut() {
+                        self.read_to_end(body).await?;
+                    }
+                }
+            }
+        }
+
+        // A switched protocol can't carry further HTTP/1.1 requests.
+        self.is_reusable =
+            self.is_reusable && keep_alive && status != http::StatusCode::SWITCHING_PROTOCOLS;
+        Ok(status)
+    }
+
+    /// Reads a CRLF-terminated line, without its terminator.
+    async fn read_line(&mut self, limit: usize) -> Result<String, Error> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut line = Vec::new();
+        let n = (&mut self.io)
+            .take(limit as u64 + 2)
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(Error::Io)?;
+        if n == 0 || !line.ends_with(b"\r\n") {
+            return Err(Error::InvalidResponse);
+        }
+        line.truncate(line.len() - 2);
+        String::from_utf8(line).map_err(|_| Error::InvalidResponse)
+    }
+
+    /// Reads `len` bytes of the body, discarding them unless `body` is set.
+    async fn read_body(&mut self, len: u64, body: Option<&mut Vec<u8>>) -> Result<(), Error> {
+        use tokio::io::AsyncReadExt;
+
+        let mut data = (&mut self.io).take(len);
+        let n = match body {
+            Some(body) => {
+                if body.len() as u64 + len > MAX_BODY as u64 {
+                    return Err(Error::InvalidResponse);
+                }
+                data.read_to_end(body).await.map_err(Error::Io)? as u64
+            }
+            None => tokio::io::copy(&mut data, &mut tokio::io::sink())
+                .await
+                .map_err(Error::Io)?,
+        };
+        if n < len {
+            return Err(Error::InvalidResponse);
+        }
+        Ok(())
+    }
+
+    async fn read_to_end(&mut self, body: &mut Vec<u8>) -> Result<(), Error> {
+        use tokio::io::AsyncReadExt;
+
+        let limit = (MAX_BODY - body.len()) as u64;
+        let n = (&mut self.io)
+            .take(limit + 1)
+            .read_to_end(body)
+            .await
+            .map_err(Error::Io)?;
+        if n as u64 > limit {
+            return Err(Error::InvalidResponse);
+        }
+        Ok(())
+    }
+
+    async fn read_chunked(&mut self, mut body: Option<&mut Vec<u8>>) -> Result<(), Error> {
+        loop {
+            let line = self.read_line(MAX_STATUS_LINE).await?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = u64::from_str_radix(size, 16).map_err(|_| Error::InvalidResponse)?;
+            if size == 0 {
+                // Skip any trailers.
+                while !self.read_line(MAX_HEADERS).await?.is_empty() {}
+                return Ok(());
+            }
+            self.read_body(size, body.as_deref_mut()).await?;
+            if !self.read_line(0).await?.is_empty() {
+                return Err(Error::InvalidResponse);
+            }
+        }
+    }
+}
+
+// === impl Persistent ===
+
+#[cfg(feature = "runtime")]
+impl Persistent {
+    /// Creates a client for the server identified by the URI's scheme and
+    /// authority.
+    pub fn new(connector: proxy::Connector, uri: http::Uri) -> Self {
+        Self {
+            connector,
+            uri,
+            idle: Mutex::default(),
+        }
+    }
+
+    /// Sends a request and returns the response status, as
+    /// [`Connection::send`] does.
+    pub async fn send(&self, req: http::Request<Vec<u8>>) -> Result<http::StatusCode, Error> {
+        self.exchange(&req, None).await
+    }
+
+    /// Sends a request and returns the response, as [`Connection::fetch`]
+    /// does.
+    pub async fn fetch(
+        &self,
+        req: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, Error> {
+        let mut body = Vec::new();
+        let status = self.exchange(&req, Some(&mut body)).await?;
+        let mut rsp = http::Response::new(body);
+        *rsp.status_mut() = status;
+        Ok(rsp)
+    }
+
+    /// Sends a request, reusing the previous request's connection if
+    /// possible.
+    async fn exchange(
+        &self,
+        req: &http::Request<Vec<u8>>,
+        mut body: Option<&mut Vec<u8>>,
+    ) -> Result<http::StatusCode, Error> {
+        // The connection is taken for the duration of the request so that a
+        // request that is canceled, e.g. by a timeout, doesn't leave a
+        // half-read response behind.
+        let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let (status, conn) = match idle {
+            // Only HTTP/1.1 connections are reused.
+            Some(mut conn) => match conn.write_request(req).await {
+                Ok(()) => match conn.read_response(req, body.as_deref_mut()).await {
+                    Ok(status) => (status, conn),
+                    // The request may have reached the server, so it's only
+                    // sent again if doing so can't repeat its effects.
+                    Err(_) if req.method().is_idempotent() => self.exchange_new(req, body).await?,
+                    Err(error) => return Err(error),
+                },
+                // The server may have closed the idle connection, so retry on
+                // a new one.
+                Err(_) => self.exchange_new(req, body).await?,
+            },
+            None => self.exchange_new(req, body).await?,
+        };
+        if conn.is_reusable() {
+            *self.idle.lock().unwrap_or_else(|e| e.into_inner()) = Some(conn);
+        }
+        Ok(status)
+    }
+
+    /// Sends a request on a new connection, discarding any part of the body
+    /// that was read from a previous one.
+    async fn exchange_new(
+        &self,
+        req: &http::Request<Vec<u8>>,
+        mut body: Option<&mut Vec<u8>>,
+    ) -> Result<(http::StatusCode, Connection), Error> {
+        if let Some(body) = body.as_mut() {
+            body.clear();
+        }
+        let mut conn = connect(&self.connector, &self.uri).await?;
+        let status = conn.exchange(req, body).await?;
+        Ok((status, conn))
+    }
+}
+
+// === impl Io ===
+
+#[cfg(feature = "runtime")]
+impl tokio::io::AsyncRead for Io {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(io) => Pin::new(io).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(io) => Pin::new(io).poll_read(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(io) => Pin::new(io).poll_read(cx, buf),
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl tokio::io::AsyncWrite for Io {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(io) => Pin::new(io).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(io) => Pin::new(io).poll_write(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(io) => Pin::new(io).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(io) => Pin::new(io).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(io) => Pin::new(io).poll_flush(cx),
+            #[cfg(unix)]
+            Self::Unix(io) => Pin::new(io).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(io) => Pin::new(io).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(io) => Pin::new(io).poll_shutdown(cx),
+            #[cfg(unix)]
+            Self::Unix(io) => Pin::new(io).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Parses a status line like `HTTP/1.1 200 OK`.
+pub(crate) fn parse_status(line: &[u8]) -> Option<http::StatusCode> {
+    let line = std::str::from_utf8(line).ok()?;
+    let mut parts = line.splitn(3, ' ');
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    http::StatusCode::from_bytes(parts.next()?.as_bytes()).ok()
+}
+
+// === impl Error ===
+
+impl Error {
+    /// Indicates whether the request failed because the server could not be
+    /// reached.
+    pub fn is_connect(&self) -> bool {
+        matches!(self, Self::Connect(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUri(uri) => write!(f, "unsupported URI: {}", uri),
+            Self::Connect(e) => write!(f, "failed to connect: {}", e),
+            Self::Io(e) => write!(f, "connection failed: {}", e),
+            Self::InvalidResponse => write!(f, "invalid HTTP response"),
+            #[cfg(feature = "runtime")]
+            Self::Http2(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Connect(e) | Self::Io(e) => Some(e),
+            Self::InvalidUri(_) | Self::InvalidResponse => None,
+            #[cfg(feature = "runtime")]
+            Self::Http2(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status(b"HTTP/1.1 200 OK"), Some(http::StatusCode::OK));
+        assert_eq!(
+            parse_status(b"HTTP/1.0 503 Service Unavailable"),
+            Some(http::StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert_eq!(
+            parse_status(b"HTTP/1.1 404"),
+            Some(http::StatusCode::NOT_FOUND)
+        );
+        assert_eq!(parse_status(b"HTTP/2 200"), None);
+        assert_eq!(parse_status(b"SSH-2.0-OpenSSH"), None);
+    }
+
+    #[test]
+    fn test_encode_headers() {
+        let req = http::Request::get("http://127.0.0.1:4191/ready")
+            .header(http::header::HOST, "linkerd-admin")
+            .header(http::header::AUTHORIZATION, "Bearer token")
+            .body(Vec::new())
+            .unwrap();
+        let head = String::from_utf8(encode(&req, false, true)).unwrap();
+        assert_eq!(
+            head,
+            "GET /ready HTTP/1.1\r\nHost: linkerd-admin\r\nauthorization: Bearer token\r\n\r\n"
+        );
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_request() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/shutdown", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut req = vec![0; 1024];
+            let n = io.read(&mut req).await.unwrap();
+            io.write_all(b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(req[..n].to_vec()).unwrap()
+        });
+
+        let req = http::Request::post(uri).body(Vec::new()).unwrap();
+        let status = request(&proxy::Connector::new(false), req).await.unwrap();
+        assert_eq!(status, http::StatusCode::ACCEPTED);
+
+        let req = server.await.unwrap();
+        assert!(req.starts_with("POST /shutdown HTTP/1.1\r\n"), "{}", req);
+        assert!(req.contains("\r\nContent-Length: 0\r\n"), "{}", req);
+        assert!(req.ends_with("\r\n\r\n"), "{}", req);
+    }
+
+    #[cfg(all(feature = "runtime", unix))]
+    #[tokio::test]
+    async fn test_request_unix_socket() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::UnixListener,
+        };
+
+        let path = std::env::temp_dir().join(format!("linkerd-await-uds-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut req = vec![0; 1024];
+            let n = io.read(&mut req).await.unwrap();
+            io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(req[..n].to_vec()).unwrap()
+        });
+
+        let connector = proxy::Connector::new(false).with_unix_socket(&path);
+        let req = http::Request::get("http://localhost:4191/ready")
+            .body(Vec::new())
+            .unwrap();
+        let status = request(&connector, req).await.unwrap();
+        assert_eq!(status, http::StatusCode::OK);
+        let req = server.await.unwrap();
+        assert!(
+            req.starts_with("GET /ready HTTP/1.1\r\nHost: localhost:4191\r\n"),
+            "{}",
+            req
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let req = http::Request::get("http://localhost:4191/ready")
+            .body(Vec::new())
+            .unwrap();
+        let error = request(&connector, req).await.unwrap_err();
+        assert!(error.is_connect(), "{}", error);
+        assert!(
+            error.to_string().contains(&path.display().to_string()),
+            "{}",
+            error
+        );
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_request_connect_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        drop(listener);
+
+        let req = http::Request::get(uri).body(Vec::new()).unwrap();
+        let error = request(&proxy::Connector::new(false), req)
+            .await
+            .unwrap_err();
+        assert!(error.is_connect(), "{}", error);
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_request_tls_handshake_error() {
+        use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("https://{}/ready", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let _ = io
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await;
+        });
+
+        let req = http::Request::get(uri).body(Vec::new()).unwrap();
+        let error = request(&proxy::Connector::new(false), req)
+            .await
+            .unwrap_err();
+        assert!(error.is_connect(), "{}", error);
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_connection_reuse() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: http::Uri = format!("http://{}/ready", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            for rsp in [
+                "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 503 Service Unavailable\r\ncontent-length: 9\r\n\r\nnot ready",
+                "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n5\r\nready\r\n0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+            ] {
+                let mut req = vec![0; 1024];
+                let n = io.read(&mut req).await.unwrap();
+                assert!(!String::from_utf8_lossy(&req[..n]).contains("Connection: close"));
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let get = || http::Request::get(uri.clone()).body(Vec::new()).unwrap();
+        let mut conn = connect(&proxy::Connector::new(false), &uri).await.unwrap();
+        assert_eq!(
+            conn.send(get()).await.unwrap(),
+            http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert!(conn.is_reusable());
+        assert_eq!(conn.send(get()).await.unwrap(), http::StatusCode::OK);
+        assert!(conn.is_reusable());
+        assert_eq!(conn.send(get()).await.unwrap(), http::StatusCode::OK);
+        assert!(!conn.is_reusable());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_persistent_fetch() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: http::Uri = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            for rsp in [
+                "HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello",
+                "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n3\r\nwor\r\n2\r\nld\r\n0\r\n\r\n",
+                "HTTP/1.1 404 Not Found\r\nconnection: close\r\n\r\nmissing",
+            ] {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = Persistent::new(proxy::Connector::new(false), uri.clone());
+        let get = || http::Request::get(uri.clone()).body(Vec::new()).unwrap();
+        let rsp = client.fetch(get()).await.unwrap();
+        assert_eq!(rsp.body(), b"hello");
+        let rsp = client.fetch(get()).await.unwrap();
+        assert_eq!(rsp.body(), b"world");
+        let rsp = client.fetch(get()).await.unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(rsp.body(), b"missing");
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_persistent_replay() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: http::Uri = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        // Each connection serves one response and then closes once it reads
+        // the next request, as a server that times out idle connections may.
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let mut req = vec![0; 1024];
+                let n = io.read(&mut req).await.unwrap();
+                tx.send(String::from_utf8_lossy(&req[..n]).into_owned())
+                    .unwrap();
+                io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+                let _ = io.read(&mut req).await;
+            }
+        });
+
+        let client = Persistent::new(proxy::Connector::new(false), uri.clone());
+        let get = || http::Request::get(uri.clone()).body(Vec::new()).unwrap();
+        let post = || http::Request::post(uri.clone()).body(Vec::new()).unwrap();
+
+        // An idempotent request is sent again on a new connection.
+        assert_eq!(client.send(get()).await.unwrap(), http::StatusCode::OK);
+        assert_eq!(client.send(get()).await.unwrap(), http::StatusCode::OK);
+        assert!(rx.recv().await.unwrap().starts_with("GET "));
+        assert!(rx.recv().await.unwrap().starts_with("GET "));
+
+        // A request that may have had an effect is not.
+        assert!(client.send(post()).await.is_err());
+        assert!(rx.try_recv().is_err());
+    }
+}
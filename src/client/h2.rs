@@ -0,0 +1,682 @@
+//! A minimal HTTP/2 client for servers that accept cleartext HTTP/2 with prior
+//! knowledge (h2c).
+//!
+//! Each connection carries a single request, on stream 1, and is then
+//! discarded, so no stream or flow-control state outlives a request. The
+//! client advertises a header table size of 0, so the server can't refer to
+//! headers it sent earlier and only the response's `:status` needs to be
+//! decoded (RFC 7541). Other response headers are ignored.
+
+use super::{Error, MAX_BODY};
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Describes how the server failed an HTTP/2 request.
+#[derive(Debug)]
+pub enum Http2Error {
+    /// The server's response didn't begin with HTTP/2 settings, e.g.
+    /// because it responded with HTTP/1.x, so it doesn't accept HTTP/2 with
+    /// prior knowledge.
+    Unsupported,
+
+    /// The server reset the request's stream.
+    Reset(Reason),
+
+    /// The server closed the connection before responding.
+    GoAway(Reason),
+
+    /// The server's frames could not be parsed.
+    Protocol(&'static str),
+}
+
+/// An HTTP/2 error code, e.g. from a `RST_STREAM` or `GOAWAY` frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Reason(u32);
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+const ACK: u8 = 0x1;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+
+/// The largest frame that either side may send without negotiating a larger
+/// size.
+const MAX_FRAME_SIZE: usize = 16_384;
+
+/// The request's stream.
+const STREAM: u32 = 1;
+
+/// The statuses in HPACK's static table, starting at index 8.
+const STATIC_STATUSES: [u16; 7] = [200, 204, 206, 304, 400, 404, 500];
+
+/// Headers that are specific to HTTP/1.x connections and are not allowed in
+/// HTTP/2.
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+struct Frame {
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: Vec<u8>,
+}
+
+/// Sends a request over a new connection and reads the response, keeping its
+/// body if `body` is set. Without a body, the response is abandoned once its
+/// status is known.
+pub(super) async fn exchange<T>(
+    io: &mut T,
+    req: &http::Request<Vec<u8>>,
+    mut body: Option<&mut Vec<u8>>,
+) -> Result<http::StatusCode, Error>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    io.write_all(&encode(req)).await.map_err(Error::Io)?;
+    io.flush().await.map_err(Error::Io)?;
+
+    // The server's connection preface is a settings frame.
+    let frame = read_frame(io).await.map_err(|e| match e {
+        Error::Http2(Http2Error::Protocol(_)) => Error::Http2(Http2Error::Unsupported),
+        e => e,
+    })?;
+    if frame.kind != SETTINGS || frame.stream != 0 || frame.flags & ACK != 0 {
+        return Err(Error::Http2(Http2Error::Unsupported));
+    }
+    write_frame(io, SETTINGS, ACK, 0, &[]).await?;
+
+    let mut status = None;
+    let mut block = Vec::new();
+    let mut end_stream = false;
+    loop {
+        let frame = read_frame(io).await?;
+        match (frame.kind, frame.stream) {
+            (SETTINGS, 0) if frame.flags & ACK == 0 => {
+                write_frame(io, SETTINGS, ACK, 0, &[]).await?;
+            }
+            (PING, 0) if frame.flags & ACK == 0 => {
+                write_frame(io, PING, ACK, 0, &frame.payload).await?;
+            }
+            (GOAWAY, 0) => {
+                let (last_stream, code) = goaway(&frame.payload)?;
+                // A graceful shutdown still completes streams the server has
+                // accepted.
+                if code != Reason::NO_ERROR || last_stream < STREAM {
+                    return Err(Error::Http2(Http2Error::GoAway(code)));
+                }
+            }
+            (RST_STREAM, STREAM) => {
+                let code = code(&frame.payload)?;
+                return Err(Error::Http2(Http2Error::Reset(code)));
+            }
+            (HEADERS, STREAM) | (CONTINUATION, STREAM) => {
+                if frame.kind == HEADERS {
+                    end_stream = frame.flags & END_STREAM != 0;
+                    block.extend_from_slice(headers_fragment(&frame)?);
+                } else {
+                    block.extend_from_slice(&frame.payload);
+                }
+                if frame.flags & END_HEADERS == 0 {
+                    continue;
+                }
+                // Later header blocks are trailers.
+                if status.is_none() {
+                    let s = decode_status(&block).ok_or(Error::Http2(Http2Error::Protocol(
+                        "response has a malformed :status",
+                    )))?;
+                    if !s.is_informational() {
+                        if body.is_none() {
+                            return Ok(s);
+                        }
+                        status = Some(s);
+                    }
+                }
+                block.clear();
+                if end_stream {
+                    return status.ok_or(Error::InvalidResponse);
+                }
+            }
+            (DATA, STREAM) => {
+                if status.is_none() {
+                    return Err(Error::Http2(Http2Error::Protocol(
+                        "response data preceded its headers",
+                    )));
+                }
+                let data = unpad(&frame)?;
+                if let Some(body) = body.as_mut() {
+                    if body.len() + data.len() > MAX_BODY {
+                        return Err(Error::InvalidResponse);
+                    }
+                    body.extend_from_slice(data);
+                }
+                if frame.flags & END_STREAM != 0 {
+                    return status.ok_or(Error::InvalidResponse);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Serializes the connection preface and the request on stream 1.
+fn encode(req: &http::Request<Vec<u8>>) -> Vec<u8> {
+    let mut buf = PREFACE.to_vec();
+
+    let mut settings = Vec::new();
+    for (id, value) in [
+        (SETTINGS_HEADER_TABLE_SIZE, 0),
+        (SETTINGS_ENABLE_PUSH, 0),
+        (SETTINGS_INITIAL_WINDOW_SIZE, MAX_BODY as u32),
+    ] {
+        settings.extend_from_slice(&id.to_be_bytes());
+        settings.extend_from_slice(&value.to_be_bytes());
+    }
+    push_frame(&mut buf, SETTINGS, 0, 0, &settings);
+    // The connection's window is not affected by the initial window size.
+    push_frame(
+        &mut buf,
+        WINDOW_UPDATE,
+        0,
+        0,
+        &(MAX_BODY as u32).to_be_bytes(),
+    );
+
+    let block = encode_headers(req);
+    let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+    let mut kind = HEADERS;
+    let mut flags = if req.body().is_empty() { END_STREAM } else { 0 };
+    while let Some(chunk) = chunks.next() {
+        if chunks.peek().is_none() {
+            flags |= END_HEADERS;
+        }
+        push_frame(&mut buf, kind, flags, STREAM, chunk);
+        kind = CONTINUATION;
+        flags = 0;
+    }
+
+    let mut chunks = req.body().chunks(MAX_FRAME_SIZE).peekable();
+    while let Some(chunk) = chunks.next() {
+        let flags = if chunks.peek().is_none() {
+            END_STREAM
+        } else {
+            0
+        };
+        push_frame(&mut buf, DATA, flags, STREAM, chunk);
+    }
+
+    buf
+}
+
+/// Encodes the request's headers without adding any to the server's header
+/// table.
+fn encode_headers(req: &http::Request<Vec<u8>>) -> Vec<u8> {
+    let uri = req.uri();
+    let mut block = Vec::new();
+
+    match *req.method() {
+        http::Method::GET => block.push(0x82),
+        http::Method::POST => block.push(0x83),
+        ref method => push_literal(&mut block, 2, method.as_str().as_bytes()),
+    }
+    if uri.scheme() == Some(&http::uri::Scheme::HTTPS) {
+        block.push(0x87);
+    } else {
+        block.push(0x86);
+    }
+    // As with HTTP/1.1, a `Host` header overrides the URI's authority.
+    let authority = req
+        .headers()
+        .get(http::header::HOST)
+        .map(|h| h.as_bytes())
+        .or_else(|| uri.authority().map(|a| a.as_str().as_bytes()))
+        .unwrap_or_default();
+    push_literal(&mut block, 1, authority);
+    let path = uri.path_and_query().map_or("/", |pq| pq.as_str());
+    push_literal(&mut block, 4, path.as_bytes());
+
+    for (name, value) in req.headers() {
+        if name == http::header::HOST || CONNECTION_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        block.push(0x00);
+        push_string(&mut block, name.as_str().as_bytes());
+        push_string(&mut block, value.as_bytes());
+    }
+    block
+}
+
+/// Encodes a header whose name is in the static table as a literal that is
+/// not indexed.
+fn push_literal(block: &mut Vec<u8>, name: usize, value: &[u8]) {
+    push_int(block, 0x00, 4, name);
+    push_string(block, value);
+}
+
+/// Encodes a string without Huffman coding.
+fn push_string(block: &mut Vec<u8>, s: &[u8]) {
+    push_int(block, 0x00, 7, s.len());
+    block.extend_from_slice(s);
+}
+
+/// Encodes an integer with an N-bit prefix, sharing its first byte with
+/// `flags` (RFC 7541, section 5.1).
+fn push_int(block: &mut Vec<u8>, flags: u8, prefix: u32, value: usize) {
+    let max = (1 << prefix) - 1;
+    if value < max {
+        block.push(flags | value as u8);
+        return;
+    }
+    block.push(flags | max as u8);
+    let mut value = value - max;
+    while value >= 0x80 {
+        block.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    block.push(value as u8);
+}
+
+fn push_frame(buf: &mut Vec<u8>, kind: u8, flags: u8, stream: u32, payload: &[u8]) {
+    buf.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    buf.push(kind);
+    buf.push(flags);
+    buf.extend_from_slice(&stream.to_be_bytes());
+    buf.extend_from_slice(payload);
+}
+
+async fn write_frame<T: AsyncWrite + Unpin>(
+    io: &mut T,
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: &[u8],
+) -> Result<(), Error> {
+    let mut buf = Vec::with_capacity(9 + payload.len());
+    push_frame(&mut buf, kind, flags, stream, payload);
+    io.write_all(&buf).await.map_err(Error::Io)?;
+    io.flush().await.map_err(Error::Io)
+}
+
+async fn read_frame<T: AsyncRead + Unpin>(io: &mut T) -> Result<Frame, Error> {
+    let mut head = [0; 9];
+    io.read_exact(&mut head).await.map_err(Error::Io)?;
+    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(Error::Http2(Http2Error::Protocol(
+            "frame exceeds the maximum size",
+        )));
+    }
+    let mut payload = vec![0; len];
+    io.read_exact(&mut payload).await.map_err(Error::Io)?;
+    Ok(Frame {
+        kind: head[3],
+        flags: head[4],
+        stream: u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff,
+        payload,
+    })
+}
+
+/// Returns a `DATA` or `HEADERS` frame's payload without its padding.
+fn unpad(frame: &Frame) -> Result<&[u8], Error> {
+    if frame.flags & PADDED == 0 {
+        return Ok(&frame.payload);
+    }
+    let (&pad, rest) = frame.payload.split_first().ok_or(MALFORMED)?;
+    let len = rest.len().checked_sub(pad as usize).ok_or(MALFORMED)?;
+    Ok(&rest[..len])
+}
+
+/// Returns a `HEADERS` frame's header block fragment.
+fn headers_fragment(frame: &Frame) -> Result<&[u8], Error> {
+    let fragment = unpad(frame)?;
+    if frame.flags & PRIORITY == 0 {
+        return Ok(fragment);
+    }
+    fragment.get(5..).ok_or(MALFORMED)
+}
+
+fn code(payload: &[u8]) -> Result<Reason, Error> {
+    let code = payload.get(..4).ok_or(MALFORMED)?;
+    Ok(Reason(u32::from_be_bytes([
+        code[0], code[1], code[2], code[3],
+    ])))
+}
+
+fn goaway(payload: &[u8]) -> Result<(u32, Reason), Error> {
+    let last = payload.get(..4).ok_or(MALFORMED)?;
+    let last = u32::from_be_bytes([last[0], last[1], last[2], last[3]]) & 0x7fff_ffff;
+    Ok((last, code(&payload[4..])?))
+}
+
+const MALFORMED: Error = Error::Http2(Http2Error::Protocol("malformed frame"));
+
+/// Decodes the `:status` that begins a response's header block.
+fn decode_status(mut block: &[u8]) -> Option<http::StatusCode> {
+    // Skip dynamic table size updates.
+    while block.first()? & 0xe0 == 0x20 {
+        decode_int(&mut block, 5)?;
+    }
+
+    let first = *block.first()?;
+    if first & 0x80 != 0 {
+        let index = decode_int(&mut block, 7)?;
+        let status = *STATIC_STATUSES.get(index.checked_sub(8)?)?;
+        return http::StatusCode::from_u16(status).ok();
+    }
+
+    // A literal, with or without indexing.
+    let prefix = if first & 0x40 != 0 { 6 } else { 4 };
+    match decode_int(&mut block, prefix)? {
+        0 => {
+            if decode_string(&mut block)? != b":status" {
+                return None;
+            }
+        }
+        8..=14 => {}
+        _ => return None,
+    }
+    http::StatusCode::from_bytes(&decode_string(&mut block)?).ok()
+}
+
+/// Decodes an integer with an N-bit prefix (RFC 7541, section 5.1).
+fn decode_int(block: &mut &[u8], prefix: u32) -> Option<usize> {
+    let (&first, rest) = block.split_first()?;
+    *block = rest;
+    let max = (1 << prefix) - 1;
+    let mut value = first as usize & max;
+    if value < max {
+        return Some(value);
+    }
+    let mut shift = 0;
+    loop {
+        let (&b, rest) = block.split_first()?;
+        *block = rest;
+        value = value.checked_add(((b & 0x7f) as usize) << shift)?;
+        if b & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        if shift > 28 {
+            return None;
+        }
+    }
+}
+
+/// Decodes a string literal. Only digits are supported in Huffman-coded
+/// strings, which suffices for a status.
+fn decode_string(block: &mut &[u8]) -> Option<Vec<u8>> {
+    let huffman = block.first()? & 0x80 != 0;
+    let len = decode_int(block, 7)?;
+    let s = block.get(..len)?;
+    *block = &block[len..];
+    if huffman {
+        decode_huffman_digits(s)
+    } else {
+        Some(s.to_vec())
+    }
+}
+
+/// Decodes a Huffman-coded string of digits, which have 5- and 6-bit codes
+/// (RFC 7541, appendix B).
+fn decode_huffman_digits(s: &[u8]) -> Option<Vec<u8>> {
+    let total = s.len() * 8;
+    let bit = |i: usize| (s[i / 8] >> (7 - i % 8)) & 1;
+    let bits = |start: usize, n: usize| (start..start + n).fold(0, |acc, i| acc << 1 | bit(i));
+
+    let mut digits = Vec::new();
+    let mut pos = 0;
+    while pos < total {
+        let rest = total - pos;
+        if rest >= 5 && bits(pos, 5) <= 0x02 {
+            digits.push(b'0' + bits(pos, 5));
+            pos += 5;
+        } else if rest >= 6 && (0x19..=0x1f).contains(&bits(pos, 6)) {
+            digits.push(b'3' + bits(pos, 6) - 0x19);
+            pos += 6;
+        } else if rest < 8 && (pos..total).all(|i| bit(i) == 1) {
+            // Padding with the most significant bits of the EOS code.
+            break;
+        } else {
+            return None;
+        }
+    }
+    Some(digits)
+}
+
+// === impl Http2Error ===
+
+impl fmt::Display for Http2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported => write!(
+                f,
+                "server did not respond with HTTP/2; it may not accept HTTP/2 with prior knowledge"
+            ),
+            Self::Reset(reason) => write!(f, "HTTP/2 stream reset by server: {}", reason),
+            Self::GoAway(reason) => write!(f, "HTTP/2 connection closed by server: {}", reason),
+            Self::Protocol(msg) => write!(f, "HTTP/2 protocol error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Http2Error {}
+
+// === impl Reason ===
+
+impl Reason {
+    const NO_ERROR: Self = Self(0x0);
+
+    /// Returns the code's name, as in RFC 9113, section 7.
+    fn name(&self) -> Option<&'static str> {
+        const NAMES: [&str; 14] = [
+            "NO_ERROR",
+            "PROTOCOL_ERROR",
+            "INTERNAL_ERROR",
+            "FLOW_CONTROL_ERROR",
+            "SETTINGS_TIMEOUT",
+            "STREAM_CLOSED",
+            "FRAME_SIZE_ERROR",
+            "REFUSED_STREAM",
+            "CANCEL",
+            "COMPRESSION_ERROR",
+            "CONNECT_ERROR",
+            "ENHANCE_YOUR_CALM",
+            "INADEQUATE_SECURITY",
+            "HTTP_1_1_REQUIRED",
+        ];
+        NAMES.get(self.0 as usize).copied()
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "unknown error code {:#x}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_decode_status() {
+        let status = |block: &[u8]| decode_status(block).map(|s| s.as_u16());
+        // Indexed.
+        assert_eq!(status(&[0x88]), Some(200));
+        assert_eq!(status(&[0x8d]), Some(404));
+        // A table size update, then a literal with indexing.
+        assert_eq!(status(&[0x20, 0x48, 0x03, b'5', b'0', b'3']), Some(503));
+        // Huffman-coded literals (RFC 7541, appendix C.6).
+        assert_eq!(status(&[0x48, 0x82, 0x64, 0x02]), Some(302));
+        assert_eq!(status(&[0x48, 0x83, 0x64, 0x0e, 0xff]), Some(307));
+        // A literal name.
+        let mut block = vec![0x00, 0x07];
+        block.extend_from_slice(b":status");
+        block.extend_from_slice(&[0x03, b'2', b'0', b'4']);
+        assert_eq!(status(&block), Some(204));
+
+        // `:method: GET`, which isn't a status.
+        assert_eq!(status(&[0x82]), None);
+        // A dynamic table entry.
+        assert_eq!(status(&[0xbe]), None);
+        assert_eq!(status(&[0x48, 0x82, 0x64]), None);
+        assert_eq!(status(&[]), None);
+    }
+
+    #[test]
+    fn test_int_round_trip() {
+        for value in [0, 14, 15, 16, 127, 128, 1337, 1 << 20] {
+            let mut buf = Vec::new();
+            push_int(&mut buf, 0x00, 4, value);
+            let mut block = &buf[..];
+            assert_eq!(decode_int(&mut block, 4), Some(value));
+            assert!(block.is_empty());
+        }
+        // RFC 7541, appendix C.1.2.
+        let mut buf = Vec::new();
+        push_int(&mut buf, 0x00, 5, 1337);
+        assert_eq!(buf, [0x1f, 0x9a, 0x0a]);
+    }
+
+    #[test]
+    fn test_encode_headers() {
+        let req = http::Request::get("http://localhost:4191/ready?verbose")
+            .header("x-route", "admin")
+            .header(http::header::CONNECTION, "close")
+            .body(Vec::new())
+            .unwrap();
+        let mut expected = vec![0x82, 0x86, 0x01, 14];
+        expected.extend_from_slice(b"localhost:4191");
+        expected.extend_from_slice(&[0x04, 14]);
+        expected.extend_from_slice(b"/ready?verbose");
+        expected.extend_from_slice(&[0x00, 7]);
+        expected.extend_from_slice(b"x-route");
+        expected.push(5);
+        expected.extend_from_slice(b"admin");
+        assert_eq!(encode_headers(&req), expected);
+    }
+
+    /// Accepts a connection, reads the client's preface and request, and
+    /// writes the response frames.
+    async fn serve(rsp: Vec<u8>) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut preface = [0; PREFACE.len()];
+            io.read_exact(&mut preface).await.unwrap();
+            assert_eq!(preface, PREFACE);
+            loop {
+                let frame = read_frame(&mut io).await.unwrap();
+                if frame.kind == HEADERS {
+                    assert_eq!(frame.stream, STREAM);
+                    assert_eq!(frame.flags, END_STREAM | END_HEADERS);
+                    break;
+                }
+            }
+            io.write_all(&rsp).await.unwrap();
+            let _ = io.read(&mut [0; 1024]).await;
+        });
+        addr
+    }
+
+    async fn get(addr: std::net::SocketAddr, body: bool) -> Result<http::Response<Vec<u8>>, Error> {
+        let mut io = TcpStream::connect(addr).await.unwrap();
+        let req = http::Request::get(format!("http://{}/ready", addr))
+            .body(Vec::new())
+            .unwrap();
+        let mut buf = Vec::new();
+        let status = exchange(&mut io, &req, body.then_some(&mut buf)).await?;
+        let mut rsp = http::Response::new(buf);
+        *rsp.status_mut() = status;
+        Ok(rsp)
+    }
+
+    #[tokio::test]
+    async fn test_exchange() {
+        let mut rsp = Vec::new();
+        push_frame(&mut rsp, SETTINGS, 0, 0, &[]);
+        push_frame(&mut rsp, PING, 0, 0, &[0; 8]);
+        // `100 Continue`, as a literal.
+        push_frame(
+            &mut rsp,
+            HEADERS,
+            END_HEADERS,
+            STREAM,
+            &[0x08, 0x03, b'1', b'0', b'0'],
+        );
+        // `503`, split across a continuation.
+        push_frame(&mut rsp, HEADERS, 0, STREAM, &[0x48, 0x03]);
+        push_frame(&mut rsp, CONTINUATION, END_HEADERS, STREAM, b"503");
+        push_frame(&mut rsp, DATA, PADDED, STREAM, &[2, b'n', b'o', 0, 0]);
+        push_frame(&mut rsp, DATA, END_STREAM, STREAM, b"t ready");
+
+        let addr = serve(rsp.clone()).await;
+        let rsp1 = get(addr, true).await.unwrap();
+        assert_eq!(rsp1.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(rsp1.body(), b"not ready");
+
+        let addr = serve(rsp).await;
+        let rsp2 = get(addr, false).await.unwrap();
+        assert_eq!(rsp2.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        assert!(rsp2.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exchange_errors() {
+        let mut rsp = Vec::new();
+        push_frame(&mut rsp, SETTINGS, 0, 0, &[]);
+        push_frame(&mut rsp, RST_STREAM, 0, STREAM, &7u32.to_be_bytes());
+        let error = get(serve(rsp).await, false).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "HTTP/2 stream reset by server: REFUSED_STREAM"
+        );
+
+        let mut rsp = Vec::new();
+        push_frame(&mut rsp, SETTINGS, 0, 0, &[]);
+        let mut payload = 0u32.to_be_bytes().to_vec();
+        payload.extend_from_slice(&11u32.to_be_bytes());
+        push_frame(&mut rsp, GOAWAY, 0, 0, &payload);
+        let error = get(serve(rsp).await, false).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "HTTP/2 connection closed by server: ENHANCE_YOUR_CALM"
+        );
+
+        for rsp in [
+            &b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n"[..],
+            b"<!DOCTYPE HTML>\n<html lang=\"en\">\n<body>Error code: 505</body>\n",
+        ] {
+            let error = get(serve(rsp.to_vec()).await, false).await.unwrap_err();
+            assert!(
+                matches!(error, Error::Http2(Http2Error::Unsupported)),
+                "{}",
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use crate::Error;
+use std::{error, fmt, io, path::Path};
+
+/// A setting from a `--config` file, named after the flag that it sets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setting {
+    /// The flag's long name, e.g. `timeout` for `--timeout`.
+    pub name: String,
+    /// The setting's values. Scalars have a single value; arrays may have
+    /// any number.
+    pub values: Vec<String>,
+    /// The line of the file on which the setting is defined.
+    pub line: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidConfig {
+    line: usize,
+    reason: &'static str,
+}
+
+/// Reads the settings from a TOML or YAML file, as determined by its
+/// extension.
+pub fn read_config(path: &Path) -> Result<Vec<Setting>, Error> {
+    let format = ConfigFormat::from_path(path).ok_or_else(|| {
+        Error::ConfigFile(
+            path.to_path_buf(),
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown format; expected a .toml, .yaml, or .yml file",
+            ),
+        )
+    })?;
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| Error::ConfigFile(path.to_path_buf(), e))?;
+    parse_config(&contents, format).map_err(|e| {
+        Error::ConfigFile(
+            path.to_path_buf(),
+            io::Error::new(io::ErrorKind::InvalidData, e),
+        )
+    })
+}
+
+/// Parses a flat file of settings.
+///
+/// Only the subset of each format needed to name flags and their values is
+/// supported: keys with string, number, and boolean values, or arrays of them.
+/// Tables, nested mappings, and multi-line strings are rejected. Underscores
+/// in keys are read as dashes, so `ready_path` sets `--ready-path`.
+pub fn parse_config(s: &str, format: ConfigFormat) -> Result<Vec<Setting>, InvalidConfig> {
+    let lines = s
+        .lines()
+        .map(|line| strip_comment(line, format))
+        .collect::<Vec<_>>();
+    let mut settings = Vec::<Setting>::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = index + 1;
+        let invalid = |reason| InvalidConfig { line, reason };
+        let text = lines[index];
+        index += 1;
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let parsed = match format {
+            ConfigFormat::Toml => toml_setting(text, &lines, &mut index),
+            ConfigFormat::Yaml => yaml_setting(text, &lines, &mut index),
+        };
+        let (name, values) = match parsed.map_err(invalid)? {
+            Some(setting) => setting,
+            None => continue,
+        };
+        let name = name.replace('_', "-");
+        if name.is_empty() {
+            return Err(invalid("empty key"));
+        }
+        if settings.iter().any(|s| s.name == name) {
+            return Err(invalid("duplicate key"));
+        }
+        settings.push(Setting { name, values, line });
+    }
+    Ok(settings)
+}
+
+type Parsed = Result<Option<(String, Vec<String>)>, &'static str>;
+
+/// Parses a `key = value` line. Arrays may continue onto the following lines.
+fn toml_setting(text: &str, lines: &[&str], index: &mut usize) -> Parsed {
+    let text = text.trim();
+    if text.starts_with('[') {
+        return Err("tables are not supported");
+    }
+    let (key, rest) = key(text, ConfigFormat::Toml)?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix('=')
+        .ok_or("expected key = value")?
+        .trim();
+
+    let mut value = rest.to_string();
+    loop {
+        match value_of(&value, ConfigFormat::Toml) {
+            Err("unterminated array") if *index < lines.len() => {
+                value.push(' ');
+                value.push_str(lines[*index].trim());
+                *index += 1;
+            }
+            Err(e) => return Err(e),
+            Ok(values) => return Ok(Some((key, values))),
+        }
+    }
+}
+
+/// Parses a top-level `key: value` line. A key without a value may be
+/// followed by an indented block of `- item` lines.
+fn yaml_setting(text: &str, lines: &[&str], index: &mut usize) -> Parsed {
+    if matches!(text.trim_end(), "---" | "...") {
+        return Ok(None);
+    }
+    if text.starts_with([' ', '\t']) {
+        return Err("nested mappings are not supported");
+    }
+    if text.starts_with('-') {
+        return Err("expected key: value");
+    }
+    let (key, rest) = key(text, ConfigFormat::Yaml)?;
+    let rest = rest.strip_prefix(':').ok_or("expected key: value")?;
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return Err("expected key: value");
+    }
+    let rest = rest.trim();
+
+    if rest.is_empty() {
+        let mut values = Vec::new();
+        while let Some(next) = lines.get(*index) {
+            if next.trim().is_empty() {
+                *index += 1;
+                continue;
+            }
+            if !next.starts_with([' ', '\t', '-']) {
+                break;
+            }
+            let item = next.trim_start();
+            let item = match item.strip_prefix('-') {
+                Some(item) if item.is_empty() || item.starts_with(' ') => item.trim(),
+                _ => return Err("nested mappings are not supported"),
+            };
+            let (value, rest) = scalar(item, ConfigFormat::Yaml, false)?;
+            if !rest.trim().is_empty() {
+                return Err("unexpected characters after value");
+            }
+            values.push(value);
+            *index += 1;
+        }
+        // A key without a value or items is null, which leaves the flag unset.
+        if values.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some((key, values)));
+    }
+
+    match rest {
+        "~" | "null" => return Ok(None),
+        _ if rest.starts_with(['|', '>']) => return Err("multi-line strings are not supported"),
+        _ if rest.starts_with('{') => return Err("nested mappings are not supported"),
+        _ => {}
+    }
+    let mut value = rest.to_string();
+    loop {
+        match value_of(&value, ConfigFormat::Yaml) {
+            Err("unterminated array") if *index < lines.len() => {
+                value.push(' ');
+                value.push_str(lines[*index].trim());
+                *index += 1;
+            }
+            Err(e) => return Err(e),
+            Ok(values) => return Ok(Some((key, values))),
+        }
+    }
+}
+
+/// Parses a bare or quoted key, returning it and the rest of the line.
+fn key(text: &str, format: ConfigFormat) -> Result<(String, &str), &'static str> {
+    if text.starts_with(['"', '\'']) {
+        return quoted(text, format);
+    }
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(text.len());
+    let (key, rest) = text.split_at(end);
+    if key.is_empty() {
+        return Err(match format {
+            ConfigFormat::Toml => "expected key = value",
+            ConfigFormat::Yaml => "expected key: value",
+        });
+    }
+    Ok((key.to_string(), rest))
+}
+
+/// Parses a scalar or an array that makes up the rest of a line.
+fn value_of(s: &str, format: ConfigFormat) -> Result<Vec<String>, &'static str> {
+    let (values, rest) = match s.strip_prefix('[') {
+        Some(items) => array(items, format)?,
+        None => {
+            let (value, rest) = scalar(s, format, false)?;
+            (vec![value], rest)
+        }
+    };
+    if !rest.trim().is_empty() {
+        return Err("unexpected characters after value");
+    }
+    Ok(values)
+}
+
+/// Parses the items of an array up to its closing bracket.
+fn array(mut s: &str, format: ConfigFormat) -> Result<(Vec<String>, &str), &'static str> {
+    let mut values = Vec::new();
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix(']') {
+            return Ok((values, rest));
+        }
+        if s.is_empty() {
+            return Err("unterminated array");
+        }
+        if s.starts_with(['[', '{']) {
+            return Err("nested arrays are not supported");
+        }
+        let (value, rest) = scalar(s, format, true)?;
+        values.push(value);
+        let rest = rest.trim_start();
+        s = match rest.strip_prefix(',') {
+            Some(rest) => rest,
+            None if rest.starts_with(']') || rest.is_empty() => rest,
+            None => return Err("expected , or ] in array"),
+        };
+    }
+}
+
+/// Parses a quoted or bare scalar, returning it and the rest of the input. In
+/// an array, bare scalars end at the next `,` or `]`.
+fn scalar(s: &str, format: ConfigFormat, in_array: bool) -> Result<(String, &str), &'static str> {
+    let s = s.trim_start();
+    if s.starts_with(['"', '\'']) {
+        return quoted(s, format);
+    }
+    let end = if in_array {
+        s.find([',', ']']).unwrap_or(s.len())
+    } else {
+        s.len()
+    };
+    let (value, rest) = s.split_at(end);
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("expected a value");
+    }
+    // TOML only allows booleans and numbers to be bare; durations like `2m`
+    // must be quoted.
+    if format == ConfigFormat::Toml {
+        let number = value.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
+            && value
+                .chars()
+                .all(|c| c.is_ascii_digit() || "+-._eE".contains(c));
+        if !(number || value == "true" || value == "false") {
+            return Err("strings must be quoted");
+        }
+    }
+    Ok((value.to_string(), rest))
+}
+
+/// Parses a quoted string at the start of `s`, returning it and the rest of
+/// `s`. Double-quoted strings support the common backslash escapes.
+/// Single-quoted strings are literal, except that YAML escapes a single quote
+/// by doubling it.
+pub(crate) fn quoted(s: &str, format: ConfigFormat) -> Result<(String, &str), &'static str> {
+    let mut chars = s.char_indices();
+    let quote = match chars.next() {
+        Some((_, c @ ('"' | '\''))) => c,
+        _ => return Err("expected a quoted string"),
+    };
+    if s.starts_with("\"\"\"") || s.starts_with("'''") {
+        return Err("multi-line strings are not supported");
+    }
+
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' if quote == '\'' => {
+                if format == ConfigFormat::Yaml && s[i + 1..].starts_with('\'') {
+                    chars.next();
+                    value.push('\'');
+                    continue;
+                }
+                return Ok((value, &s[i + 1..]));
+            }
+            '"' if quote == '"' => return Ok((value, &s[i + 1..])),
+            '\\' if quote == '"' => {
+                let (_, escape) = chars.next().ok_or("unterminated string")?;
+                value.push(match escape {
+                    '\\' => '\\',
+                    '"' => '"',
+                    '/' => '/',
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    'u' => {
+                        let hex = (0..4)
+                            .map(|_| chars.next().map(|(_, c)| c))
+                            .collect::<Option<String>>()
+                            .ok_or("invalid escape")?;
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or("invalid escape")?
+                    }
+                    _ => return Err("invalid escape"),
+                });
+            }
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string")
+}
+
+/// Removes a trailing `#` comment from a line. Quotes only begin a string
+/// where a value may begin, so apostrophes in bare YAML values are kept. In
+/// YAML, a `#` only begins a comment at the start of a line or after
+/// whitespace.
+fn strip_comment(line: &str, format: ConfigFormat) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut prev = None;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'')
+                && prev.map_or(true, |p: char| p.is_whitespace() || "[,=".contains(p)) =>
+            {
+                quote = Some(c)
+            }
+            None if c == '#' => {
+                if format == ConfigFormat::Toml || prev.map_or(true, char::is_whitespace) {
+                    return &line[..i];
+                }
+            }
+            None => {}
+        }
+        prev = Some(c);
+    }
+    line
+}
+
+// === impl ConfigFormat ===
+
+impl ConfigFormat {
+    /// Determines a file's format from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+// === impl InvalidConfig ===
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl error::Error for InvalidConfig {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(name: &str, values: &[&str], line: usize) -> Setting {
+        Setting {
+            name: name.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+            line,
+        }
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let toml = r#"
+# Readiness
+port = 4192
+ready_path = "/ready" # trailing comment
+timeout = '2m'
+shutdown = true
+header = ["x-a: 1", 'x-b: #2']
+forward-signals = [
+  "TERM",
+  "INT", # interrupts
+]
+expect-body = "a\"b!"
+"#;
+        assert_eq!(
+            parse_config(toml, ConfigFormat::Toml),
+            Ok(vec![
+                setting("port", &["4192"], 3),
+                setting("ready-path", &["/ready"], 4),
+                setting("timeout", &["2m"], 5),
+                setting("shutdown", &["true"], 6),
+                setting("header", &["x-a: 1", "x-b: #2"], 7),
+                setting("forward-signals", &["TERM", "INT"], 8),
+                setting("expect-body", &["a\"b!"], 12),
+            ])
+        );
+
+        for (toml, line, reason) in [
+            ("timeout = 2m", 1, "strings must be quoted"),
+            ("[linkerd]\nport = 1", 1, "tables are not supported"),
+            ("port", 1, "expected key = value"),
+            ("port = 1\nport = 2", 2, "duplicate key"),
+            ("header = [\"a\"", 1, "unterminated array"),
+            ("header = [[\"a\"]]", 1, "nested arrays are not supported"),
+            ("timeout = \"2m", 1, "unterminated string"),
+            ("timeout = \"2m\" x", 1, "unexpected characters after value"),
+            (
+                "body = \"\"\"a\"\"\"",
+                1,
+                "multi-line strings are not supported",
+            ),
+        ] {
+            assert_eq!(
+                parse_config(toml, ConfigFormat::Toml),
+                Err(InvalidConfig { line, reason }),
+                "{}",
+                toml
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = r#"---
+# Readiness
+port: 4192
+ready_path: /ready # trailing comment
+timeout: "2m"
+shutdown: true
+expect-body: it's ready # comment
+header:
+  - "x-a: 1"
+  - 'x-b: it''s'
+
+forward-signals: [TERM, INT]
+backoff: ~
+namespace:
+"#;
+        assert_eq!(
+            parse_config(yaml, ConfigFormat::Yaml),
+            Ok(vec![
+                setting("port", &["4192"], 3),
+                setting("ready-path", &["/ready"], 4),
+                setting("timeout", &["2m"], 5),
+                setting("shutdown", &["true"], 6),
+                setting("expect-body", &["it's ready"], 7),
+                setting("header", &["x-a: 1", "x-b: it's"], 8),
+                setting("forward-signals", &["TERM", "INT"], 12),
+            ])
+        );
+
+        for (yaml, line, reason) in [
+            (
+                "linkerd:\n  port: 1",
+                1,
+                "nested mappings are not supported",
+            ),
+            ("  port: 1", 1, "nested mappings are not supported"),
+            ("- port", 1, "expected key: value"),
+            ("port=1", 1, "expected key: value"),
+            ("port: 1\nport: 2", 2, "duplicate key"),
+            (
+                "expect-body: |\n  ready",
+                1,
+                "multi-line strings are not supported",
+            ),
+            ("header: {a: b}", 1, "nested mappings are not supported"),
+            ("timeout: '2m", 1, "unterminated string"),
+        ] {
+            assert_eq!(
+                parse_config(yaml, ConfigFormat::Yaml),
+                Err(InvalidConfig { line, reason }),
+                "{}",
+                yaml
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_format() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/etc/linkerd-await/config.toml")),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yaml")),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yml")),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("config.json")), None);
+        assert_eq!(ConfigFormat::from_path(Path::new("config")), None);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::Error;
+use std::future::Future;
+use tokio::time;
+
+/// Bounds the time linkerd-await spends outside of CMD.
+#[derive(Copy, Clone, Debug)]
+pub struct Deadline {
+    limit: Option<time::Duration>,
+    spent: time::Duration,
+}
+
+impl Deadline {
+    /// Creates a deadline. A zero limit never elapses.
+    pub fn new(limit: Option<time::Duration>) -> Self {
+        Self {
+            limit: limit.filter(|limit| !limit.is_zero()),
+            spent: time::Duration::ZERO,
+        }
+    }
+
+    /// Runs the future, charging the time it takes against the deadline and
+    /// failing if the deadline elapses first.
+    pub async fn run<F: Future>(&mut self, f: F) -> Result<F::Output, Error> {
+        let Some(limit) = self.limit else {
+            return Ok(f.await);
+        };
+        if self.spent >= limit {
+            return Err(Error::Deadline(limit));
+        }
+        let start = time::Instant::now();
+        let res = time::timeout(limit.saturating_sub(self.spent), f).await;
+        self.spent += start.elapsed();
+        res.map_err(|_| Error::Deadline(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline() {
+        use time::Duration;
+
+        let mut deadline = Deadline::new(None);
+        assert!(deadline.run(time::sleep(Duration::MAX)).await.is_ok());
+
+        let mut deadline = Deadline::new(Some(Duration::from_secs(10)));
+        assert!(deadline
+            .run(time::sleep(Duration::from_secs(6)))
+            .await
+            .is_ok());
+        assert!(deadline
+            .run(time::sleep(Duration::from_secs(3)))
+            .await
+            .is_ok());
+        assert!(matches!(
+            deadline.run(time::sleep(Duration::from_secs(3))).await,
+            Err(Error::Deadline(_))
+        ));
+        assert!(matches!(
+            deadline.run(async {}).await,
+            Err(Error::Deadline(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_boundary() {
+        use time::Duration;
+
+        // Time spent outside of the deadline isn't charged against it.
+        let mut deadline = Deadline::new(Some(Duration::from_secs(10)));
+        assert!(deadline
+            .run(time::sleep(Duration::from_millis(9_999)))
+            .await
+            .is_ok());
+        time::advance(Duration::from_secs(60)).await;
+        assert!(deadline
+            .run(time::sleep(Duration::from_millis(2)))
+            .await
+            .is_err());
+
+        let mut deadline = Deadline::new(Some(Duration::from_secs(10)));
+        let start = time::Instant::now();
+        assert!(deadline.run(time::sleep(Duration::MAX)).await.is_err());
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+    }
+}
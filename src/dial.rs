@@ -0,0 +1,272 @@
+//! Establishes TCP connections to hosts that may resolve to several addresses.
+//!
+//! `localhost` commonly resolves to both `::1` and `127.0.0.1`, but the proxy
+//! may only listen on one of them. Rather than trying addresses one at a time,
+//! connection attempts are raced in the manner of Happy Eyeballs (RFC 8305):
+//! address families are interleaved and each attempt gets a head start
+//! before the next one begins. The first connection established wins.
+//!
+//! `localhost` isn't resolved at all: both loopback addresses are always
+//! tried, since the system resolver may only return the one the proxy isn't
+//! listening on. When every attempt fails, the error describes each address's
+//! failure.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use tokio::{net::TcpStream, task::JoinSet, time};
+
+/// Determines the addresses that a host name resolves to.
+///
+/// By default, names are resolved by the system resolver. Overrides take
+/// precedence over DNS and, when a DNS server is configured, the remaining
+/// names are resolved through it rather than the system resolver.
+#[derive(Clone, Debug, Default)]
+pub struct Resolver {
+    /// Fixed addresses for host names, e.g. from `--resolve`.
+    pub overrides: Vec<(String, IpAddr)>,
+
+    /// A DNS server to query instead of using the system resolver.
+    pub dns_server: Option<SocketAddr>,
+}
+
+/// The head start given to each connection attempt.
+const ATTEMPT_DELAY: time::Duration = time::Duration::from_millis(250);
+
+/// Connects to the host, racing connections to its addresses.
+pub(crate) async fn dial(resolver: &Resolver, host: &str, port: u16) -> io::Result<TcpStream> {
+    let addrs = resolver.resolve(host, port).await?;
+    connect_any(interleave(addrs)).await
+}
+
+// === impl Resolver ===
+
+impl Resolver {
+    /// Returns the addresses of the host.
+    pub(crate) async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+
+        let name = host.trim_end_matches('.');
+        let overrides = self
+            .overrides
+            .iter()
+            .filter(|(h, _)| h.trim_end_matches('.').eq_ignore_ascii_case(name))
+            .map(|(_, ip)| SocketAddr::new(*ip, port))
+            .collect::<Vec<_>>();
+        if !overrides.is_empty() {
+            return Ok(overrides);
+        }
+
+        // As in RFC 6761, `localhost` is always the loopback interface.
+        if name.eq_ignore_ascii_case("localhost") {
+            return Ok(vec![
+                SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port),
+                SocketAddr::new(Ipv6Addr::LOCALHOST.into(), port),
+            ]);
+        }
+
+        match self.dns_server {
+            Some(server) => {
+                let ips = crate::dns::lookup(server, name).await?;
+                Ok(ips
+                    .into_iter()
+                    .map(|ip| SocketAddr::new(ip, port))
+                    .collect())
+            }
+            None => Ok(tokio::net::lookup_host((host, port)).await?.collect()),
+        }
+    }
+}
+
+/// Connects to the first address that accepts a connection, starting a new
+/// attempt whenever the previous one fails or is slow to complete.
+async fn connect_any(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let connect = |addr| async move { (addr, TcpStream::connect(addr).await) };
+    let mut addrs = addrs.into_iter();
+    let mut attempts = JoinSet::new();
+    let mut errors = Vec::new();
+    loop {
+        let more = addrs.len() > 0;
+        if attempts.is_empty() {
+            match addrs.next() {
+                Some(addr) => {
+                    attempts.spawn(connect(addr));
+                }
+                None => return Err(connect_error(errors)),
+            }
+        }
+        tokio::select! {
+            res = attempts.join_next() => match res {
+                // Dropping the set cancels the remaining attempts.
+                Some(Ok((_, Ok(io)))) => return Ok(io),
+                Some(Ok((addr, Err(e)))) => errors.push((addr, e)),
+                Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+                None => {}
+            },
+            () = time::sleep(ATTEMPT_DELAY), if more => {
+                if let Some(addr) = addrs.next() {
+                    attempts.spawn(connect(addr));
+                }
+            }
+        }
+    }
+}
+
+/// Describes each failed attempt, e.g. so that it's clear whether the proxy
+/// refused connections on one address family or both. The error has the kind
+/// of the last failure.
+fn connect_error(mut errors: Vec<(SocketAddr, io::Error)>) -> io::Error {
+    match errors.len() {
+        0 => io::Error::new(io::ErrorKind::NotFound, "host has no addresses"),
+        1 => errors.pop().expect("error must exist").1,
+        _ => {
+            let kind = errors.last().expect("error must exist").1.kind();
+            let msg = errors
+                .iter()
+                .map(|(addr, e)| format!("{}: {}", addr, e))
+                .collect::<Vec<_>>()
+                .join("; ");
+            io::Error::new(kind, msg)
+        }
+    }
+}
+
+/// Orders addresses so that families alternate, starting with the resolver's
+/// preferred family.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let preferred = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+    let (first, second): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|a| a.is_ipv6() == preferred);
+    let mut second = second.into_iter();
+    let mut ordered = Vec::with_capacity(first.len() + second.len());
+    for addr in first {
+        ordered.push(addr);
+        ordered.extend(second.next());
+    }
+    ordered.extend(second);
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave() {
+        let addrs = [
+            "[::1]:1",
+            "[::1]:2",
+            "127.0.0.1:3",
+            "[::1]:4",
+            "127.0.0.1:5",
+        ]
+        .iter()
+        .map(|a| a.parse().unwrap())
+        .collect();
+        let expected: Vec<SocketAddr> = [
+            "[::1]:1",
+            "127.0.0.1:3",
+            "[::1]:2",
+            "127.0.0.1:5",
+            "[::1]:4",
+        ]
+        .iter()
+        .map(|a| a.parse().unwrap())
+        .collect();
+        assert_eq!(interleave(addrs), expected);
+    }
+
+    #[tokio::test]
+    async fn test_connect_any_falls_back() {
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let io = connect_any(vec![closed_addr, addr]).await.unwrap();
+        assert_eq!(io.peer_addr().unwrap(), addr);
+
+        let error = connect_any(vec![closed_addr]).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            connect_any(vec![]).await.unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_any_describes_each_failure() {
+        let closed = |ip: &str| {
+            let listener = std::net::TcpListener::bind((ip, 0)).unwrap();
+            listener.local_addr().unwrap()
+        };
+        let (a, b) = (closed("127.0.0.1"), closed("127.0.0.1"));
+
+        let error = connect_any(vec![a, b]).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        let msg = error.to_string();
+        assert!(msg.starts_with(&format!("{}: ", a)), "{}", msg);
+        assert!(msg.contains(&format!("; {}: ", b)), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn test_dial_localhost() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(dial(&Resolver::default(), "localhost", port).await.is_ok());
+
+        // The proxy may only listen on the IPv6 loopback address, which the
+        // system resolver may not return for `localhost`.
+        let Ok(listener) = tokio::net::TcpListener::bind("[::1]:0").await else {
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+        let io = dial(&Resolver::default(), "localhost", port).await.unwrap();
+        assert!(io.peer_addr().unwrap().is_ipv6());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_localhost() {
+        let resolver = Resolver {
+            overrides: vec![],
+            // Unreachable, so any query would fail.
+            dns_server: Some("127.0.0.1:9".parse().unwrap()),
+        };
+        let addrs = resolver.resolve("LOCALHOST.", 4191).await.unwrap();
+        let expected: Vec<SocketAddr> = ["127.0.0.1:4191", "[::1]:4191"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        assert_eq!(addrs, expected);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_overrides() {
+        let resolver = Resolver {
+            overrides: vec![
+                ("Proxy.Example".to_string(), [10, 0, 0, 1].into()),
+                ("proxy.example".to_string(), "fd00::1".parse().unwrap()),
+                ("other.example".to_string(), [10, 0, 0, 2].into()),
+            ],
+            // Unreachable, so any query would fail.
+            dns_server: Some("127.0.0.1:9".parse().unwrap()),
+        };
+        let addrs = resolver.resolve("proxy.example.", 4191).await.unwrap();
+        let expected: Vec<SocketAddr> = ["10.0.0.1:4191", "[fd00::1]:4191"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        assert_eq!(addrs, expected);
+
+        // Addresses are never resolved.
+        let addrs = resolver.resolve("::1", 4191).await.unwrap();
+        assert_eq!(addrs, ["[::1]:4191".parse::<SocketAddr>().unwrap()]);
+    }
+}
@@ -0,0 +1,233 @@
+//! A minimal DNS client for resolving names through a specific server.
+//!
+//! Cluster DNS may itself be unreachable until the proxy is ready, so
+//! `--dns-server` lets names be resolved without the system resolver. Only A
+//! and AAAA queries over UDP are supported.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use tokio::{net::UdpSocket, time};
+
+/// Bounds the time spent waiting for each response.
+const TIMEOUT: time::Duration = time::Duration::from_secs(2);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+/// Resolves the name's IPv6 and IPv4 addresses through the given server.
+pub(crate) async fn lookup(server: SocketAddr, name: &str) -> io::Result<Vec<IpAddr>> {
+    let bind: SocketAddr = if server.is_ipv6() {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+
+    let mut addrs = Vec::new();
+    for (i, qtype) in [TYPE_AAAA, TYPE_A].into_iter().enumerate() {
+        let id = query_id().wrapping_add(i as u16);
+        socket.send(&encode_query(id, name, qtype)?).await?;
+
+        let mut buf = [0; 1232];
+        let response = loop {
+            let n = time::timeout(TIMEOUT, socket.recv(&mut buf))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "DNS query timed out"))??;
+            // Ignore stray responses, e.g. to an earlier query that timed out.
+            if n >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+                break &buf[..n];
+            }
+        };
+        addrs.extend(decode_response(response)?);
+    }
+
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} has no addresses", name),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Returns an unpredictable query ID without depending on an RNG.
+fn query_id() -> u16 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.finish() as u16
+}
+
+fn encode_query(id: u16, name: &str, qtype: u16) -> io::Result<Vec<u8>> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid name: {}", name),
+        )
+    };
+
+    let mut buf = Vec::with_capacity(18 + name.len());
+    buf.extend_from_slice(&id.to_be_bytes());
+    // A standard query with recursion desired.
+    buf.extend_from_slice(&[0x01, 0x00]);
+    // One question and no other records.
+    buf.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    if buf.len() > 12 + 255 {
+        return Err(invalid());
+    }
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+/// Returns the addresses in a response's answer section. Other records, such
+/// as the CNAMEs that lead to the addresses, are skipped.
+fn decode_response(msg: &[u8]) -> io::Result<Vec<IpAddr>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid DNS response");
+    let u16_at = |i: usize| -> io::Result<u16> {
+        msg.get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(invalid)
+    };
+
+    let flags = u16_at(2)?;
+    if flags & 0x8000 == 0 {
+        return Err(invalid());
+    }
+    match flags & 0x000f {
+        0 => {}
+        // The name doesn't exist.
+        3 => return Ok(Vec::new()),
+        rcode => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("DNS server failed with response code {}", rcode),
+            ))
+        }
+    }
+
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos).ok_or_else(invalid)? + 4;
+    }
+
+    let mut addrs = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(msg, pos).ok_or_else(invalid)?;
+        let rtype = u16_at(pos)?;
+        let class = u16_at(pos + 2)?;
+        let len = usize::from(u16_at(pos + 8)?);
+        let data = msg.get(pos + 10..pos + 10 + len).ok_or_else(invalid)?;
+        pos += 10 + len;
+
+        match (rtype, class, data.len()) {
+            (TYPE_A, CLASS_IN, 4) => {
+                addrs.push(IpAddr::from(<[u8; 4]>::try_from(data).unwrap()));
+            }
+            (TYPE_AAAA, CLASS_IN, 16) => {
+                addrs.push(IpAddr::from(<[u8; 16]>::try_from(data).unwrap()));
+            }
+            _ => {}
+        }
+    }
+    Ok(addrs)
+}
+
+/// Returns the position after the (possibly compressed) name at `pos`.
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // A pointer to a name elsewhere in the message ends the name.
+            len if len & 0xc0 == 0xc0 => return Some(pos + 2).filter(|p| *p <= msg.len()),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(
+            encode_query(0x1234, "a.bc.", TYPE_A).unwrap(),
+            [
+                0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, // header
+                1, b'a', 2, b'b', b'c', 0, // name
+                0, 1, 0, 1, // type and class
+            ]
+        );
+        assert!(encode_query(1, "a..b", TYPE_A).is_err());
+        assert!(encode_query(1, &"a".repeat(64), TYPE_A).is_err());
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let mut msg = encode_query(7, "db.example", TYPE_A).unwrap();
+        msg[2] = 0x81;
+        msg[3] = 0x80;
+        msg[7] = 2;
+        // A CNAME for the question's name, followed by the address it aliases.
+        msg.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 4, 1, b'x', 0xc0, 15]);
+        msg.extend_from_slice(&[
+            1, b'x', 0xc0, 15, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 10, 0, 0, 1,
+        ]);
+        assert_eq!(
+            decode_response(&msg).unwrap(),
+            [IpAddr::from([10, 0, 0, 1])]
+        );
+
+        // NXDOMAIN
+        msg[3] = 0x83;
+        assert!(decode_response(&msg).unwrap().is_empty());
+
+        // SERVFAIL
+        msg[3] = 0x82;
+        assert!(decode_response(&msg).is_err());
+
+        assert!(decode_response(&msg[..20]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lookup() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            loop {
+                let (n, peer) = server.recv_from(&mut buf).await.unwrap();
+                let mut rsp = buf[..n].to_vec();
+                rsp[2] |= 0x80;
+                let qtype = u16::from_be_bytes([rsp[n - 4], rsp[n - 3]]);
+                if qtype == TYPE_A {
+                    rsp[7] = 1;
+                    rsp.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 10, 1, 2, 3]);
+                }
+                server.send_to(&rsp, peer).await.unwrap();
+            }
+        });
+
+        assert_eq!(
+            lookup(addr, "db.example").await.unwrap(),
+            [IpAddr::from([10, 1, 2, 3])]
+        );
+    }
+}
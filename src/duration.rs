@@ -0,0 +1,77 @@
+use std::{error, fmt, str::FromStr, time::Duration};
+
+/// Parses a duration like `10s`, `250ms`, or `0`. Units of `ms`, `s`, `m`, `h`,
+/// and `d` are supported; only zero may omit the unit.
+pub fn parse_duration(s: &str) -> Result<Duration, InvalidDuration> {
+    let s = s.trim();
+    let milliseconds = match s.rfind(|c: char| c.is_ascii_digit()) {
+        None => return Err(InvalidDuration),
+        Some(index) => {
+            let (magnitude, unit) = s.split_at(index + 1);
+            let magnitude = u64::from_str(magnitude).map_err(|_| InvalidDuration)?;
+            let multiplier = match unit {
+                "" if magnitude == 0 => 0,
+                "ms" => 1,
+                "s" => 1000,
+                "m" => 1000 * 60,
+                "h" => 1000 * 60 * 60,
+                "d" => 1000 * 60 * 60 * 24,
+                _ => return Err(InvalidDuration),
+            };
+            magnitude.checked_mul(multiplier).ok_or(InvalidDuration)?
+        }
+    };
+    Ok(Duration::from_millis(milliseconds))
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InvalidDuration;
+
+impl fmt::Display for InvalidDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid duration")
+    }
+}
+
+impl error::Error for InvalidDuration {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert_eq!(parse_duration(""), Err(InvalidDuration));
+        assert_eq!(parse_duration("  "), Err(InvalidDuration));
+        assert_eq!(parse_duration("\t\n"), Err(InvalidDuration));
+        assert_eq!(parse_duration("x"), Err(InvalidDuration));
+        assert_eq!(parse_duration("1"), Err(InvalidDuration));
+        assert_eq!(parse_duration("0x"), Err(InvalidDuration));
+        assert_eq!(parse_duration("123x"), Err(InvalidDuration));
+        assert_eq!(parse_duration("  123x  "), Err(InvalidDuration));
+        assert_eq!(
+            parse_duration(&format!("{}s", u64::MAX)),
+            Err(InvalidDuration),
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_valid() {
+        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::from_secs(0)));
+        assert_eq!(parse_duration("1ms"), Ok(Duration::from_millis(1)));
+        assert_eq!(parse_duration("1s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_duration(" \n12s  \t"), Ok(Duration::from_secs(12)));
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(10 * 60)));
+        assert_eq!(parse_duration("10h"), Ok(Duration::from_secs(10 * 60 * 60)));
+        assert_eq!(
+            parse_duration("10d"),
+            Ok(Duration::from_secs(10 * 60 * 60 * 24))
+        );
+        assert_eq!(
+            parse_duration(&format!("{}ms", u64::MAX)),
+            Ok(Duration::from_millis(u64::MAX)),
+        );
+    }
+}
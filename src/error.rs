@@ -0,0 +1,257 @@
+use crate::check::CheckError;
+use std::time;
+use std::{error, fmt, io, path::PathBuf};
+
+// From https://man.netbsd.org/sysexits.3
+pub const EX_USAGE: i32 = 64;
+pub const EX_UNAVAILABLE: i32 = 69;
+pub const EX_SOFTWARE: i32 = 70;
+pub const EX_OSERR: i32 = 71;
+pub const EX_CANTCREAT: i32 = 73;
+pub const EX_TEMPFAIL: i32 = 75;
+pub const EX_PROTOCOL: i32 = 76;
+pub const EX_CONFIG: i32 = 78;
+
+/// Errors that cause linkerd-await to exit before (or instead of) running the
+/// command.
+///
+/// Each error is reported as a single line on stderr and maps to a documented
+/// exit code. See [`Error::exit_code`].
+#[derive(Debug)]
+pub enum Error {
+    /// Flags were combined in a way that has no effect and `--strict` is set.
+    InvalidConfig(String),
+
+    /// The admin server's URI could not be constructed.
+    InvalidUri(http::Error),
+
+    /// A request could not be constructed, e.g. from its URI and headers.
+    InvalidRequest(http::Error),
+
+    /// A check did not succeed before the timeout elapsed. The check's last
+    /// failure, or its description if no attempt completed, is included.
+    Timeout(String, time::Duration),
+
+    /// A check was not ready after the maximum number of checks. The last
+    /// check's failure is included.
+    Attempts(u32, CheckError),
+
+    /// The sidecar's shutdown request failed and the proxy wasn't signaled
+    /// instead.
+    #[cfg(feature = "runtime")]
+    Shutdown(http::Uri, Box<crate::ShutdownError>),
+
+    /// The admin server still accepted connections once `--verify-shutdown`
+    /// elapsed after the shutdown request.
+    ShutdownTimeout(http::Uri, time::Duration),
+
+    /// linkerd-await's own runtime exceeded the `--deadline`.
+    Deadline(time::Duration),
+
+    /// linkerd-await was asked to terminate while waiting for the proxy.
+    #[cfg(feature = "runtime")]
+    Interrupted(crate::process::Signal),
+
+    /// A readiness check failed in a way that retrying cannot fix.
+    Unrecoverable(CheckError),
+
+    /// A readiness check did not succeed within its own timeout.
+    CheckTimeout(CheckError),
+
+    /// The Kubernetes API client could not be configured.
+    Kubernetes(io::Error),
+
+    /// The admin server's CA certificates could not be read.
+    CaFile(PathBuf, io::Error),
+
+    /// The client certificate presented to the admin server could not be
+    /// loaded.
+    ClientCert(PathBuf, io::Error),
+
+    /// The `--config` file could not be read or has invalid settings.
+    ConfigFile(PathBuf, io::Error),
+
+    /// The `--annotations-file` could not be read or parsed.
+    AnnotationsFile(PathBuf, io::Error),
+
+    /// A signal handler could not be registered.
+    Signal(io::Error),
+
+    /// The command could not be spawned in `--shutdown` mode.
+    Spawn(String, io::Error),
+
+    /// The PID file could not be written.
+    PidFile(PathBuf, io::Error),
+
+    /// A `--decode-secret` source could not be read or decoded.
+    SecretRead(PathBuf, io::Error),
+
+    /// A `--decode-secret` destination could not be written.
+    SecretWrite(PathBuf, io::Error),
+
+    /// The lock file could not be opened or locked.
+    Lock(PathBuf, io::Error),
+
+    /// The lock file is held by another instance.
+    Locked(PathBuf),
+
+    /// The forked command's status could not be obtained.
+    Wait(io::Error),
+
+    /// The command could not be executed.
+    Exec(String, io::Error),
+}
+
+impl Error {
+    /// Returns the process exit code for the error.
+    ///
+    /// - `EX_USAGE` (64) when the configuration is invalid;
+    /// - `EX_UNAVAILABLE` (69) when the proxy, or a check with its own
+    ///   timeout, did not become ready in time, or the proxy could not be
+    ///   shut down;
+    /// - `EX_SOFTWARE` (70) when an internal invariant is violated or a
+    ///   request can't be constructed;
+    /// - `EX_OSERR` (71) when the OS prevented the command from running;
+    /// - `EX_CANTCREAT` (73) when a file could not be written;
+    /// - `EX_TEMPFAIL` (75) when another instance holds the lock file;
+    /// - `EX_PROTOCOL` (76) when the proxy was not ready after the maximum
+    ///   number of checks;
+    /// - `128 + N` when signal `N` (e.g. SIGTERM) was received while waiting,
+    ///   as a shell reports a process killed by the signal;
+    /// - `EX_CONFIG` (78) when a readiness check can never succeed, the
+    ///   Kubernetes API can't be used, or a secret, TLS, config, or
+    ///   annotations file can't be read.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidConfig(_) => EX_USAGE,
+            Self::Timeout(..)
+            | Self::ShutdownTimeout(..)
+            | Self::Deadline(_)
+            | Self::CheckTimeout(_) => EX_UNAVAILABLE,
+            #[cfg(feature = "runtime")]
+            Self::Interrupted(signal) => 128 + signal.number(),
+            #[cfg(feature = "runtime")]
+            Self::Shutdown(_, e) if matches!(**e, crate::ShutdownError::Invalid(_)) => EX_SOFTWARE,
+            #[cfg(feature = "runtime")]
+            Self::Shutdown(..) => EX_UNAVAILABLE,
+            Self::InvalidUri(_) | Self::InvalidRequest(_) => EX_SOFTWARE,
+            Self::Unrecoverable(_)
+            | Self::Kubernetes(_)
+            | Self::CaFile(..)
+            | Self::ClientCert(..)
+            | Self::ConfigFile(..)
+            | Self::AnnotationsFile(..)
+            | Self::SecretRead(..) => EX_CONFIG,
+            Self::PidFile(..) | Self::Lock(..) | Self::SecretWrite(..) => EX_CANTCREAT,
+            Self::Locked(_) => EX_TEMPFAIL,
+            Self::Attempts(..) => EX_PROTOCOL,
+            Self::Signal(_) | Self::Spawn(..) | Self::Wait(_) | Self::Exec(..) => EX_OSERR,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(reason) => write!(f, "Invalid configuration: {}", reason),
+            Self::InvalidUri(e) => write!(f, "Invalid admin server URI: {}", e),
+            Self::InvalidRequest(e) => write!(f, "Invalid request: {}", e),
+            Self::Timeout(check, timeout) => {
+                write!(f, "Not ready within {:?} timeout: {}", timeout, check)
+            }
+            Self::Attempts(attempts, e) => write!(f, "Not ready after {} checks: {}", attempts, e),
+            #[cfg(feature = "runtime")]
+            Self::Shutdown(uri, e) => write!(f, "Failed to shut down the sidecar with {}: {}", uri, e),
+            Self::ShutdownTimeout(uri, timeout) => write!(
+                f,
+                "{} still answers {:?} after the shutdown request; the sidecar may not have shut down",
+                uri, timeout
+            ),
+            Self::Deadline(deadline) => {
+                write!(f, "linkerd-await exceeded its {:?} deadline", deadline)
+            }
+            #[cfg(feature = "runtime")]
+            Self::Interrupted(signal) => write!(
+                f,
+                "Received {} before the proxy became ready; CMD will not be run",
+                signal
+            ),
+            Self::Unrecoverable(e) => write!(f, "Readiness check cannot succeed: {}", e),
+            Self::CheckTimeout(e) => write!(f, "Readiness check timed out: {}", e),
+            Self::Kubernetes(e) => write!(f, "Failed to configure Kubernetes API client: {}", e),
+            Self::CaFile(path, e) => write!(
+                f,
+                "Failed to read CA certificates: {}: {}",
+                path.display(),
+                e
+            ),
+            Self::ClientCert(path, e) => write!(
+                f,
+                "Failed to load client certificate: {}: {}",
+                path.display(),
+                e
+            ),
+            Self::ConfigFile(path, e) => {
+                write!(f, "Failed to load config file: {}: {}", path.display(), e)
+            }
+            Self::AnnotationsFile(path, e) => {
+                write!(
+                    f,
+                    "Failed to read pod annotations: {}: {}",
+                    path.display(),
+                    e
+                )
+            }
+            Self::Signal(e) => write!(f, "Failed to register signal handler: {}", e),
+            Self::Spawn(cmd, e) => write!(f, "Failed to fork child program: {}: {}", cmd, e),
+            Self::PidFile(path, e) => {
+                write!(f, "Failed to write PID file: {}: {}", path.display(), e)
+            }
+            Self::SecretRead(path, e) => {
+                write!(f, "Failed to read secret: {}: {}", path.display(), e)
+            }
+            Self::SecretWrite(path, e) => {
+                write!(f, "Failed to write secret: {}: {}", path.display(), e)
+            }
+            Self::Lock(path, e) => write!(f, "Failed to lock {}: {}", path.display(), e),
+            Self::Locked(path) => write!(
+                f,
+                "Another instance of linkerd-await holds the lock on {}",
+                path.display()
+            ),
+            Self::Wait(e) => write!(f, "Failed to wait for child program: {}", e),
+            Self::Exec(cmd, e) => write!(f, "Failed to exec child program: {}: {}", cmd, e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidConfig(_)
+            | Self::Timeout(..)
+            | Self::ShutdownTimeout(..)
+            | Self::Deadline(_)
+            | Self::Locked(_) => None,
+            #[cfg(feature = "runtime")]
+            Self::Interrupted(_) => None,
+            #[cfg(feature = "runtime")]
+            Self::Shutdown(_, e) => Some(&**e),
+            Self::InvalidUri(e) | Self::InvalidRequest(e) => Some(e),
+            Self::Unrecoverable(e) | Self::CheckTimeout(e) | Self::Attempts(_, e) => Some(e),
+            Self::Kubernetes(e)
+            | Self::CaFile(_, e)
+            | Self::ClientCert(_, e)
+            | Self::ConfigFile(_, e)
+            | Self::AnnotationsFile(_, e)
+            | Self::Signal(e)
+            | Self::Spawn(_, e)
+            | Self::PidFile(_, e)
+            | Self::SecretRead(_, e)
+            | Self::SecretWrite(_, e)
+            | Self::Lock(_, e)
+            | Self::Wait(e)
+            | Self::Exec(_, e) => Some(e),
+        }
+    }
+}
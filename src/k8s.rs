@@ -0,0 +1,377 @@
+//! A minimal Kubernetes API client, authenticated as the pod's service
+//! account.
+//!
+//! Only the in-cluster configuration is supported: the API server's address
+//! is read from the environment Kubernetes sets in every container, and the
+//! service account's token and CA certificate from the files mounted into the
+//! pod.
+
+use crate::{client, proxy, TlsConnector};
+use std::{error, fmt, io, path::PathBuf, str::FromStr};
+
+/// Where Kubernetes mounts the pod's service account credentials.
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Sends requests to the Kubernetes API server.
+#[derive(Debug)]
+pub struct Client {
+    server: http::Uri,
+    token: Option<PathBuf>,
+    client: client::Persistent,
+}
+
+/// A workload whose readiness can be awaited, as `KIND/NAME`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    pub kind: WorkloadKind,
+    pub name: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WorkloadKind {
+    Deployment,
+    StatefulSet,
+    Pod,
+}
+
+#[derive(Debug)]
+pub struct InvalidWorkload(String);
+
+/// A ConfigMap or Secret that must exist, as `KIND/NAME[:KEY]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectRef {
+    pub kind: ObjectKind,
+    pub name: String,
+
+    /// A key that must be present in the object's data.
+    pub key: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    ConfigMap,
+    Secret,
+}
+
+#[derive(Debug)]
+pub struct InvalidObjectRef(String);
+
+/// Describes why a request to the API server failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The service account token could not be read.
+    Token(io::Error),
+
+    /// The request failed.
+    Request(client::Error),
+}
+
+/// Returns the namespace of the pod's service account.
+pub fn namespace() -> io::Result<String> {
+    let path = PathBuf::from(SERVICE_ACCOUNT_DIR).join("namespace");
+    let ns = std::fs::read_to_string(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    Ok(ns.trim().to_string())
+}
+
+// === impl Client ===
+
+impl Client {
+    /// Creates a client from the pod's environment and service account.
+    pub fn in_cluster(connector: proxy::Connector) -> io::Result<Self> {
+        let host = std::env::var("KUBERNETES_SERVICE_HOST").map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "KUBERNETES_SERVICE_HOST is not set; is linkerd-await running in a pod?",
+            )
+        })?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".to_string());
+        let server = if host.contains(':') {
+            format!("https://[{}]:{}", host, port)
+        } else {
+            format!("https://{}:{}", host, port)
+        };
+        let server = server
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let dir = PathBuf::from(SERVICE_ACCOUNT_DIR);
+        let ca = dir.join("ca.crt");
+        let tls = std::fs::read(&ca)
+            .and_then(|pem| TlsConnector::from_pem(&pem))
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", ca.display(), e)))?;
+
+        Ok(Self::new(
+            connector.with_tls(tls),
+            server,
+            Some(dir.join("token")),
+        ))
+    }
+
+    /// Creates a client for the given API server, authenticating with the
+    /// bearer token in the `token` file, if any.
+    pub fn new(connector: proxy::Connector, server: http::Uri, token: Option<PathBuf>) -> Self {
+        Self {
+            client: client::Persistent::new(connector, server.clone()),
+            server,
+            token,
+        }
+    }
+
+    /// Sends a `GET` request for the path.
+    pub async fn get(&self, path: &str) -> Result<http::Response<Vec<u8>>, Error> {
+        self.request(http::Method::GET, path, Vec::new()).await
+    }
+
+    /// Sends a request with a JSON body, if any, to the path.
+    pub async fn request(
+        &self,
+        method: http::Method,
+        path: &str,
+        body: Vec<u8>,
+    ) -> Result<http::Response<Vec<u8>>, Error> {
+        let mut req = http::Request::builder()
+            .method(method)
+            .uri(format!(
+                "{}{}",
+                self.server.to_string().trim_end_matches('/'),
+                path
+            ))
+            .header(http::header::ACCEPT, "application/json");
+        if !body.is_empty() {
+            req = req.header(http::header::CONTENT_TYPE, "application/json");
+        }
+        // Service account tokens are rotated, so the token is read for each
+        // request.
+        if let Some(path) = self.token.as_ref() {
+            let token = std::fs::read_to_string(path).map_err(Error::Token)?;
+            req = req.header(
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", token.trim()),
+            );
+        }
+        let req = req
+            .body(body)
+            .map_err(|_| Error::Request(client::Error::InvalidUri(self.server.clone())))?;
+        self.client.fetch(req).await.map_err(Error::Request)
+    }
+}
+
+// === impl Workload ===
+
+impl Workload {
+    /// Returns the API path of the workload in the namespace.
+    pub fn path(&self, namespace: &str) -> String {
+        let (group, resource) = match self.kind {
+            WorkloadKind::Deployment => ("apis/apps/v1", "deployments"),
+            WorkloadKind::StatefulSet => ("apis/apps/v1", "statefulsets"),
+            WorkloadKind::Pod => ("api/v1", "pods"),
+        };
+        format!(
+            "/{}/namespaces/{}/{}/{}",
+            group, namespace, resource, self.name
+        )
+    }
+}
+
+impl FromStr for Workload {
+    type Err = InvalidWorkload;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidWorkload(s.to_string());
+        let (kind, name) = s.split_once('/').ok_or_else(invalid)?;
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "deployment" | "deployments" | "deploy" => WorkloadKind::Deployment,
+            "statefulset" | "statefulsets" | "sts" => WorkloadKind::StatefulSet,
+            "pod" | "pods" | "po" => WorkloadKind::Pod,
+            _ => return Err(invalid()),
+        };
+        if !is_valid_name(name) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            kind,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            WorkloadKind::Deployment => "deployment",
+            WorkloadKind::StatefulSet => "statefulset",
+            WorkloadKind::Pod => "pod",
+        };
+        write!(f, "{}/{}", kind, self.name)
+    }
+}
+
+impl fmt::Display for InvalidWorkload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid workload {:?}; expected deployment/NAME, statefulset/NAME, or pod/NAME",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidWorkload {}
+
+// === impl ObjectRef ===
+
+impl ObjectRef {
+    /// Returns the API path of the object in the namespace.
+    pub fn path(&self, namespace: &str) -> String {
+        let resource = match self.kind {
+            ObjectKind::ConfigMap => "configmaps",
+            ObjectKind::Secret => "secrets",
+        };
+        format!(
+            "/api/v1/namespaces/{}/{}/{}",
+            namespace, resource, self.name
+        )
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = InvalidObjectRef;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidObjectRef(s.to_string());
+        let (kind, name) = s.split_once('/').ok_or_else(invalid)?;
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "configmap" | "configmaps" | "cm" => ObjectKind::ConfigMap,
+            "secret" | "secrets" => ObjectKind::Secret,
+            _ => return Err(invalid()),
+        };
+        let (name, key) = match name.split_once(':') {
+            Some((name, key)) => (name, Some(key)),
+            None => (name, None),
+        };
+        if !is_valid_name(name) {
+            return Err(invalid());
+        }
+        // Keys may only contain alphanumerics, '-', '_', and '.'.
+        let valid_key = |k: &str| {
+            !k.is_empty()
+                && k.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        };
+        if !key.map_or(true, valid_key) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            kind,
+            name: name.to_string(),
+            key: key.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ObjectKind::ConfigMap => "configmap",
+            ObjectKind::Secret => "secret",
+        };
+        write!(f, "{}/{}", kind, self.name)?;
+        if let Some(key) = self.key.as_ref() {
+            write!(f, ":{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InvalidObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid object {:?}; expected configmap/NAME[:KEY] or secret/NAME[:KEY]",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidObjectRef {}
+
+/// Object names are DNS subdomains, so they never need escaping in paths.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.';
+    !name.is_empty() && name.len() <= 253 && name.chars().all(valid)
+}
+
+// === impl Error ===
+
+impl Error {
+    /// Indicates whether the API server could not be reached.
+    pub fn is_connect(&self) -> bool {
+        matches!(self, Self::Request(e) if e.is_connect())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(e) => write!(f, "failed to read service account token: {}", e),
+            Self::Request(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Token(e) => Some(e),
+            Self::Request(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_workload() {
+        let workload = "deploy/my-dep".parse::<Workload>().unwrap();
+        assert_eq!(workload.kind, WorkloadKind::Deployment);
+        assert_eq!(workload.to_string(), "deployment/my-dep");
+        assert_eq!(
+            workload.path("ns"),
+            "/apis/apps/v1/namespaces/ns/deployments/my-dep"
+        );
+        assert_eq!(
+            "Pod/web-0".parse::<Workload>().unwrap().path("ns"),
+            "/api/v1/namespaces/ns/pods/web-0"
+        );
+        assert_eq!(
+            "statefulsets/db".parse::<Workload>().unwrap().kind,
+            WorkloadKind::StatefulSet
+        );
+
+        assert!("my-dep".parse::<Workload>().is_err());
+        assert!("job/my-job".parse::<Workload>().is_err());
+        assert!("deployment/".parse::<Workload>().is_err());
+        assert!("deployment/a/b".parse::<Workload>().is_err());
+    }
+
+    #[test]
+    fn test_parse_object_ref() {
+        let object = "secret/my-cert:tls.crt".parse::<ObjectRef>().unwrap();
+        assert_eq!(object.kind, ObjectKind::Secret);
+        assert_eq!(object.key.as_deref(), Some("tls.crt"));
+        assert_eq!(object.to_string(), "secret/my-cert:tls.crt");
+        assert_eq!(object.path("ns"), "/api/v1/namespaces/ns/secrets/my-cert");
+
+        let object = "cm/config".parse::<ObjectRef>().unwrap();
+        assert_eq!(object.kind, ObjectKind::ConfigMap);
+        assert_eq!(object.key, None);
+
+        assert!("secret".parse::<ObjectRef>().is_err());
+        assert!("pod/web-0".parse::<ObjectRef>().is_err());
+        assert!("secret/my-cert:".parse::<ObjectRef>().is_err());
+        assert!("secret/my-cert:a/b".parse::<ObjectRef>().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Waits for a Linkerd proxy to become ready and, optionally, shuts it down
+//! once a supervised process completes.
+//!
+//! This is the machinery behind the `linkerd-await` command, exposed so that
+//! other init and wrapper tools can embed it:
+//!
+//! - [`await_ready`] retries a [`Check`], such as the proxy's admin server
+//!   reporting ready, until it succeeds;
+//! - [`supervise()`] runs a command, forwarding signals to it;
+//! - [`send_shutdown`] asks the proxy to shut down, and [`await_shutdown`]
+//!   waits until it has.
+//!
+//! The `minimal` feature adds the `blocking` module, a synchronous
+//! implementation of the core of this behavior that needs no async runtime.
+
+#![deny(warnings, rust_2018_idioms)]
+
+mod backoff;
+#[cfg(feature = "minimal")]
+pub mod blocking;
+pub mod check;
+#[cfg(any(feature = "runtime", feature = "minimal"))]
+pub mod client;
+#[cfg(feature = "runtime")]
+mod config;
+mod duration;
+mod error;
+#[cfg(any(feature = "runtime", feature = "minimal"))]
+pub mod process;
+
+pub use self::{
+    backoff::{Backoff, BackoffStrategy},
+    check::{Check, CheckError},
+    duration::{parse_duration, InvalidDuration},
+    error::*,
+};
+
+#[cfg(feature = "runtime")]
+mod annotations;
+#[cfg(feature = "runtime")]
+mod deadline;
+#[cfg(feature = "runtime")]
+mod dial;
+#[cfg(feature = "runtime")]
+mod dns;
+#[cfg(feature = "k8s")]
+pub mod k8s;
+#[cfg(feature = "runtime")]
+pub mod proxy;
+#[cfg(feature = "runtime")]
+mod ready;
+#[cfg(feature = "runtime")]
+mod secret;
+#[cfg(feature = "runtime")]
+mod shutdown;
+#[cfg(feature = "runtime")]
+mod supervise;
+#[cfg(feature = "runtime")]
+mod timestamp;
+#[cfg(feature = "tls")]
+mod tls;
+
+#[cfg(feature = "tls")]
+pub use self::tls::TlsConnector;
+
+#[cfg(feature = "runtime")]
+pub use self::{
+    annotations::{InvalidAnnotations, PodAnnotations},
+    config::{parse_config, read_config, ConfigFormat, InvalidConfig, Setting},
+    deadline::Deadline,
+    dial::Resolver,
+    ready::{await_ready, Retry, TimeoutWarning},
+    secret::{DecodeSecret, InvalidDecodeSecret},
+    shutdown::{await_shutdown, retry_shutdown, send_shutdown, ShutdownError},
+    supervise::{
+        inherit_on_exec, lock_exclusive, supervise, write_pid_file, write_ready_file, Notify,
+    },
+};
+
+/// Returns the reason Linkerd is disabled, as set by the
+/// `LINKERD_AWAIT_DISABLED` (or `LINKERD_DISABLED`) environment variable.
+pub fn linkerd_disabled_reason() -> Option<String> {
+    std::env::var("LINKERD_AWAIT_DISABLED")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .or_else(|| {
+            std::env::var("LINKERD_DISABLED")
+                .ok()
+                .filter(|v| !v.is_empty())
+        })
+}
+
+/// Builds the URI of an endpoint on the proxy's admin server.
+pub fn admin_uri(
+    scheme: http::uri::Scheme,
+    host: &str,
+    port: u16,
+    path: &str,
+) -> Result<http::Uri, Error> {
+    // IPv6 addresses must be bracketed in the authority.
+    let authority = if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    http::Uri::builder()
+        .scheme(scheme)
+        .authority(authority)
+        .path_and_query(path)
+        .build()
+        .map_err(Error::InvalidUri)
+}
@@ -1,339 +1,5456 @@
 #![deny(warnings, rust_2018_idioms)]
 
-use clap::Parser;
-use http_body_util::Empty;
-use hyper::body::Bytes;
-use hyper_util::{client::legacy as client, rt::TokioExecutor};
-use std::{convert::TryInto, error, fmt, io, process::ExitStatus, str::FromStr};
+use clap::{parser::ValueSource, ArgAction, CommandFactory, FromArgMatches, Parser};
+#[cfg(feature = "k8s")]
+use linkerd_await::k8s::{self, ObjectRef, Workload};
+use linkerd_await::{
+    admin_uri, await_ready, await_shutdown,
+    check::{self, HttpCheck},
+    client, inherit_on_exec, linkerd_disabled_reason, lock_exclusive, parse_duration,
+    process::{self, Signal},
+    proxy, read_config, retry_shutdown, send_shutdown, supervise, write_pid_file, write_ready_file,
+    Backoff, BackoffStrategy, Check, Deadline, DecodeSecret, Error, InvalidDuration, Notify,
+    PodAnnotations, Resolver, Retry, TimeoutWarning, EX_OSERR, EX_UNAVAILABLE,
+};
+use std::{
+    error,
+    ffi::OsString,
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    process::ExitStatus,
+    str::FromStr,
+};
 use tokio::time;
 
+/// Without the `k8s` feature, workloads and objects are accepted as-is so
+/// that the flags can be rejected by [`Args::unsupported`].
+#[cfg(not(feature = "k8s"))]
+type Workload = String;
+#[cfg(not(feature = "k8s"))]
+type ObjectRef = String;
+
+type Checks = Vec<Box<dyn Check>>;
+
+#[cfg(feature = "k8s")]
+type Lease = std::sync::Arc<check::LeaseCheck>;
+/// Without the `k8s` feature, leases are never acquired.
+#[cfg(not(feature = "k8s"))]
+type Lease = std::convert::Infallible;
+
+const DEFAULT_ADMIN_HOST: &str = "localhost";
+/// Where the Vault Agent injector mounts the agent's token in application
+/// containers, with the `vault.hashicorp.com/agent-inject-token` annotation.
+const DEFAULT_VAULT_TOKEN: &str = "/vault/secrets/token";
+/// The readiness endpoint that the Cloud SQL Auth Proxy serves with
+/// `--health-check`, on its default HTTP port.
+const DEFAULT_CLOUD_SQL_PROXY_READY: &str = "http://localhost:9090/readiness";
+/// The endpoint that the Cloud SQL Auth Proxy serves with `--quitquitquit`,
+/// on its default admin port.
+const DEFAULT_CLOUD_SQL_PROXY_SHUTDOWN: &str = "http://localhost:9091/quitquitquit";
+/// Bounds each attempt of a check, other than an `--exec-probe`'s, so that a
+/// hung target is retried.
+const DEFAULT_ATTEMPT_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+/// How long a failed shutdown request is retried unless `--shutdown-timeout`
+/// is set.
+const DEFAULT_SHUTDOWN_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+/// How long a sidecar that drains before it's shut down is given to do so
+/// unless `--drain-time` is set.
+const DEFAULT_DRAIN_TIME: time::Duration = time::Duration::from_secs(5);
+/// The ports of the Linkerd proxy's inbound and outbound listeners, unless
+/// the pod's annotations configure others.
+const DEFAULT_INBOUND_PORT: u16 = 4143;
+const DEFAULT_OUTBOUND_PORT: u16 = 4140;
+/// Set on the proxy container by the injector, or on the application
+/// container by hand, when the admin server's port is customized.
+const ADMIN_LISTEN_ADDR_ENV: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
+/// How deeply `--require` expressions may nest, so that a pathological
+/// expression is rejected rather than overflowing the stack.
+const MAX_REQUIRE_DEPTH: usize = 32;
+
 #[derive(Clone, Debug, Parser)]
 #[clap(about, version)]
+#[clap(group(clap::ArgGroup::new("k8s").multiple(true)))]
+#[clap(args_conflicts_with_subcommands = true)]
 /// Wait for linkerd to become ready before running a program.
 struct Args {
     #[clap(
         short = 'p',
         long = "port",
-        default_value = "4191",
-        help = "The port of the local Linkerd proxy admin server"
+        global = true,
+        help = "The port of the local Linkerd proxy admin server [default: the mesh's, e.g. 4191, or the port in LINKERD2_PROXY_ADMIN_LISTEN_ADDR]",
+        env = "LINKERD_AWAIT_PORT"
+    )]
+    port: Option<u16>,
+
+    #[clap(
+        long = "host",
+        global = true,
+        help = "The host of the Linkerd proxy admin server [default: localhost]",
+        env = "LINKERD_AWAIT_HOST"
+    )]
+    host: Option<String>,
+
+    #[clap(
+        long = "admin-addr",
+        value_parser = parse_admin_addr,
+        global = true,
+        help = "The address of the admin server as HOST:PORT, overriding --host and --port (e.g. [::1]:4191)",
+        conflicts_with_all = ["port", "host", "ready_url"],
+        env = "LINKERD_AWAIT_ADMIN_ADDR"
+    )]
+    admin_addr: Option<(String, u16)>,
+
+    #[clap(
+        long = "admin-uds",
+        global = true,
+        help = "Sends requests to the admin server over this Unix domain socket instead of TCP",
+        conflicts_with_all = ["ready_url", "scheme", "ca_file", "insecure_skip_verify", "tls_cert"],
+        env = "LINKERD_AWAIT_ADMIN_UDS"
+    )]
+    admin_uds: Option<PathBuf>,
+
+    #[clap(
+        long = "annotations-file",
+        global = true,
+        help = "A downward API file of the pod's annotations, which may set the admin port, disable Linkerd, or make the proxy a native sidecar",
+        env = "LINKERD_AWAIT_ANNOTATIONS_FILE"
+    )]
+    annotations_file: Option<PathBuf>,
+
+    #[clap(
+        long = "ready-url",
+        value_parser = parse_ready_url,
+        global = true,
+        help = "The URL of the readiness endpoint, overriding --host, --port, and --ready-path (e.g. http://127.0.0.1:4191/ready)",
+        conflicts_with_all = ["port", "host"],
+        env = "LINKERD_AWAIT_READY_URL"
+    )]
+    ready_url: Option<http::Uri>,
+
+    #[clap(
+        long = "ready-path",
+        value_parser = parse_ready_path,
+        global = true,
+        help = "The path of the admin server's readiness endpoint (e.g. /live) [default: the mesh's, e.g. /ready]",
+        conflicts_with = "ready_url",
+        env = "LINKERD_AWAIT_READY_PATH"
+    )]
+    ready_path: Option<String>,
+
+    #[clap(
+        long = "mesh",
+        value_enum,
+        default_value = "linkerd",
+        global = true,
+        help = "The service mesh whose sidecar is awaited and shut down",
+        env = "LINKERD_AWAIT_MESH"
+    )]
+    mesh: Mesh,
+
+    #[clap(
+        long = "sidecar",
+        value_parser = parse_ready_url,
+        help = "The readiness URL of another sidecar that must also be ready, e.g. http://127.0.0.1:8200/v1/sys/health (may be repeated)",
+        env = "LINKERD_AWAIT_SIDECAR"
+    )]
+    sidecar: Vec<http::Uri>,
+
+    #[clap(
+        long = "tcp",
+        value_parser = parse_tcp_addr,
+        help = "A HOST:PORT address that must accept TCP connections, e.g. postgres:5432 (may be repeated)",
+        env = "LINKERD_AWAIT_TCP"
+    )]
+    tcp: Vec<String>,
+
+    #[clap(
+        long = "http",
+        value_parser = parse_http_probe,
+        help = "A URL that must respond with a 2xx status, e.g. http://api:8080/healthz; a fragment may set the expected statuses and body, e.g. #status=200,204&body=ok (may be repeated)",
+        env = "LINKERD_AWAIT_HTTP"
+    )]
+    http: Vec<HttpProbe>,
+
+    #[clap(
+        long = "grpc",
+        value_parser = parse_grpc_probe,
+        help = "A HOST:PORT[/SERVICE] gRPC server that must report SERVING through the health checking protocol, over cleartext HTTP/2 (may be repeated)",
+        env = "LINKERD_AWAIT_GRPC"
+    )]
+    grpc: Vec<(http::Uri, String)>,
+
+    #[clap(
+        long = "dns",
+        value_parser = parse_dns_probe,
+        help = "A NAME[/COUNT] host name that must resolve to at least COUNT addresses [default: 1] (may be repeated)",
+        env = "LINKERD_AWAIT_DNS"
+    )]
+    dns: Vec<(String, usize)>,
+
+    #[clap(
+        long = "file",
+        help = "A file that must exist and not be empty, e.g. a secret projected by a CSI driver (may be repeated)",
+        env = "LINKERD_AWAIT_FILE"
+    )]
+    file: Vec<PathBuf>,
+
+    #[clap(
+        long = "unix-socket",
+        help = "A Unix domain socket that must accept connections, e.g. /run/php-fpm.sock (may be repeated)",
+        env = "LINKERD_AWAIT_UNIX_SOCKET"
+    )]
+    unix_socket: Vec<PathBuf>,
+
+    #[clap(
+        long = "exec-probe",
+        value_parser = parse_exec_probe,
+        help = "A command, split into words as by a shell, that must exit successfully, e.g. 'pg_isready -h db' (may be repeated)",
+        env = "LINKERD_AWAIT_EXEC_PROBE"
+    )]
+    exec_probe: Vec<ExecProbe>,
+
+    #[clap(
+        long = "exec-probe-timeout",
+        default_value = "1s",
+        value_parser = parse_duration,
+        help = "Time after which an --exec-probe command is killed and the attempt fails",
+        env = "LINKERD_AWAIT_EXEC_PROBE_TIMEOUT"
+    )]
+    exec_probe_timeout: time::Duration,
+
+    #[clap(
+        long = "postgres",
+        value_parser = parse_postgres_url,
+        help = "A postgres://[USER@]HOST[:PORT][/DATABASE] server that must accept connections, as pg_isready checks (may be repeated)",
+        env = "LINKERD_AWAIT_POSTGRES"
+    )]
+    postgres: Vec<check::DatabaseUrl>,
+
+    #[clap(
+        long = "mysql",
+        value_parser = parse_mysql_url,
+        help = "A mysql://HOST[:PORT] server that must greet clients, as mysqladmin ping checks (may be repeated)",
+        env = "LINKERD_AWAIT_MYSQL"
+    )]
+    mysql: Vec<check::DatabaseUrl>,
+
+    #[clap(
+        long = "redis",
+        value_parser = parse_redis_url,
+        help = "A redis://[[USER]:PASSWORD@]HOST[:PORT] server that must reply to PING (may be repeated)",
+        env = "LINKERD_AWAIT_REDIS"
+    )]
+    redis: Vec<check::DatabaseUrl>,
+
+    #[clap(
+        long = "vault-agent-token",
+        help = "A Vault Agent token sink that must hold a token before CMD runs",
+        default_missing_value = DEFAULT_VAULT_TOKEN,
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_VAULT_AGENT_TOKEN"
+    )]
+    vault_agent_token: Option<PathBuf>,
+
+    #[clap(
+        long = "cloud-sql-proxy",
+        value_parser = parse_cloud_sql_proxy,
+        help = "Waits for a Cloud SQL Auth Proxy sidecar's readiness URL, or for a PORT on which it accepts connections",
+        default_missing_value = DEFAULT_CLOUD_SQL_PROXY_READY,
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_CLOUD_SQL_PROXY"
+    )]
+    cloud_sql_proxy: Option<CloudSqlProxy>,
+
+    #[clap(
+        long = "require",
+        value_parser = parse_require,
+        help = "A dependency expression of all(...) and any(...) groups of FLAG=VALUE probes, e.g. 'any(postgres=postgres://primary, postgres=postgres://replica)' (may be repeated)",
+        env = "LINKERD_AWAIT_REQUIRE"
+    )]
+    require: Vec<Probe>,
+
+    #[clap(
+        long = "stage",
+        value_parser = parse_stage,
+        help = "A comma-separated list of probes, as in --require, that must succeed after the proxy, dependencies, and previous stages (may be repeated)",
+        env = "LINKERD_AWAIT_STAGE"
+    )]
+    stage: Vec<Probe>,
+
+    #[clap(
+        long = "probe-concurrency",
+        default_value = "8",
+        value_parser = parse_count,
+        help = "The number of probes, and the proxy's readiness check, that are attempted at once",
+        env = "LINKERD_AWAIT_PROBE_CONCURRENCY"
+    )]
+    probe_concurrency: u32,
+
+    #[clap(
+        long = "scheme",
+        default_value = "http",
+        value_parser = parse_scheme,
+        global = true,
+        help = "The scheme of the admin server, http or https",
+        conflicts_with = "ready_url",
+        env = "LINKERD_AWAIT_SCHEME"
+    )]
+    scheme: http::uri::Scheme,
+
+    #[clap(
+        long = "ca-file",
+        global = true,
+        help = "A PEM file of CA certificates that verify an https admin server, instead of the built-in roots",
+        env = "LINKERD_AWAIT_CA_FILE"
+    )]
+    ca_file: Option<PathBuf>,
+
+    #[clap(
+        long = "insecure-skip-verify",
+        global = true,
+        help = "Accepts any certificate from an https admin server",
+        conflicts_with = "ca_file",
+        env = "LINKERD_AWAIT_INSECURE_SKIP_VERIFY"
+    )]
+    insecure_skip_verify: bool,
+
+    #[clap(
+        long = "tls-cert",
+        global = true,
+        help = "A PEM file of the client certificate chain presented to an https admin server",
+        requires = "tls_key",
+        env = "LINKERD_AWAIT_TLS_CERT"
+    )]
+    tls_cert: Option<PathBuf>,
+
+    #[clap(
+        long = "tls-key",
+        global = true,
+        help = "A PEM file of the private key for --tls-cert",
+        requires = "tls_cert",
+        env = "LINKERD_AWAIT_TLS_KEY"
+    )]
+    tls_key: Option<PathBuf>,
+
+    #[clap(
+        long = "http2",
+        global = true,
+        help = "Sends requests to a cleartext admin server with HTTP/2 (h2c, with prior knowledge) instead of HTTP/1.1",
+        env = "LINKERD_AWAIT_HTTP2"
+    )]
+    http2: bool,
+
+    #[clap(
+        long = "expect-status",
+        global = true,
+        help = "The readiness endpoint's statuses that indicate readiness, as a list of statuses or ranges (e.g. 200,204 or 200-299,503) [default: 200-299]",
+        env = "LINKERD_AWAIT_EXPECT_STATUS"
+    )]
+    expect_status: Option<check::StatusSet>,
+
+    #[clap(
+        long = "expect-body",
+        global = true,
+        help = "Text that the readiness endpoint's response body must contain (matched as a substring)",
+        env = "LINKERD_AWAIT_EXPECT_BODY"
+    )]
+    expect_body: Option<String>,
+
+    #[clap(
+        short = 'H',
+        long = "header",
+        value_parser = parse_header,
+        global = true,
+        help = "A header sent with requests to the admin server, as 'Name: value' (may be repeated)",
+        env = "LINKERD_AWAIT_HEADER"
+    )]
+    header: Vec<(http::HeaderName, http::HeaderValue)>,
+
+    #[clap(
+        long = "resolve",
+        value_parser = parse_resolve,
+        global = true,
+        help = "Resolves HOST to IP instead of using DNS, as HOST=IP (may be repeated)",
+        env = "LINKERD_AWAIT_RESOLVE"
+    )]
+    resolve: Vec<(String, IpAddr)>,
+
+    #[clap(
+        long = "dns-server",
+        value_parser = parse_dns_server,
+        global = true,
+        help = "Resolves host names through this DNS server (IP[:PORT]) instead of the system resolver",
+        env = "LINKERD_AWAIT_DNS_SERVER"
+    )]
+    dns_server: Option<SocketAddr>,
+
+    #[clap(
+        short = 'b',
+        long = "backoff",
+        default_value = "1s",
+        value_parser = parse_duration,
+        global = true,
+        help = "Time to wait after a failed readiness check",
+        env = "LINKERD_AWAIT_BACKOFF"
+    )]
+    backoff: time::Duration,
+
+    #[clap(
+        long = "backoff-strategy",
+        value_enum,
+        default_value = "constant",
+        global = true,
+        help = "How the time between failed readiness checks grows",
+        env = "LINKERD_AWAIT_BACKOFF_STRATEGY"
+    )]
+    backoff_strategy: BackoffStrategy,
+
+    #[clap(
+        long = "backoff-max",
+        value_parser = parse_duration,
+        global = true,
+        help = "The maximum time to wait between readiness checks",
+        env = "LINKERD_AWAIT_BACKOFF_MAX"
+    )]
+    backoff_max: Option<time::Duration>,
+
+    #[clap(
+        long = "backoff-multiplier",
+        value_parser = parse_multiplier,
+        global = true,
+        help = "The factor by which the exponential --backoff-strategy grows [default: 2]",
+        env = "LINKERD_AWAIT_BACKOFF_MULTIPLIER"
+    )]
+    backoff_multiplier: Option<f64>,
+
+    #[clap(
+        long = "backoff-jitter",
+        value_parser = parse_percent,
+        global = true,
+        help = "Randomly shortens each wait by up to this percentage so that pods don't check in lockstep (e.g. 20%)",
+        env = "LINKERD_AWAIT_BACKOFF_JITTER"
+    )]
+    backoff_jitter: Option<u8>,
+
+    #[clap(
+        long = "connect-backoff",
+        value_parser = parse_duration,
+        help = "Time to wait after a readiness check fails to connect to the admin server [default: --backoff]",
+        env = "LINKERD_AWAIT_CONNECT_BACKOFF"
+    )]
+    connect_backoff: Option<time::Duration>,
+
+    #[clap(
+        long = "check-interval",
+        value_parser = parse_duration,
+        help = "Checks on a fixed schedule, measured from the start of each check, instead of backing off",
+        conflicts_with_all = ["backoff", "backoff_strategy", "backoff_max", "connect_backoff"],
+        env = "LINKERD_AWAIT_CHECK_INTERVAL"
+    )]
+    check_interval: Option<time::Duration>,
+
+    #[clap(
+        long = "use-env-proxy",
+        global = true,
+        help = "Sends admin requests through the proxy configured by HTTP_PROXY, honoring NO_PROXY",
+        env = "LINKERD_AWAIT_USE_ENV_PROXY"
+    )]
+    use_env_proxy: bool,
+
+    #[clap(
+        long = "verify-proxy",
+        help = "Verifies that the admin server is a Linkerd proxy's before trusting its readiness",
+        env = "LINKERD_AWAIT_VERIFY_PROXY"
+    )]
+    verify_proxy: bool,
+
+    #[clap(
+        long = "verify-listeners",
+        help = "Also waits until the proxy's inbound and outbound listeners accept TCP connections",
+        env = "LINKERD_AWAIT_VERIFY_LISTENERS"
+    )]
+    verify_listeners: bool,
+
+    #[clap(
+        long = "require-identity",
+        help = "Also waits until the proxy has been issued its identity certificate, as reported by its metrics",
+        env = "LINKERD_AWAIT_REQUIRE_IDENTITY"
+    )]
+    require_identity: bool,
+
+    #[clap(
+        long = "check-live",
+        help = "Also requires the proxy's /live endpoint to succeed, reporting each endpoint's status",
+        env = "LINKERD_AWAIT_CHECK_LIVE"
+    )]
+    check_live: bool,
+
+    #[clap(
+        long = "metric",
+        help = "A condition on the proxy's metrics that must hold, e.g. 'name{label=\"value\"} >= 1' (may be repeated)",
+        env = "LINKERD_AWAIT_METRIC"
+    )]
+    metric: Vec<check::MetricCondition>,
+
+    #[clap(
+        long = "min-proxy-version",
+        help = "Fails with exit code 78 unless the proxy's version, as reported by its metrics, is at least X.Y.Z",
+        env = "LINKERD_AWAIT_MIN_PROXY_VERSION"
+    )]
+    min_proxy_version: Option<check::ProxyVersion>,
+
+    #[clap(
+        long = "inbound-port",
+        help = "The port of the proxy's inbound listener, for --verify-listeners [default: 4143, or the pod's config.linkerd.io/inbound-port annotation]",
+        env = "LINKERD_AWAIT_INBOUND_PORT"
+    )]
+    inbound_port: Option<u16>,
+
+    #[clap(
+        long = "outbound-port",
+        help = "The port of the proxy's outbound listener, for --verify-listeners and --pre-resolve [default: 4140, or the pod's config.linkerd.io/outbound-port annotation]",
+        env = "LINKERD_AWAIT_OUTBOUND_PORT"
+    )]
+    outbound_port: Option<u16>,
+
+    #[clap(
+        long = "pre-resolve",
+        value_parser = parse_pre_resolve,
+        help = "A HOST:PORT HTTP destination that is resolved through the proxy, with a HEAD request to its outbound listener, once the proxy is ready (may be repeated)",
+        env = "LINKERD_AWAIT_PRE_RESOLVE"
+    )]
+    pre_resolve: Vec<http::Uri>,
+
+    #[clap(
+        long = "warmup",
+        value_parser = parse_ready_url,
+        help = "A URL that is sent GET requests through the proxy once it's ready, before CMD runs, e.g. to establish connections (may be repeated)",
+        env = "LINKERD_AWAIT_WARMUP"
+    )]
+    warmup: Vec<http::Uri>,
+
+    #[clap(
+        long = "warmup-requests",
+        default_value = "1",
+        value_parser = parse_count,
+        help = "The number of requests sent to each --warmup URL",
+        env = "LINKERD_AWAIT_WARMUP_REQUESTS"
+    )]
+    warmup_requests: u32,
+
+    #[clap(
+        long = "plugin-check",
+        help = "An executable that must also succeed before the proxy is considered ready (may be repeated)",
+        env = "LINKERD_AWAIT_PLUGIN_CHECK"
+    )]
+    plugin_checks: Vec<PathBuf>,
+
+    #[clap(
+        long = "wait-port-free",
+        help = "A local TCP port that nothing may be listening on before CMD runs (may be repeated)",
+        env = "LINKERD_AWAIT_WAIT_PORT_FREE"
+    )]
+    wait_port_free: Vec<u16>,
+
+    #[clap(
+        long = "wait-for-route",
+        help = "A route (`default` or an IP address) that must be configured first (may be repeated)",
+        env = "LINKERD_AWAIT_WAIT_FOR_ROUTE"
+    )]
+    wait_for_routes: Vec<check::Route>,
+
+    #[clap(
+        long = "wait-for-interface",
+        value_parser = parse_interface,
+        help = "A network interface that must be up with an address first (may be repeated)",
+        env = "LINKERD_AWAIT_WAIT_FOR_INTERFACE"
+    )]
+    wait_for_interfaces: Vec<String>,
+
+    #[clap(
+        long = "await-k8s",
+        help = "A Kubernetes workload (deployment/NAME, statefulset/NAME, or pod/NAME) that must be ready first (may be repeated)",
+        group = "k8s",
+        env = "LINKERD_AWAIT_AWAIT_K8S"
+    )]
+    await_k8s: Vec<Workload>,
+
+    #[clap(
+        long = "await-k8s-object",
+        help = "A Kubernetes ConfigMap or Secret (configmap/NAME[:KEY] or secret/NAME[:KEY]) that must exist first, with KEY if specified (may be repeated)",
+        group = "k8s",
+        env = "LINKERD_AWAIT_AWAIT_K8S_OBJECT"
+    )]
+    await_k8s_objects: Vec<ObjectRef>,
+
+    #[clap(
+        long = "await-lease",
+        value_parser = parse_lease_name,
+        help = "A Kubernetes Lease that must be free first",
+        group = "k8s",
+        env = "LINKERD_AWAIT_AWAIT_LEASE"
+    )]
+    await_lease: Option<String>,
+
+    #[clap(
+        long = "acquire",
+        help = "Acquires the --await-lease lease once it is free and holds it while CMD runs",
+        requires("await_lease"),
+        conflicts_with("background"),
+        env = "LINKERD_AWAIT_ACQUIRE"
+    )]
+    acquire: bool,
+
+    #[clap(
+        long = "lease-duration",
+        default_value = "15s",
+        value_parser = parse_duration,
+        help = "How long an acquired lease is held without being renewed",
+        requires("acquire"),
+        env = "LINKERD_AWAIT_LEASE_DURATION"
+    )]
+    lease_duration: time::Duration,
+
+    #[clap(
+        short = 'n',
+        long = "namespace",
+        value_parser = parse_namespace,
+        help = "The namespace of --await-k8s workloads and --await-k8s-object objects [default: the pod's namespace]",
+        requires("k8s"),
+        env = "LINKERD_AWAIT_NAMESPACE"
+    )]
+    namespace: Option<String>,
+
+    #[clap(
+        short = 'S',
+        long = "shutdown",
+        help = "Forks the program and triggers proxy shutdown on completion",
+        requires("CMD"),
+        env = "LINKERD_AWAIT_SHUTDOWN"
+    )]
+    shutdown: bool,
+
+    #[clap(
+        long = "shutdown-url",
+        value_parser = parse_ready_url,
+        global = true,
+        help = "The URL of the endpoint that shuts the sidecar down, overriding the mesh's (e.g. http://127.0.0.1:15000/quitquitquit)",
+        env = "LINKERD_AWAIT_SHUTDOWN_URL"
+    )]
+    shutdown_url: Option<http::Uri>,
+
+    #[clap(
+        long = "shutdown-path",
+        value_parser = parse_ready_path,
+        global = true,
+        help = "The path of the admin server's shutdown endpoint (e.g. /quitquitquit) [default: the mesh's, e.g. /shutdown]",
+        conflicts_with = "shutdown_url",
+        env = "LINKERD_AWAIT_SHUTDOWN_PATH"
+    )]
+    shutdown_path: Option<String>,
+
+    #[clap(
+        long = "shutdown-method",
+        value_parser = parse_method,
+        global = true,
+        help = "The method of shutdown requests [default: the mesh's, e.g. POST]",
+        env = "LINKERD_AWAIT_SHUTDOWN_METHOD"
+    )]
+    shutdown_method: Option<http::Method>,
+
+    #[clap(
+        long = "shutdown-delay",
+        value_parser = parse_duration,
+        global = true,
+        help = "Time to wait after CMD exits before shutting down the proxy, e.g. so that telemetry and in-flight requests complete",
+        env = "LINKERD_AWAIT_SHUTDOWN_DELAY"
+    )]
+    shutdown_delay: Option<time::Duration>,
+
+    #[clap(
+        long = "drain-time",
+        value_parser = parse_duration,
+        global = true,
+        help = "Time the sidecar's listeners are given to drain before it's shut down, for meshes whose sidecar drains first, e.g. app-mesh [default: 5s]",
+        env = "LINKERD_AWAIT_DRAIN_TIME"
+    )]
+    drain_time: Option<time::Duration>,
+
+    #[clap(
+        long = "shutdown-on-success-only",
+        value_parser = parse_duration,
+        help = "Keeps the proxy running for up to this long (default 5m) before shutting it down if CMD fails, e.g. for postmortem tooling",
+        default_missing_value = "5m",
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_SHUTDOWN_ON_SUCCESS_ONLY"
+    )]
+    shutdown_on_success_only: Option<time::Duration>,
+
+    #[clap(
+        long = "verify-shutdown",
+        value_parser = parse_duration,
+        global = true,
+        help = "Waits up to this long (default 10s) for the admin server to stop answering after the shutdown request, failing if it doesn't",
+        default_missing_value = "10s",
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_VERIFY_SHUTDOWN"
     )]
-    port: u16,
+    verify_shutdown: Option<time::Duration>,
+
+    #[clap(
+        long = "shutdown-timeout",
+        value_parser = parse_duration,
+        global = true,
+        help = "How long a failed shutdown request is retried, with --backoff, before its error is reported [default: 10s]",
+        env = "LINKERD_AWAIT_SHUTDOWN_TIMEOUT"
+    )]
+    shutdown_timeout: Option<time::Duration>,
+
+    #[clap(
+        long = "signal-proxy",
+        global = true,
+        help = "Sends SIGTERM to the processes with this name (default linkerd2-proxy) if the shutdown request fails; requires shareProcessNamespace",
+        default_missing_value = "linkerd2-proxy",
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_SIGNAL_PROXY"
+    )]
+    signal_proxy: Option<String>,
+
+    #[clap(
+        long = "also-shutdown",
+        value_parser = parse_ready_url,
+        global = true,
+        help = "The URL of another sidecar's shutdown endpoint, which is sent a POST once CMD completes, e.g. http://127.0.0.1:15020/quitquitquit (may be repeated)",
+        env = "LINKERD_AWAIT_ALSO_SHUTDOWN"
+    )]
+    also_shutdown: Vec<http::Uri>,
+
+    #[clap(
+        long = "cloud-sql-proxy-shutdown",
+        value_parser = parse_ready_url,
+        global = true,
+        help = "Shuts down a Cloud SQL Auth Proxy sidecar started with --quitquitquit once CMD completes, through this URL",
+        default_missing_value = DEFAULT_CLOUD_SQL_PROXY_SHUTDOWN,
+        num_args(0..=1),
+        require_equals(true),
+        env = "LINKERD_AWAIT_CLOUD_SQL_PROXY_SHUTDOWN"
+    )]
+    cloud_sql_proxy_shutdown: Option<http::Uri>,
+
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        help = "Causes linkerd-await to print an error message when disabled",
+        env = "LINKERD_AWAIT_VERBOSE"
+    )]
+    verbose: bool,
+
+    #[clap(
+        short = 't',
+        long = "timeout",
+        value_parser = parse_duration,
+        help = "Causes linked-await to fail when the timeout elapses before the proxy becomes ready",
+        env = "LINKERD_AWAIT_TIMEOUT"
+    )]
+    timeout: Option<time::Duration>,
+
+    #[clap(
+        long = "max-attempts",
+        value_parser = parse_count,
+        help = "Fails once the proxy isn't ready after this many readiness checks",
+        env = "LINKERD_AWAIT_MAX_ATTEMPTS"
+    )]
+    max_attempts: Option<u32>,
+
+    #[clap(
+        long = "success-threshold",
+        default_value = "1",
+        value_parser = parse_count,
+        help = "The number of consecutive successful readiness checks required",
+        env = "LINKERD_AWAIT_SUCCESS_THRESHOLD"
+    )]
+    success_threshold: u32,
+
+    #[clap(
+        long = "success-interval",
+        value_parser = parse_duration,
+        help = "Time to wait after a successful readiness check until --success-threshold is reached [default: --check-interval or --backoff]",
+        env = "LINKERD_AWAIT_SUCCESS_INTERVAL"
+    )]
+    success_interval: Option<time::Duration>,
+
+    #[clap(
+        long = "timeout-warn",
+        value_parser = parse_percent,
+        help = "Prints a warning when the proxy isn't ready after this percentage of the timeout (e.g. 50%)",
+        requires("timeout"),
+        env = "LINKERD_AWAIT_TIMEOUT_WARN"
+    )]
+    timeout_warn: Option<u8>,
+
+    #[clap(
+        long = "timeout-warn-webhook",
+        help = "An HTTP URL that is sent a JSON event along with the --timeout-warn warning",
+        requires("timeout_warn"),
+        env = "LINKERD_AWAIT_TIMEOUT_WARN_WEBHOOK"
+    )]
+    timeout_warn_webhook: Option<http::Uri>,
+
+    #[clap(
+        long = "deadline",
+        value_parser = parse_duration,
+        help = "Bounds the total time spent waiting for the proxy and shutting it down, excluding CMD's runtime",
+        env = "LINKERD_AWAIT_DEADLINE"
+    )]
+    deadline: Option<time::Duration>,
+
+    #[clap(
+        long = "initial-delay",
+        value_parser = parse_duration,
+        help = "Time to wait before the first readiness check",
+        env = "LINKERD_AWAIT_INITIAL_DELAY"
+    )]
+    initial_delay: Option<time::Duration>,
+
+    #[clap(
+        long = "post-ready-delay",
+        value_parser = parse_duration,
+        help = "Time to wait after the proxy becomes ready before running CMD",
+        env = "LINKERD_AWAIT_POST_READY_DELAY"
+    )]
+    post_ready_delay: Option<time::Duration>,
+
+    #[clap(
+        long,
+        help = "Controls whether a readiness timeout failure prevents CMD from running [default: true]",
+        default_missing_value("true"),
+        num_args(0..=1),
+        require_equals(true),
+        action = clap::ArgAction::Set,
+        requires("CMD"),
+        env = "LINKERD_AWAIT_TIMEOUT_FATAL"
+    )]
+    timeout_fatal: Option<bool>,
+
+    #[clap(
+        long = "background",
+        help = "Runs CMD immediately and notifies it once the proxy becomes ready",
+        requires("CMD"),
+        env = "LINKERD_AWAIT_BACKGROUND"
+    )]
+    background: bool,
+
+    #[clap(
+        long = "notify-signal",
+        value_parser = parse_signal,
+        help = "The signal sent to CMD in --background mode once the proxy is ready [default: SIGUSR1]",
+        requires("background"),
+        env = "LINKERD_AWAIT_NOTIFY_SIGNAL"
+    )]
+    notify_signal: Option<Signal>,
+
+    #[clap(
+        long = "notify-file",
+        help = "A file created in --background mode once the proxy is ready",
+        requires("background"),
+        env = "LINKERD_AWAIT_NOTIFY_FILE"
+    )]
+    notify_file: Option<PathBuf>,
+
+    #[clap(
+        long = "forward-signals",
+        value_parser = parse_forwarded_signal,
+        value_delimiter = ',',
+        help = "Signals forwarded to CMD in --shutdown and --background modes (comma-separated) [default: TERM,INT]",
+        env = "LINKERD_AWAIT_FORWARD_SIGNALS"
+    )]
+    forward_signals: Vec<Signal>,
+
+    #[clap(
+        long = "block-signals",
+        value_parser = parse_forwarded_signal,
+        value_delimiter = ',',
+        help = "Signals that are neither forwarded to CMD nor allowed to terminate linkerd-await (comma-separated)",
+        env = "LINKERD_AWAIT_BLOCK_SIGNALS"
+    )]
+    block_signals: Vec<Signal>,
+
+    #[clap(
+        long = "pid-file",
+        help = "A file to which CMD's PID is written once it starts",
+        requires("CMD"),
+        env = "LINKERD_AWAIT_PID_FILE"
+    )]
+    pid_file: Option<PathBuf>,
+
+    #[clap(
+        long = "ready-file",
+        help = "A file written with a JSON timestamp once the proxy is ready, e.g. for other containers to wait on",
+        env = "LINKERD_AWAIT_READY_FILE"
+    )]
+    ready_file: Option<PathBuf>,
+
+    #[clap(
+        long = "barrier",
+        help = "A directory shared by a pod's containers in which startup markers are written",
+        env = "LINKERD_AWAIT_BARRIER"
+    )]
+    barrier: Option<PathBuf>,
+
+    #[clap(
+        long = "barrier-name",
+        value_parser = parse_marker,
+        help = "The name of the marker written in the --barrier directory once ready",
+        requires("barrier"),
+        env = "LINKERD_AWAIT_BARRIER_NAME"
+    )]
+    barrier_name: Option<String>,
+
+    #[clap(
+        long = "barrier-wait-for",
+        value_parser = parse_marker,
+        value_delimiter = ',',
+        help = "Markers in the --barrier directory that must be written first (comma-separated)",
+        requires("barrier"),
+        env = "LINKERD_AWAIT_BARRIER_WAIT_FOR"
+    )]
+    barrier_wait_for: Vec<String>,
+
+    #[clap(
+        long = "decode-secret",
+        help = "Copies a mounted secret to where CMD expects it, as SRC:DST[:base64], decoding it if base64 is set (may be repeated)",
+        env = "LINKERD_AWAIT_DECODE_SECRET"
+    )]
+    decode_secrets: Vec<DecodeSecret>,
+
+    #[clap(
+        long = "secret-mode",
+        default_value = "0600",
+        value_parser = parse_mode,
+        help = "The permissions of files written by --decode-secret, in octal",
+        env = "LINKERD_AWAIT_SECRET_MODE"
+    )]
+    secret_mode: u32,
+
+    #[clap(
+        long = "lock-file",
+        help = "A file that is locked to prevent multiple instances from running concurrently",
+        env = "LINKERD_AWAIT_LOCK_FILE"
+    )]
+    lock_file: Option<PathBuf>,
+
+    #[clap(
+        long = "strict",
+        help = "Fails instead of warning when flags are combined in ways that have no effect",
+        env = "LINKERD_AWAIT_STRICT"
+    )]
+    strict: bool,
+
+    #[clap(
+        long = "config",
+        help = "A TOML or YAML file of settings named after flags, which the command line and environment override",
+        env = "LINKERD_AWAIT_CONFIG"
+    )]
+    config: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(name = "CMD", help = "The command to run after linkerd is ready")]
+    cmd: Option<String>,
+
+    #[clap(name = "ARGS", help = "Arguments to pass to CMD if specified")]
+    args: Vec<String>,
+}
+
+/// The admin server's flags are global, so that the subcommands that talk to
+/// the proxy locate it as linkerd-await does.
+#[derive(Clone, Debug, clap::Subcommand)]
+enum Command {
+    /// Checks the proxy's readiness once, without retrying, exiting 0 if it's
+    /// ready and 1 otherwise.
+    Check(CheckProxy),
+
+    /// Checks the proxy, and optionally the application, once with a strict
+    /// time budget, for use as a Docker HEALTHCHECK.
+    Healthcheck(Healthcheck),
+
+    /// Prints a strategic merge patch that wraps a workload's container with
+    /// linkerd-await.
+    #[clap(name = "k8s-snippet")]
+    K8sSnippet(K8sSnippet),
+
+    /// Asks the proxy to shut down, retrying until it accepts, for use as a
+    /// container's preStop hook.
+    Shutdown(ShutdownProxy),
+
+    /// Waits for hosts and ports before running a command, as wait-for-it.sh
+    /// and dockerize do, without awaiting the proxy.
+    #[clap(name = "wait-for")]
+    WaitFor(WaitFor),
+}
+
+#[derive(Clone, Debug, clap::Args)]
+struct Healthcheck {
+    #[clap(
+        long = "app-url",
+        value_parser = parse_ready_url,
+        help = "An application URL that must also respond successfully (e.g. http://localhost:8080/healthz)"
+    )]
+    app_url: Option<http::Uri>,
+
+    #[clap(
+        short = 't',
+        long = "timeout",
+        default_value = "2s",
+        value_parser = parse_duration,
+        help = "The time within which all probes must succeed"
+    )]
+    timeout: time::Duration,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+struct CheckProxy {
+    #[clap(
+        short = 't',
+        long = "timeout",
+        default_value = "1s",
+        value_parser = parse_duration,
+        help = "The time within which the check must succeed"
+    )]
+    timeout: time::Duration,
+}
+
+/// The sidecar is shut down as it is once CMD completes, so the shutdown
+/// flags are global, too.
+#[derive(Clone, Debug, clap::Args)]
+struct ShutdownProxy {}
+
+#[derive(Clone, Debug, clap::Args)]
+struct K8sSnippet {
+    #[clap(
+        short = 'c',
+        long = "container",
+        help = "The name of the container to wrap"
+    )]
+    container: String,
+
+    #[clap(
+        long = "path",
+        default_value = "/linkerd-await",
+        help = "Where linkerd-await is installed in the container's image"
+    )]
+    path: String,
+
+    #[clap(
+        short = 'o',
+        long = "output",
+        value_enum,
+        default_value = "yaml",
+        help = "The format of the patch"
+    )]
+    output: SnippetFormat,
+
+    #[clap(
+        short = 'S',
+        long = "shutdown",
+        help = "Shuts the proxy down once the container's command completes"
+    )]
+    shutdown: bool,
+
+    #[clap(
+        short = 't',
+        long = "timeout",
+        value_parser = parse_duration,
+        help = "Fails when the timeout elapses before the proxy becomes ready"
+    )]
+    timeout: Option<time::Duration>,
+
+    #[clap(
+        name = "CMD",
+        last = true,
+        help = "The container's command and its arguments"
+    )]
+    cmd: Vec<String>,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+struct WaitFor {
+    #[clap(
+        name = "HOST:PORT",
+        required = true,
+        value_parser = parse_wait_for_target,
+        help = "A HOST:PORT (or tcp://, http://, https://, unix://, or file:// URL) that must become available"
+    )]
+    targets: Vec<Probe>,
+
+    #[clap(
+        short = 't',
+        long = "timeout",
+        default_value = "15",
+        value_parser = parse_wait_for_timeout,
+        help = "The number of seconds, or a duration, to wait; 0 waits indefinitely"
+    )]
+    timeout: time::Duration,
+
+    #[clap(
+        short = 's',
+        long = "strict",
+        help = "Runs CMD only if every target becomes available; otherwise CMD runs once the timeout elapses"
+    )]
+    strict: bool,
+
+    #[clap(short = 'q', long = "quiet", help = "Doesn't print status messages")]
+    quiet: bool,
+
+    #[clap(
+        name = "CMD",
+        last = true,
+        help = "The command to run and its arguments"
+    )]
+    cmd: Vec<String>,
+}
+
+/// The service mesh whose sidecar is awaited. Each mesh's sidecar serves its
+/// readiness and shutdown endpoints on well-known ports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Mesh {
+    Linkerd,
+    Istio,
+    Envoy,
+    Consul,
+    Dapr,
+    #[value(name = "app-mesh")]
+    AppMeshEnvoy,
+}
+
+/// How a sidecar is awaited and shut down. Each [`Mesh`] has a built-in
+/// profile, whose fields flags (or a config file) may override.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Profile {
+    /// The port of the readiness endpoint, unless `--port` is set.
+    ready_port: u16,
+    /// The path of the readiness endpoint, unless `--ready-path` is set.
+    ready_path: &'static str,
+    /// The statuses that indicate readiness, unless `--expect-status` is set.
+    expected_status: check::StatusSet,
+    /// The port of the shutdown endpoint, if it isn't served with the
+    /// readiness endpoint.
+    shutdown_port: Option<u16>,
+    /// The path of the shutdown endpoint, unless `--shutdown-url` is set.
+    shutdown_path: &'static str,
+    /// The method of shutdown requests, unless `--shutdown-method` is set.
+    shutdown_method: http::Method,
+    /// The path, on the shutdown endpoint's server, that is sent a `POST` so
+    /// that the sidecar drains its listeners before it's shut down.
+    drain_path: Option<&'static str>,
+}
+
+/// An `--http` probe. The URL's fragment, which is never sent, sets the
+/// responses that indicate success.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HttpProbe {
+    uri: http::Uri,
+    expected_status: Option<check::StatusSet>,
+    expected_body: Option<String>,
+}
+
+/// How a Cloud SQL Auth Proxy sidecar is awaited: through its readiness
+/// endpoint or, when its health checks aren't enabled, by connecting to the
+/// port on which it proxies the database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CloudSqlProxy {
+    Ready(http::Uri),
+    Port(u16),
+}
+
+/// An `--exec-probe` command and its arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExecProbe {
+    cmd: String,
+    args: Vec<String>,
+}
+
+/// A dependency that must be ready, as configured by a probe flag or within
+/// a `--require` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Probe {
+    Sidecar(http::Uri),
+    Tcp(String),
+    Http(HttpProbe),
+    Grpc(http::Uri, String),
+    Dns(String, usize),
+    File(PathBuf),
+    UnixSocket(PathBuf),
+    Exec(ExecProbe),
+    Database(check::DatabaseUrl),
+    VaultToken(PathBuf),
+    All(Vec<Probe>),
+    Any(Vec<Probe>),
+    Retry(Box<Probe>, ProbeRetry),
+}
+
+/// Overrides how a probe is retried, for a `retry(...)` expression.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ProbeRetry {
+    timeout: Option<time::Duration>,
+    backoff: Option<time::Duration>,
+    max_attempts: Option<u32>,
+}
+
+/// Builds the checks of probes.
+struct Prober {
+    connector: proxy::Connector,
+    resolver: Resolver,
+    exec_timeout: time::Duration,
+    /// The number of checks in a group that are attempted at once.
+    concurrency: usize,
+}
+
+/// The mesh's admin server, as located by the admin server's flags or, if
+/// they don't, by the pod's annotations or the proxy's environment.
+struct AdminServer {
+    /// The readiness endpoint. The admin server's other endpoints are served
+    /// at other paths of the same URI.
+    ready_uri: http::Uri,
+    /// Connects to the admin server, e.g. with its TLS settings.
+    connector: proxy::Connector,
+    /// Sent with each request to the admin server.
+    headers: http::HeaderMap,
+    expected_status: check::StatusSet,
+    expected_body: Option<String>,
+    /// Only the Linkerd proxy's admin server is known to serve its readiness
+    /// endpoint as soon as it listens; other sidecars, e.g. Envoy, may answer
+    /// 404 while they start.
+    fail_fast_on_not_found: bool,
+}
+
+/// How the mesh's sidecar, and any others, are shut down once CMD completes.
+struct Shutdown {
+    admin: proxy::Connector,
+    method: http::Method,
+    uri: http::Uri,
+    headers: http::HeaderMap,
+    /// Lets CMD's outstanding work, e.g. buffered telemetry, drain through
+    /// the proxy before it's shut down.
+    delay: Option<time::Duration>,
+    /// The endpoint that makes the sidecar drain its listeners, and how long
+    /// they're given to drain, before the shutdown request is sent.
+    drain: Option<(http::Uri, time::Duration)>,
+    /// How long the proxy is kept running after CMD fails.
+    hold: Option<time::Duration>,
+    /// Paces the shutdown request's retries, for up to the timeout.
+    backoff: Backoff,
+    timeout: time::Duration,
+    /// The readiness endpoint, which `--verify-shutdown` polls until the
+    /// admin server stops accepting connections.
+    ready_uri: http::Uri,
+    verify: Option<time::Duration>,
+    /// The name of the proxy's process, which is sent SIGTERM if the
+    /// shutdown request fails.
+    signal: Option<String>,
+    /// Sends requests to the `--also-shutdown` sidecars.
+    connector: proxy::Connector,
+    also: Vec<http::Uri>,
+}
+
+/// The `--warmup` requests that are sent once the proxy is ready.
+struct Warmup {
+    connector: proxy::Connector,
+    uris: Vec<http::Uri>,
+    requests: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SnippetFormat {
+    Yaml,
+    Json,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let opts = std::env::var("LINKERD_AWAIT_OPTS").ok();
+    let result = match load_args(std::env::args_os().collect(), opts.as_deref()) {
+        Ok(args) => run(args).await,
+        Err(error) => Err(error),
+    };
+    let code = match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            error.exit_code()
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Parses the command line, applying the flags in `LINKERD_AWAIT_OPTS` and
+/// then the settings of a `--config` file.
+///
+/// Each setting is passed as a flag preceding the command line's, unless its
+/// flag is set on the command line or by its environment variable, so that
+/// settings are validated exactly as flags are.
+fn load_args(argv: Vec<OsString>, opts: Option<&str>) -> Result<Args, Error> {
+    let argv = match opts {
+        Some(opts) => with_opts(argv, opts)?,
+        None => argv,
+    };
+    let matches = Args::command().get_matches_from(&argv);
+    let path = match matches.get_one::<PathBuf>("config") {
+        Some(path) if matches.subcommand().is_none() => path.clone(),
+        _ => return Ok(Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())),
+    };
+
+    let command = Args::command();
+    let mut flags = Vec::new();
+    for setting in read_config(&path)? {
+        let invalid = |reason: String| {
+            Error::ConfigFile(
+                path.clone(),
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", setting.line, reason),
+                ),
+            )
+        };
+        let arg = command
+            .get_arguments()
+            .filter(|arg| !matches!(arg.get_long(), Some("config" | "help" | "version")))
+            .find(|arg| arg.get_long() == Some(setting.name.as_str()))
+            .ok_or_else(|| invalid(format!("unknown setting {:?}", setting.name)))?;
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        if !arg.get_action().takes_values() {
+            match setting.values.as_slice() {
+                [value] if value == "true" => flags.push(format!("--{}", setting.name)),
+                [value] if value == "false" => {}
+                _ => return Err(invalid(format!("{} must be true or false", setting.name))),
+            }
+        } else if setting.values.len() == 1 || matches!(arg.get_action(), ArgAction::Append) {
+            flags.extend(
+                setting
+                    .values
+                    .iter()
+                    .map(|value| format!("--{}={}", setting.name, value)),
+            );
+        } else {
+            return Err(invalid(format!("{} takes a single value", setting.name)));
+        }
+    }
+
+    let argv = argv
+        .iter()
+        .take(1)
+        .cloned()
+        .chain(flags.into_iter().map(OsString::from))
+        .chain(argv.iter().skip(1).cloned());
+    Ok(Args::try_parse_from(argv).unwrap_or_else(|e| e.exit()))
+}
+
+/// Inserts the flags in `LINKERD_AWAIT_OPTS` ahead of the command line's.
+/// Flags that are also set on the command line are dropped so that the
+/// command line takes precedence.
+fn with_opts(argv: Vec<OsString>, opts: &str) -> Result<Vec<OsString>, Error> {
+    let invalid = |reason: String| Error::InvalidConfig(format!("LINKERD_AWAIT_OPTS: {}", reason));
+    let words = split_opts(opts).map_err(|e| invalid(e.to_string()))?;
+    let matches = Args::command().get_matches_from(&argv);
+    if words.is_empty() || matches.subcommand().is_some() {
+        return Ok(argv);
+    }
+
+    let command = Args::command();
+    let mut flags = Vec::new();
+    let mut words = words.into_iter();
+    while let Some(word) = words.next() {
+        let (arg, has_value) = if let Some(long) = word.strip_prefix("--") {
+            let (long, value) = long
+                .split_once('=')
+                .map_or((long, None), |(l, v)| (l, Some(v)));
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(long));
+            (arg, value.is_some())
+        } else if let Some(short) = word.strip_prefix('-') {
+            let mut chars = short.chars();
+            let c = chars.next();
+            let arg = command
+                .get_arguments()
+                .find(|arg| c.is_some() && arg.get_short() == c);
+            (arg, !chars.as_str().is_empty())
+        } else {
+            return Err(invalid(format!(
+                "{:?} is not a flag; CMD must be passed on the command line",
+                word
+            )));
+        };
+        let arg = arg.ok_or_else(|| invalid(format!("unknown flag {:?}", word)))?;
+
+        let mut flag = vec![word];
+        if arg.get_action().takes_values() && !has_value && !arg.is_require_equals_set() {
+            flag.extend(words.next());
+        }
+        if matches.value_source(arg.get_id().as_str()) != Some(ValueSource::CommandLine) {
+            flags.extend(flag);
+        }
+    }
+
+    Ok(argv
+        .iter()
+        .take(1)
+        .cloned()
+        .chain(flags.into_iter().map(OsString::from))
+        .chain(argv.iter().skip(1).cloned())
+        .collect())
+}
+
+/// Runs linkerd-await, returning the exit code of the process.
+///
+/// When CMD is run without `--shutdown` or `--background`, the process is
+/// replaced by CMD and this function only returns if the command could not be
+/// executed.
+async fn run(mut args: Args) -> Result<i32, Error> {
+    let disabled_reason = linkerd_disabled_reason();
+
+    match args.command.take() {
+        Some(Command::Check(check)) => {
+            let admin = args.subcommand_admin(disabled_reason)?;
+            return check.run(admin).await;
+        }
+        Some(Command::Healthcheck(healthcheck)) => {
+            let admin = args.subcommand_admin(disabled_reason)?;
+            return healthcheck.run(admin).await;
+        }
+        Some(Command::K8sSnippet(snippet)) => {
+            if snippet.cmd.is_empty() {
+                eprintln!("Warning: no CMD was given; append the container's command and arguments to `args`");
+            }
+            println!("{}", snippet.render());
+            return Ok(0);
+        }
+        Some(Command::Shutdown(shutdown)) => {
+            let sidecars = match args.subcommand_admin(disabled_reason)? {
+                Some(server) => Some(args.sidecars(&server, &args.connector())?),
+                None => None,
+            };
+            return shutdown.run(sidecars).await;
+        }
+        Some(Command::WaitFor(wait_for)) => return wait_for.run().await,
+        None => {}
+    }
+
+    if let Some(error) = args.unsupported() {
+        return Err(Error::InvalidConfig(error));
+    }
+
+    let annotations = args.annotations()?;
+    let disabled_reason = disabled_reason.or_else(|| annotations.disabled_reason());
+
+    // Flag combinations that don't make sense are reported before we do
+    // anything else so that misconfigurations are visible in the pod's logs.
+    let mut warnings = args.validate(disabled_reason.is_some());
+    if args.shutdown && annotations.native_sidecar() {
+        warnings.push(
+            "--shutdown has no effect when the proxy is a native sidecar; Kubernetes shuts it down once CMD completes"
+                .to_string(),
+        );
+    }
+    if args.strict && !warnings.is_empty() {
+        return Err(Error::InvalidConfig(warnings.join("; ")));
+    }
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let resolver = args.resolver();
+    let connector = args.connector();
+    let server = args.admin_server(&annotations, &connector)?;
+    let backoff = args.backoff();
+    let sidecars = args.sidecars(&server, &connector)?;
+
+    let Args {
+        sidecar,
+        tcp,
+        http,
+        grpc,
+        dns,
+        file,
+        unix_socket,
+        exec_probe,
+        exec_probe_timeout,
+        postgres,
+        mysql,
+        redis,
+        vault_agent_token,
+        cloud_sql_proxy,
+        require,
+        stage,
+        probe_concurrency,
+        connect_backoff,
+        check_interval,
+        verify_proxy,
+        verify_listeners,
+        require_identity,
+        check_live,
+        metric,
+        min_proxy_version,
+        inbound_port,
+        outbound_port,
+        pre_resolve,
+        warmup,
+        warmup_requests,
+        plugin_checks,
+        wait_port_free,
+        wait_for_routes,
+        wait_for_interfaces,
+        await_k8s,
+        await_k8s_objects,
+        await_lease,
+        acquire,
+        lease_duration,
+        namespace,
+        shutdown,
+        verbose,
+        timeout,
+        max_attempts,
+        success_threshold,
+        success_interval,
+        timeout_warn,
+        timeout_warn_webhook,
+        timeout_fatal,
+        deadline,
+        initial_delay,
+        post_ready_delay,
+        background,
+        notify_signal,
+        notify_file,
+        forward_signals,
+        block_signals,
+        pid_file,
+        ready_file,
+        barrier,
+        barrier_name,
+        barrier_wait_for,
+        decode_secrets,
+        secret_mode,
+        lock_file,
+        command: _,
+        cmd,
+        args,
+        ..
+    } = args;
+    // Native sidecars are shut down by the kubelet, so no request is sent.
+    let shutdown = shutdown && !annotations.native_sidecar();
+
+    let forward = forwarded_signals(forward_signals, &block_signals);
+
+    // The lock is held until linkerd-await exits or, when CMD is exec'd, for
+    // as long as CMD runs.
+    let lock = lock_file.map(lock_exclusive).transpose()?;
+
+    // Secrets are mounted before containers start, so they're copied right
+    // away and a misconfiguration fails before waiting for the proxy.
+    for secret in &decode_secrets {
+        secret.write(secret_mode)?;
+    }
+
+    let admin = server.connector.clone();
+    let ready_uri = server.ready_uri.clone();
+    // Networking is checked first since the other checks may depend on it.
+    let mut checks = wait_for_interfaces
+        .into_iter()
+        .map(|name| Box::new(check::InterfaceCheck::new(name)) as Box<dyn Check>)
+        .chain(
+            wait_for_routes
+                .into_iter()
+                .map(|route| Box::new(check::RouteCheck::new(route)) as Box<dyn Check>),
+        )
+        .collect::<Checks>();
+    // Another server listening on the admin port, e.g. because of a port
+    // collision, could otherwise report readiness on the proxy's behalf.
+    let headers = server.headers.clone();
+    if verify_proxy {
+        checks.push(bounded(
+            check::ProxyIdentityCheck::new(admin.clone(), with_path(&ready_uri, "/metrics")?)
+                .with_headers(headers.clone()),
+        ));
+    }
+    // An old proxy is reported before waiting for it to become ready.
+    if let Some(min) = min_proxy_version {
+        checks.push(bounded(
+            check::ProxyVersionCheck::new(admin.clone(), with_path(&ready_uri, "/metrics")?, min)
+                .with_headers(headers.clone()),
+        ));
+    }
+    let ready = server.ready();
+    // Other sidecars and dependencies are awaited with the same retry loop.
+    let prober = Prober {
+        connector: connector.clone(),
+        resolver: resolver.clone(),
+        exec_timeout: exec_probe_timeout,
+        concurrency: probe_concurrency as usize,
+    };
+    let mut dependencies = sidecar
+        .into_iter()
+        .map(Probe::Sidecar)
+        .chain(tcp.into_iter().map(Probe::Tcp))
+        .chain(http.into_iter().map(Probe::Http))
+        .chain(
+            grpc.into_iter()
+                .map(|(uri, service)| Probe::Grpc(uri, service)),
+        )
+        .chain(dns.into_iter().map(|(name, count)| Probe::Dns(name, count)))
+        .chain(file.into_iter().map(Probe::File))
+        .chain(unix_socket.into_iter().map(Probe::UnixSocket))
+        .chain(exec_probe.into_iter().map(Probe::Exec))
+        .chain(
+            postgres
+                .into_iter()
+                .chain(mysql)
+                .chain(redis)
+                .map(Probe::Database),
+        )
+        .chain(vault_agent_token.map(Probe::VaultToken))
+        .chain(cloud_sql_proxy.map(|proxy| match proxy {
+            CloudSqlProxy::Ready(uri) => Probe::Sidecar(uri),
+            CloudSqlProxy::Port(port) => Probe::Tcp(format!("localhost:{}", port)),
+        }))
+        .chain(require)
+        .map(|probe| prober.check(probe))
+        .collect::<Checks>();
+    // A proxy that is alive but never becomes ready is told apart from one
+    // that isn't serving at all by reporting both endpoints.
+    if check_live {
+        let live = HttpCheck::new(admin.clone(), with_path(&ready_uri, "/live")?)
+            .with_headers(headers.clone());
+        dependencies.insert(0, bounded(live));
+    }
+    if dependencies.is_empty() {
+        checks.push(bounded(ready));
+    } else {
+        // With several endpoints, each one's status is logged so that the one
+        // holding up startup can be identified. They're attempted
+        // concurrently so that a slow dependency doesn't delay the others.
+        let reported =
+            std::iter::once(Box::new(check::Reported::new(bounded(ready))) as Box<dyn Check>)
+                .chain(
+                    dependencies
+                        .into_iter()
+                        .map(|check| Box::new(check::Reported::new(check)) as Box<dyn Check>),
+                )
+                .collect();
+        checks.push(Box::new(
+            check::All::new(reported).with_concurrency(prober.concurrency),
+        ));
+    }
+    // The proxy may report ready while a listener isn't bound, e.g. as it
+    // restarts. The outbound listener is only bound to the loopback address.
+    let outbound_port = outbound_port
+        .or_else(|| annotations.outbound_port())
+        .unwrap_or(DEFAULT_OUTBOUND_PORT);
+    if verify_listeners {
+        let inbound = inbound_port.or_else(|| annotations.inbound_port());
+        for port in [inbound.unwrap_or(DEFAULT_INBOUND_PORT), outbound_port] {
+            checks.push(Box::new(check::TcpCheck::new(format!(
+                "127.0.0.1:{}",
+                port
+            ))));
+        }
+    }
+    // A proxy may be ready before it has a certificate, so that applications
+    // that don't use mTLS aren't held up.
+    if require_identity {
+        checks.push(bounded(
+            check::IdentityCertCheck::new(admin.clone(), with_path(&ready_uri, "/metrics")?)
+                .with_headers(headers.clone()),
+        ));
+    }
+    if !metric.is_empty() {
+        checks.push(Box::new(check::Reported::new(bounded(
+            check::MetricCheck::new(admin.clone(), with_path(&ready_uri, "/metrics")?, metric)
+                .with_headers(headers.clone()),
+        ))));
+    }
+    // Destinations are resolved once the proxy is ready, since their
+    // requests are sent to its outbound listener. The listener is local, so
+    // no proxy configured by the environment is used.
+    if !pre_resolve.is_empty() {
+        let outbound = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), outbound_port);
+        let reported = pre_resolve
+            .into_iter()
+            .map(|uri| {
+                let check =
+                    check::PreResolveCheck::new(proxy::Connector::new(false), outbound, uri);
+                Box::new(check::Reported::new(bounded(check))) as Box<dyn Check>
+            })
+            .collect();
+        checks.push(Box::new(
+            check::All::new(reported).with_concurrency(prober.concurrency),
+        ));
+    }
+    let admin_port = ready_uri
+        .port_u16()
+        .unwrap_or(if is_https(&ready_uri) { 443 } else { 80 });
+    checks.extend(plugins(plugin_checks, admin_port));
+    checks.extend(
+        wait_port_free
+            .into_iter()
+            .map(|port| Box::new(check::PortFreeCheck::new(port)) as Box<dyn Check>),
+    );
+    // Stages are attempted in turn, e.g. so that migrations only run once
+    // the dependencies that precede them are ready.
+    if !stage.is_empty() {
+        let stages = stage
+            .into_iter()
+            .map(|probe| {
+                let probes = match probe {
+                    Probe::All(probes) => probes,
+                    probe => vec![probe],
+                };
+                let reported = probes
+                    .into_iter()
+                    .map(|probe| {
+                        Box::new(check::Reported::new(prober.check(probe))) as Box<dyn Check>
+                    })
+                    .collect();
+                Box::new(check::All::new(reported).with_concurrency(prober.concurrency))
+                    as Box<dyn Check>
+            })
+            .collect();
+        checks.push(Box::new(check::Stages::new(stages)));
+    }
+    let (k8s_checks, lease) = kubernetes(
+        Kubernetes {
+            workloads: await_k8s,
+            objects: await_k8s_objects,
+            lease: await_lease,
+            acquire: acquire.then_some(lease_duration),
+            namespace,
+        },
+        &connector,
+    )?;
+    checks.extend(k8s_checks.into_iter().map(bounded));
+    // Other containers' markers are checked last so that a container's
+    // marker only depends on its own readiness.
+    if let Some(dir) = barrier.as_ref() {
+        checks.extend(
+            barrier_wait_for
+                .iter()
+                .map(|name| Box::new(check::FileCheck::new(dir.join(name))) as Box<dyn Check>),
+        );
+    }
+    let ready_files = ready_file
+        .into_iter()
+        .chain(barrier.zip(barrier_name).map(|(dir, name)| dir.join(name)))
+        .collect::<Vec<_>>();
+    let check = check::All::new(checks);
+    let retry = Retry {
+        backoff,
+        connect_backoff: Backoff {
+            base: connect_backoff.unwrap_or(backoff.base),
+            ..backoff
+        },
+        interval: check_interval,
+        initial_delay,
+        max_attempts,
+        success_threshold,
+        success_interval: success_interval.or(check_interval).unwrap_or(backoff.base),
+        timeout,
+        timeout_warning: timeout_warn.map(|percent| TimeoutWarning {
+            percent,
+            webhook: timeout_warn_webhook,
+            connector: connector.clone(),
+        }),
+    };
+    let mut deadline = Deadline::new(deadline);
+
+    if let Some(reason) = disabled_reason.as_ref() {
+        if verbose {
+            eprintln!("Linkerd readiness check skipped: {}", reason);
+        }
+        // Containers waiting on the ready file would otherwise never start.
+        mark_ready(&ready_files);
+    }
+
+    // In background mode, the command is started right away and is notified
+    // once the proxy becomes ready.
+    if let (true, Some(cmd)) = (background, cmd.as_ref()) {
+        let linkerd_disabled = disabled_reason.is_some();
+        let warmup = Warmup {
+            connector: connector.clone(),
+            uris: warmup,
+            requests: warmup_requests,
+        };
+        let ready = Box::pin(async move {
+            if linkerd_disabled {
+                return Ok(());
+            }
+            await_ready(&check, &retry).await?;
+            mark_ready(&ready_files);
+            warmup.run().await;
+            if let Some(delay) = post_ready_delay {
+                time::sleep(delay).await;
+            }
+            Ok(())
+        });
+        let notify = Notify {
+            ready,
+            // If no notification is configured, SIGUSR1 is sent to the command.
+            signal: notify_signal.or_else(|| notify_file.is_none().then_some(Signal::SIGUSR1)),
+            file: notify_file,
+        };
+        let ex = supervise(
+            cmd.clone(),
+            args,
+            &forward,
+            &block_signals,
+            pid_file,
+            Some(notify),
+        )
+        .await;
+        if shutdown && !linkerd_disabled {
+            // The readiness wait overlaps with the command's runtime, so only
+            // the shutdown is charged against the deadline.
+            sidecars.hold(&ex).await;
+            let shut_down = deadline.run(sidecars.run()).await.and_then(|r| r);
+            return exit_code(ex, shut_down);
+        }
+        return Ok(ex?.code().unwrap_or(EX_OSERR));
+    }
+
+    // If linkerd is not explicitly disabled, wait until the proxy is ready
+    // before running the application.
+    if disabled_reason.is_none() {
+        let warmup = Warmup {
+            connector: connector.clone(),
+            uris: warmup,
+            requests: warmup_requests,
+        };
+        let ready = deadline.run(async move {
+            await_ready(&check, &retry).await?;
+            mark_ready(&ready_files);
+            warmup.run().await;
+            // Give the proxy time to settle (e.g. for routes and certificates
+            // to propagate) before the application starts sending requests.
+            if let Some(delay) = post_ready_delay {
+                time::sleep(delay).await;
+            }
+            Ok(())
+        });
+        // A pod deleted during startup shouldn't keep waiting until it's
+        // killed, so termination requests stop the wait and CMD isn't run.
+        let mut termination = process::Termination::register().map_err(Error::Signal)?;
+        let ready = tokio::select! {
+            ready = ready => ready.and_then(|ready| ready),
+            signal = termination.recv() => return Err(Error::Interrupted(signal)),
+        };
+        drop(termination);
+        match ready {
+            Ok(()) => {}
+            Err(error) => {
+                // Continue running the command when timeouts are configured to
+                // be non-fatal.
+                if timeout_fatal.unwrap_or(true) {
+                    return Err(error);
+                }
+                eprintln!("{}", error);
+            }
+        }
+
+        if let (true, Some(cmd)) = (shutdown, cmd.as_ref()) {
+            // If shutdown is configured, fork the process and proxy SIGTERM.
+            let ex = holding(
+                lease.as_ref(),
+                supervise(cmd.clone(), args, &forward, &block_signals, pid_file, None),
+            )
+            .await;
+
+            // Once the process completes, issue a shutdown request to the
+            // proxy.
+            sidecars.hold(&ex).await;
+            let shut_down = deadline.run(sidecars.run()).await.and_then(|r| r);
+
+            // Try to exit with the process's original exit code. If we didn't
+            // get an exit code from the forked program, fail with an OS error.
+            return exit_code(ex, shut_down);
+        }
+    }
+
+    match cmd {
+        // If Linkerd shutdown is not configured, exec the process directly so
+        // that the we don't have to bother with signal proxying, etc.
+        Some(cmd) => {
+            // Exec preserves our PID, so it's also the command's PID.
+            if let Some(path) = pid_file {
+                write_pid_file(&path, std::process::id()).map_err(|e| Error::PidFile(path, e))?;
+            }
+            if let Some((path, file)) = lock.as_ref() {
+                inherit_on_exec(file).map_err(|e| Error::Lock(path.clone(), e))?;
+            }
+            Err(process::exec(cmd, args))
+        }
+        None => Ok(0),
+    }
+}
+
+/// Returns a connector whose TLS sessions trust `--ca-file`, or no
+/// certificates at all with `--insecure-skip-verify`, and that present the
+/// `--tls-cert` client certificate.
+#[cfg(feature = "tls")]
+fn admin_connector(
+    connector: &proxy::Connector,
+    ca_file: Option<PathBuf>,
+    insecure_skip_verify: bool,
+    client_cert: Option<(PathBuf, PathBuf)>,
+) -> Result<proxy::Connector, Error> {
+    use linkerd_await::TlsConnector;
+
+    if ca_file.is_none() && !insecure_skip_verify && client_cert.is_none() {
+        return Ok(connector.clone());
+    }
+    let mut tls = match ca_file {
+        Some(path) => std::fs::read(&path)
+            .and_then(|pem| TlsConnector::from_pem(&pem))
+            .map_err(|e| Error::CaFile(path, e))?,
+        None if insecure_skip_verify => TlsConnector::insecure(),
+        None => TlsConnector::new(),
+    };
+    if let Some((cert, key)) = client_cert {
+        let key_pem = std::fs::read(&key).map_err(|e| Error::ClientCert(key, e))?;
+        tls = std::fs::read(&cert)
+            .and_then(|cert_pem| tls.with_client_cert(&cert_pem, &key_pem))
+            .map_err(|e| Error::ClientCert(cert, e))?;
+    }
+    Ok(connector.clone().with_tls(tls))
+}
+
+#[cfg(not(feature = "tls"))]
+fn admin_connector(
+    connector: &proxy::Connector,
+    _: Option<PathBuf>,
+    _: bool,
+    _: Option<(PathBuf, PathBuf)>,
+) -> Result<proxy::Connector, Error> {
+    Ok(connector.clone())
+}
+
+fn is_https(uri: &http::Uri) -> bool {
+    uri.scheme() == Some(&http::uri::Scheme::HTTPS)
+}
+
+/// Returns the URI with its path replaced.
+fn with_path(uri: &http::Uri, path: &str) -> Result<http::Uri, Error> {
+    let parts = uri.clone().into_parts();
+    let mut builder = http::Uri::builder().path_and_query(path);
+    if let Some(scheme) = parts.scheme {
+        builder = builder.scheme(scheme);
+    }
+    if let Some(authority) = parts.authority {
+        builder = builder.authority(authority);
+    }
+    builder.build().map_err(Error::InvalidUri)
+}
+
+/// Returns `uri` with its port replaced.
+fn with_port(uri: &http::Uri, port: u16) -> Result<http::Uri, Error> {
+    let mut parts = uri.clone().into_parts();
+    if let Some(host) = uri.host() {
+        let authority = format!("{}:{}", host, port);
+        parts.authority = Some(
+            authority
+                .parse()
+                .map_err(|e: http::uri::InvalidUri| Error::InvalidUri(e.into()))?,
+        );
+    }
+    http::Uri::from_parts(parts).map_err(|e| Error::InvalidUri(e.into()))
+}
+
+/// Returns the signals that are forwarded to CMD: termination requests
+/// unless `--forward-signals` is set, less any `--block-signals`.
+fn forwarded_signals(forward: Vec<Signal>, block: &[Signal]) -> Vec<Signal> {
+    let forward = if forward.is_empty() {
+        vec![Signal::SIGTERM, Signal::SIGINT]
+    } else {
+        forward
+    };
+    forward.into_iter().filter(|s| !block.contains(s)).collect()
+}
+
+/// Writes the `--ready-file` and `--barrier` marker, if any. A failure is
+/// reported but doesn't prevent CMD from running.
+fn mark_ready(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(e) = write_ready_file(path) {
+            eprintln!("Failed to write ready file: {}: {}", path.display(), e);
+        }
+    }
+}
+
+// === impl Warmup ===
+
+impl Warmup {
+    /// Bounds each request, so that an unresponsive URL can't hold up CMD.
+    const REQUEST_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+    /// Sends each URL its requests, one at a time over a kept-alive
+    /// connection. Warm-up is best-effort: any response will do, and a
+    /// failure is logged and skips the URL's remaining requests.
+    async fn run(self) {
+        for uri in self.uris {
+            let client = client::Persistent::new(self.connector.clone(), uri.clone());
+            for _ in 0..self.requests {
+                let req = match http::Request::get(uri.clone()).body(Vec::new()) {
+                    Ok(req) => req,
+                    Err(e) => {
+                        let error = Error::InvalidRequest(e);
+                        eprintln!("Warning: warm-up request to {} failed: {}", uri, error);
+                        break;
+                    }
+                };
+                let error = match time::timeout(Self::REQUEST_TIMEOUT, client.send(req)).await {
+                    Ok(Ok(_)) => continue,
+                    Ok(Err(error)) => error.to_string(),
+                    Err(_) => format!("no response within {:?}", Self::REQUEST_TIMEOUT),
+                };
+                eprintln!("Warning: warm-up request to {} failed: {}", uri, error);
+                break;
+            }
+        }
+    }
+}
+
+// === impl AdminServer ===
+
+impl AdminServer {
+    /// Returns a check of the readiness endpoint.
+    fn ready(&self) -> HttpCheck {
+        let mut ready = HttpCheck::new(self.connector.clone(), self.ready_uri.clone())
+            .with_expected_status(self.expected_status.clone())
+            .with_headers(self.headers.clone());
+        if self.fail_fast_on_not_found {
+            ready = ready.fail_fast_on_not_found();
+        }
+        if let Some(text) = self.expected_body.clone() {
+            ready = ready.with_expected_body(text);
+        }
+        ready
+    }
+}
+
+// === impl Shutdown ===
+
+impl Shutdown {
+    /// Keeps the proxy running, if CMD failed, so that postmortem tooling can
+    /// still reach the network. This isn't charged against the deadline.
+    async fn hold(&self, ex: &Result<ExitStatus, Error>) {
+        let Some(hold) = self.hold else {
+            return;
+        };
+        if !matches!(ex, Ok(status) if status.success()) {
+            eprintln!(
+                "CMD failed; keeping the proxy running for {:?} before shutting it down",
+                hold
+            );
+            time::sleep(hold).await;
+        }
+    }
+
+    /// Sends the shutdown request to the mesh's sidecar, retrying it until it
+    /// succeeds or, failing that, signaling the proxy, and then to each
+    /// `--also-shutdown` URL. Fails if the sidecar couldn't be shut down or
+    /// `--verify-shutdown` finds that it's still running.
+    async fn run(self) -> Result<(), Error> {
+        if let Some(delay) = self.delay {
+            time::sleep(delay).await;
+        }
+        // The sidecar is shut down whether or not it drains.
+        if let Some((uri, drain_time)) = self.drain.clone() {
+            match send_shutdown(
+                &self.admin,
+                http::Method::POST,
+                uri.clone(),
+                self.headers.clone(),
+            )
+            .await
+            {
+                Ok(()) => time::sleep(drain_time).await,
+                Err(error) => eprintln!("Failed to drain the sidecar with {}: {}", uri, error),
+            }
+        }
+        let uri = self.uri.clone();
+        let mut result = Ok(());
+        let shut_down = match retry_shutdown(
+            &self.admin,
+            self.method,
+            self.uri,
+            self.headers,
+            &self.backoff,
+            self.timeout,
+        )
+        .await
+        {
+            Ok(()) => true,
+            Err(error) => {
+                let error = Error::Shutdown(uri, Box::new(error));
+                // The proxy is shut down nonetheless if it's signaled.
+                let signaled = match self.signal.as_deref() {
+                    Some(name) => {
+                        eprintln!("{}", error);
+                        signal_proxy(name)
+                    }
+                    None => false,
+                };
+                if !signaled {
+                    result = Err(error);
+                }
+                signaled
+            }
+        };
+        if let (true, Some(timeout)) = (shut_down, self.verify) {
+            if !await_shutdown(&self.admin, &self.ready_uri, timeout).await {
+                result = Err(Error::ShutdownTimeout(self.ready_uri.clone(), timeout));
+            }
+        }
+        // Other sidecars' shutdown requests are best-effort.
+        for uri in self.also {
+            let _ = send_shutdown(
+                &self.connector,
+                http::Method::POST,
+                uri,
+                http::HeaderMap::new(),
+            )
+            .await;
+        }
+        result
+    }
+}
+
+/// Returns CMD's exit code, unless CMD succeeded but the sidecar didn't shut
+/// down, so that a Job whose proxy keeps running doesn't appear to succeed.
+/// Other shutdown failures are only logged.
+fn exit_code(ex: Result<ExitStatus, Error>, shut_down: Result<(), Error>) -> Result<i32, Error> {
+    if let Err(error) = shut_down {
+        if matches!(error, Error::ShutdownTimeout(..))
+            && matches!(&ex, Ok(status) if status.success())
+        {
+            return Err(error);
+        }
+        eprintln!("{}", error);
+    }
+    Ok(ex?.code().unwrap_or(EX_OSERR))
+}
+
+/// Sends SIGTERM to each process with the name, returning true if any was
+/// signaled.
+fn signal_proxy(name: &str) -> bool {
+    let pids = match process::find(name) {
+        Ok(pids) => pids,
+        Err(error) => {
+            eprintln!("Failed to find the {} process: {}", name, error);
+            return false;
+        }
+    };
+    if pids.is_empty() {
+        eprintln!(
+            "Failed to find the {} process; the pod must set shareProcessNamespace",
+            name
+        );
+    }
+    let mut signaled = false;
+    for pid in pids {
+        match process::terminate(pid) {
+            Ok(()) => {
+                eprintln!("Sent SIGTERM to {} (pid {})", name, pid);
+                signaled = true;
+            }
+            Err(error) => eprintln!("Failed to signal {} (pid {}): {}", name, pid, error),
+        }
+    }
+    signaled
+}
+
+impl Prober {
+    fn check(&self, probe: Probe) -> Box<dyn Check> {
+        self.check_within(probe, DEFAULT_ATTEMPT_TIMEOUT)
+    }
+
+    /// Builds the probe's check. Each attempt of a target is bounded by
+    /// `attempt_timeout` so that a hung target is retried without holding up
+    /// the others; a command's attempts are bounded by `--exec-probe-timeout`
+    /// instead.
+    fn check_within(&self, probe: Probe, attempt_timeout: time::Duration) -> Box<dyn Check> {
+        let check: Box<dyn Check> = match probe {
+            // Unlike the proxy's `/ready`, these endpoints may answer 404
+            // while they start, so they don't fail fast on it.
+            Probe::Sidecar(uri) => Box::new(HttpCheck::new(self.connector.clone(), uri)),
+            Probe::Tcp(addr) => Box::new(check::TcpCheck::new(addr)),
+            Probe::Http(probe) => {
+                let mut check = HttpCheck::new(self.connector.clone(), probe.uri);
+                if let Some(expected) = probe.expected_status {
+                    check = check.with_expected_status(expected);
+                }
+                if let Some(text) = probe.expected_body {
+                    check = check.with_expected_body(text);
+                }
+                Box::new(check)
+            }
+            Probe::Grpc(uri, service) => {
+                Box::new(check::GrpcCheck::new(self.connector.clone(), uri, service))
+            }
+            Probe::Dns(name, count) => {
+                Box::new(check::DnsCheck::new(self.resolver.clone(), name).with_min_addrs(count))
+            }
+            Probe::File(path) => Box::new(check::FileCheck::new(path)),
+            Probe::UnixSocket(path) => Box::new(check::UnixSocketCheck::new(path)),
+            Probe::Exec(probe) => {
+                return Box::new(
+                    check::CommandCheck::new(probe.cmd, probe.args).with_timeout(self.exec_timeout),
+                )
+            }
+            Probe::Database(url) => Box::new(check::DatabaseCheck::new(self.resolver.clone(), url)),
+            Probe::VaultToken(path) => Box::new(check::VaultTokenCheck::new(path)),
+            Probe::All(probes) => {
+                return Box::new(
+                    check::All::new(
+                        probes
+                            .into_iter()
+                            .map(|p| self.check_within(p, attempt_timeout))
+                            .collect(),
+                    )
+                    .with_concurrency(self.concurrency),
+                )
+            }
+            Probe::Any(probes) => {
+                return Box::new(
+                    check::Any::new(
+                        probes
+                            .into_iter()
+                            .map(|p| self.check_within(p, attempt_timeout))
+                            .collect(),
+                    )
+                    .with_concurrency(self.concurrency),
+                )
+            }
+            // A target given its own timeout may take as long to answer.
+            Probe::Retry(probe, retry) => {
+                let attempt_timeout = retry.timeout.unwrap_or(attempt_timeout);
+                let mut check = check::Limited::new(self.check_within(*probe, attempt_timeout));
+                if let Some(timeout) = retry.timeout {
+                    check = check.with_timeout(timeout);
+                }
+                if let Some(backoff) = retry.backoff {
+                    check = check.with_backoff(backoff);
+                }
+                if let Some(max) = retry.max_attempts {
+                    check = check.with_max_attempts(max);
+                }
+                return Box::new(check);
+            }
+        };
+        Box::new(check::Limited::new(check).with_attempt_timeout(attempt_timeout))
+    }
+}
+
+/// Builds the `--plugin-check` checks.
+#[cfg(feature = "plugins")]
+fn plugins(paths: Vec<PathBuf>, port: u16) -> Vec<Box<dyn Check>> {
+    paths
+        .into_iter()
+        .map(|path| Box::new(check::PluginCheck::new(path, port)) as Box<dyn Check>)
+        .collect()
+}
+
+/// Plugins are rejected by [`Args::unsupported`] in builds without them.
+#[cfg(not(feature = "plugins"))]
+fn plugins(_: Vec<PathBuf>, _: u16) -> Vec<Box<dyn Check>> {
+    Vec::new()
+}
+
+/// Configures the Kubernetes API checks.
+#[cfg_attr(not(feature = "k8s"), allow(dead_code))]
+struct Kubernetes {
+    workloads: Vec<Workload>,
+    objects: Vec<ObjectRef>,
+    lease: Option<String>,
+    /// The duration for which the lease is acquired, if it's acquired.
+    acquire: Option<time::Duration>,
+    namespace: Option<String>,
+}
+
+/// Bounds each attempt of a check of the proxy, or of another local target,
+/// so that a hung server is retried.
+fn bounded(check: impl Check + 'static) -> Box<dyn Check> {
+    Box::new(check::Limited::new(check).with_attempt_timeout(DEFAULT_ATTEMPT_TIMEOUT))
+}
+
+/// Builds the Kubernetes API checks. An acquired lease is also returned so
+/// that it can be held while CMD runs.
+#[cfg(feature = "k8s")]
+fn kubernetes(
+    config: Kubernetes,
+    connector: &proxy::Connector,
+) -> Result<(Checks, Option<Lease>), Error> {
+    let Kubernetes {
+        workloads,
+        objects,
+        lease,
+        acquire,
+        namespace,
+    } = config;
+    if workloads.is_empty() && objects.is_empty() && lease.is_none() {
+        return Ok((Vec::new(), None));
+    }
+    let namespace = match namespace {
+        Some(ns) => ns,
+        None => k8s::namespace().map_err(Error::Kubernetes)?,
+    };
+    let client = k8s::Client::in_cluster(connector.clone()).map_err(Error::Kubernetes)?;
+    let client = std::sync::Arc::new(client);
+
+    // Objects are checked first since workloads commonly depend on them.
+    let mut checks = objects
+        .into_iter()
+        .map(|object| {
+            Box::new(check::ObjectCheck::new(
+                client.clone(),
+                object,
+                namespace.clone(),
+            )) as Box<dyn Check>
+        })
+        .collect::<Vec<_>>();
+    checks.extend(workloads.into_iter().map(|workload| {
+        Box::new(check::WorkloadCheck::new(
+            client.clone(),
+            workload,
+            namespace.clone(),
+        )) as Box<dyn Check>
+    }));
+
+    // The lease is checked last so that it isn't held while waiting for the
+    // other checks.
+    let lease = lease.map(|name| match acquire {
+        Some(duration) => std::sync::Arc::new(check::LeaseCheck::acquire(
+            client.clone(),
+            name,
+            namespace.clone(),
+            lease_holder(),
+            duration,
+        )),
+        None => std::sync::Arc::new(check::LeaseCheck::new(client.clone(), name, namespace)),
+    });
+    if let Some(lease) = lease.as_ref() {
+        checks.push(Box::new(lease.clone()));
+    }
+    Ok((checks, lease.filter(|_| acquire.is_some())))
+}
+
+/// Kubernetes checks are rejected by [`Args::unsupported`] in builds without
+/// them.
+#[cfg(not(feature = "k8s"))]
+fn kubernetes(_: Kubernetes, _: &proxy::Connector) -> Result<(Checks, Option<Lease>), Error> {
+    Ok((Vec::new(), None))
+}
+
+/// Identifies this process as a lease holder by its pod's name.
+#[cfg(feature = "k8s")]
+fn lease_holder() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| format!("linkerd-await-{}", std::process::id()))
+}
+
+/// Runs the future while renewing the lease, if any, and then releases the
+/// lease.
+#[cfg(feature = "k8s")]
+async fn holding<F: std::future::Future>(lease: Option<&Lease>, f: F) -> F::Output {
+    let lease = match lease {
+        Some(lease) => lease,
+        None => return f.await,
+    };
+    let output = tokio::select! {
+        output = f => output,
+        never = lease.hold() => match never {},
+    };
+    lease.release().await;
+    output
+}
+
+#[cfg(not(feature = "k8s"))]
+async fn holding<F: std::future::Future>(_: Option<&Lease>, f: F) -> F::Output {
+    f.await
+}
+
+// === impl Mesh ===
+
+impl Mesh {
+    /// Returns the mesh's built-in profile.
+    fn profile(self) -> Profile {
+        let (ready_port, ready_path, shutdown_port, shutdown_path) = match self {
+            Self::Linkerd => (4191, "/ready", None, "/shutdown"),
+            Self::Istio => (15021, "/healthz/ready", Some(15020), "/quitquitquit"),
+            Self::Envoy | Self::AppMeshEnvoy => (9901, "/ready", None, "/quitquitquit"),
+            // consul-dataplane's lifecycle server, on consul-k8s's default
+            // graceful port.
+            Self::Consul => (19000, "/ready", Some(20600), "/graceful_shutdown"),
+            Self::Dapr => (3500, "/v1.0/healthz", None, "/v1.0/shutdown"),
+        };
+        Profile {
+            ready_port,
+            ready_path,
+            expected_status: check::StatusSet::default(),
+            shutdown_port,
+            shutdown_path,
+            shutdown_method: http::Method::POST,
+            // App Mesh's Envoy closes connections gracefully while it drains,
+            // rather than resetting them when it exits.
+            drain_path: (self == Self::AppMeshEnvoy).then_some("/drain_listeners?graceful"),
+        }
+    }
+}
+
+// === impl Healthcheck ===
+
+impl Healthcheck {
+    /// Probes the proxy (unless Linkerd is disabled) and the application
+    /// concurrently, exiting 0 if both succeed within the timeout and 1
+    /// otherwise, as Docker expects of a HEALTHCHECK.
+    async fn run(self, admin: Option<AdminServer>) -> Result<i32, Error> {
+        let proxy = admin.map(|admin| admin.ready());
+        let app = self
+            .app_url
+            .map(|uri| HttpCheck::new(proxy::Connector::new(false), uri));
+
+        let probe = |check: Option<HttpCheck>| async move {
+            match check {
+                Some(check) => check
+                    .check()
+                    .await
+                    .map_err(|error| format!("{}: {}", check, error)),
+                None => Ok(()),
+            }
+        };
+        let probes = async { tokio::join!(probe(proxy), probe(app)) };
+        let failures = match time::timeout(self.timeout, probes).await {
+            Ok((proxy, app)) => [proxy, app].into_iter().filter_map(Result::err).collect(),
+            Err(_) => vec![format!("probes did not complete within {:?}", self.timeout)],
+        };
+
+        for failure in &failures {
+            eprintln!("Unhealthy: {}", failure);
+        }
+        Ok(if failures.is_empty() { 0 } else { 1 })
+    }
+}
+
+// === impl CheckProxy ===
+
+impl CheckProxy {
+    /// Checks the proxy once (unless Linkerd is disabled), exiting 0 if it's
+    /// ready within the timeout and 1 otherwise. The readiness endpoint is
+    /// checked exactly as linkerd-await checks it.
+    async fn run(self, admin: Option<AdminServer>) -> Result<i32, Error> {
+        let Some(admin) = admin else {
+            return Ok(0);
+        };
+        let check = admin.ready();
+        let error = match time::timeout(self.timeout, check.check()).await {
+            Ok(Ok(())) => return Ok(0),
+            Ok(Err(error)) => error.to_string(),
+            Err(_) => format!("no response within {:?}", self.timeout),
+        };
+        eprintln!("Not ready: {}: {}", check, error);
+        Ok(1)
+    }
+}
+
+// === impl ShutdownProxy ===
+
+impl ShutdownProxy {
+    /// Shuts the sidecar down as linkerd-await does once CMD completes
+    /// (unless Linkerd is disabled), exiting 0 once it has and
+    /// `EX_UNAVAILABLE` if it hasn't.
+    async fn run(self, sidecars: Option<Shutdown>) -> Result<i32, Error> {
+        match sidecars {
+            Some(sidecars) => sidecars.run().await.map(|()| 0),
+            None => Ok(0),
+        }
+    }
+}
+
+// === impl WaitFor ===
+
+impl WaitFor {
+    /// Waits for the targets, each retried every second, and then execs the
+    /// command. As with wait-for-it.sh, the command is run even when the
+    /// timeout elapses unless `--strict` is set.
+    async fn run(self) -> Result<i32, Error> {
+        let prober = Prober {
+            connector: proxy::Connector::new(false),
+            resolver: Resolver::default(),
+            exec_timeout: time::Duration::from_secs(1),
+            concurrency: self.targets.len(),
+        };
+        let checks = self
+            .targets
+            .into_iter()
+            .map(|probe| {
+                let check = prober.check(probe);
+                if self.quiet {
+                    check
+                } else {
+                    Box::new(check::Reported::new(check))
+                }
+            })
+            .collect();
+        let check = check::All::new(checks).with_concurrency(prober.concurrency);
+        let retry = Retry {
+            timeout: Some(self.timeout),
+            ..Retry::new()
+        };
+
+        let available = match await_ready(&check, &retry).await {
+            Ok(()) => true,
+            Err(error @ Error::Timeout(..)) => {
+                if !self.quiet {
+                    eprintln!("wait-for: {}", error);
+                }
+                false
+            }
+            Err(error) => return Err(error),
+        };
+        if !available && (self.strict || self.cmd.is_empty()) {
+            return Ok(EX_UNAVAILABLE);
+        }
+        let mut cmd = self.cmd.into_iter();
+        match cmd.next() {
+            Some(name) => Err(process::exec(name, cmd.collect())),
+            None => Ok(0),
+        }
+    }
+}
+
+// === impl K8sSnippet ===
+
+impl K8sSnippet {
+    /// The container's new arguments: linkerd-await's flags followed by its
+    /// original command.
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.shutdown {
+            args.push("--shutdown".to_string());
+        }
+        if let Some(timeout) = self.timeout {
+            args.push(format!("--timeout={}", format_duration(timeout)));
+        }
+        args.push("--".to_string());
+        args.extend(self.cmd.iter().cloned());
+        args
+    }
+
+    /// Renders a patch for `kubectl patch --type=strategic`. Containers are
+    /// merged by name, so the patch only replaces the named container's
+    /// `command` and `args`.
+    ///
+    /// Strings are always quoted as JSON, which is also valid YAML.
+    fn render(&self) -> String {
+        let list = |items: &[String]| {
+            let items = items.iter().map(|s| quote(s)).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        };
+        let name = quote(&self.container);
+        let command = list(&[self.path.clone()]);
+        let args = list(&self.args());
+        match self.output {
+            SnippetFormat::Yaml => format!(
+                concat!(
+                    "spec:\n",
+                    "  template:\n",
+                    "    spec:\n",
+                    "      containers:\n",
+                    "      - name: {}\n",
+                    "        command: {}\n",
+                    "        args: {}",
+                ),
+                name, command, args
+            ),
+            SnippetFormat::Json => format!(
+                "{{\"spec\": {{\"template\": {{\"spec\": {{\"containers\": [{{\"name\": {}, \"command\": {}, \"args\": {}}}]}}}}}}}}",
+                name, command, args
+            ),
+        }
+    }
+}
+
+/// Formats a duration in the largest unit that represents it exactly, as
+/// accepted by [`parse_duration`].
+fn format_duration(duration: time::Duration) -> String {
+    let ms = duration.as_millis();
+    match ms {
+        0 => "0s".to_string(),
+        ms if ms % 3_600_000 == 0 => format!("{}h", ms / 3_600_000),
+        ms if ms % 60_000 == 0 => format!("{}m", ms / 60_000),
+        ms if ms % 1_000 == 0 => format!("{}s", ms / 1_000),
+        ms => format!("{}ms", ms),
+    }
+}
+
+/// Quotes a string as a JSON string.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl Args {
+    /// Reads the pod's annotations from `--annotations-file`, which only the
+    /// Linkerd injector sets.
+    fn annotations(&self) -> Result<PodAnnotations, Error> {
+        match self.annotations_file.as_deref() {
+            Some(path) if self.mesh == Mesh::Linkerd => PodAnnotations::read(path),
+            _ => Ok(PodAnnotations::default()),
+        }
+    }
+
+    fn resolver(&self) -> Resolver {
+        Resolver {
+            overrides: self.resolve.clone(),
+            dns_server: self.dns_server,
+        }
+    }
+
+    /// Locates the mesh's admin server. Unless the flags locate it, the
+    /// Linkerd proxy's is found through the pod's annotations or the proxy's
+    /// environment.
+    fn admin_server(
+        &self,
+        annotations: &PodAnnotations,
+        connector: &proxy::Connector,
+    ) -> Result<AdminServer, Error> {
+        let profile = self.mesh.profile();
+        let ready_uri = match self.ready_url.clone() {
+            Some(uri) => uri,
+            None => {
+                let (host, port) = match (self.admin_addr.clone(), self.host.clone(), self.port) {
+                    (Some(addr), ..) => addr,
+                    (None, None, None) if self.mesh == Mesh::Linkerd => {
+                        match annotations.admin_port() {
+                            Some(port) => (DEFAULT_ADMIN_HOST.to_string(), port),
+                            None => discover_admin_addr()?.unwrap_or_else(|| {
+                                (DEFAULT_ADMIN_HOST.to_string(), profile.ready_port)
+                            }),
+                        }
+                    }
+                    (None, host, port) => (
+                        host.unwrap_or_else(|| DEFAULT_ADMIN_HOST.to_string()),
+                        port.unwrap_or(profile.ready_port),
+                    ),
+                };
+                let path = self.ready_path.as_deref().unwrap_or(profile.ready_path);
+                admin_uri(self.scheme.clone(), &host, port, path)?
+            }
+        };
+        // TLS settings only apply to the admin server, not to webhooks or the
+        // Kubernetes API.
+        let admin = admin_connector(
+            connector,
+            self.ca_file.clone(),
+            self.insecure_skip_verify,
+            self.tls_cert.clone().zip(self.tls_key.clone()),
+        )?;
+        let admin = match self.admin_uds.clone() {
+            Some(path) => admin.with_unix_socket(path),
+            None => admin,
+        };
+        Ok(AdminServer {
+            connector: if self.http2 {
+                admin.with_http2()
+            } else {
+                admin
+            },
+            headers: self.header.iter().cloned().collect(),
+            expected_status: self
+                .expect_status
+                .clone()
+                .unwrap_or(profile.expected_status),
+            expected_body: self.expect_body.clone(),
+            fail_fast_on_not_found: self.mesh == Mesh::Linkerd && self.ready_url.is_none(),
+            ready_uri,
+        })
+    }
+
+    fn connector(&self) -> proxy::Connector {
+        proxy::Connector::new(self.use_env_proxy).with_resolver(self.resolver())
+    }
+
+    /// Paces the readiness checks and the shutdown request's retries.
+    fn backoff(&self) -> Backoff {
+        Backoff {
+            strategy: self.backoff_strategy,
+            base: self.backoff,
+            max: self.backoff_max,
+            multiplier: self
+                .backoff_multiplier
+                .unwrap_or(Backoff::DEFAULT_MULTIPLIER),
+            jitter: self.backoff_jitter.unwrap_or(0),
+        }
+    }
+
+    /// Configures how the mesh's sidecar, and any others, are shut down.
+    /// Unless the mesh serves it on another port, the shutdown endpoint is
+    /// served by the same admin server.
+    fn sidecars(
+        &self,
+        server: &AdminServer,
+        connector: &proxy::Connector,
+    ) -> Result<Shutdown, Error> {
+        let profile = self.mesh.profile();
+        let path = self
+            .shutdown_path
+            .as_deref()
+            .unwrap_or(profile.shutdown_path);
+        let uri = match (self.shutdown_url.clone(), profile.shutdown_port) {
+            (Some(uri), _) => uri,
+            (None, Some(port)) => with_port(&with_path(&server.ready_uri, path)?, port)?,
+            (None, None) => with_path(&server.ready_uri, path)?,
+        };
+        let drain = match profile.drain_path {
+            Some(path) => Some((
+                with_path(&uri, path)?,
+                self.drain_time.unwrap_or(DEFAULT_DRAIN_TIME),
+            )),
+            None => None,
+        };
+        Ok(Shutdown {
+            admin: server.connector.clone(),
+            method: self
+                .shutdown_method
+                .clone()
+                .unwrap_or(profile.shutdown_method),
+            uri,
+            headers: server.headers.clone(),
+            delay: self.shutdown_delay,
+            drain,
+            hold: self.shutdown_on_success_only,
+            backoff: self.backoff(),
+            timeout: self.shutdown_timeout.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
+            ready_uri: server.ready_uri.clone(),
+            verify: self.verify_shutdown,
+            signal: self.signal_proxy.clone(),
+            connector: connector.clone(),
+            also: self
+                .also_shutdown
+                .iter()
+                .cloned()
+                .chain(self.cloud_sql_proxy_shutdown.clone())
+                .collect(),
+        })
+    }
+
+    /// Locates the admin server for a subcommand, which skips the proxy, as
+    /// linkerd-await does, when Linkerd is disabled.
+    fn subcommand_admin(
+        &self,
+        disabled_reason: Option<String>,
+    ) -> Result<Option<AdminServer>, Error> {
+        let annotations = self.annotations()?;
+        if disabled_reason
+            .or_else(|| annotations.disabled_reason())
+            .is_some()
+        {
+            return Ok(None);
+        }
+        self.admin_server(&annotations, &self.connector()).map(Some)
+    }
+
+    /// Describes a flag that can't be honored because linkerd-await was built
+    /// without the feature that implements it.
+    fn unsupported(&self) -> Option<String> {
+        let flags = [
+            (
+                self.use_env_proxy && !cfg!(feature = "env-proxy"),
+                "--use-env-proxy",
+                "env-proxy",
+            ),
+            (
+                !self.plugin_checks.is_empty() && !cfg!(feature = "plugins"),
+                "--plugin-check",
+                "plugins",
+            ),
+            (
+                !self.await_k8s.is_empty() && !cfg!(feature = "k8s"),
+                "--await-k8s",
+                "k8s",
+            ),
+            (
+                !self.await_k8s_objects.is_empty() && !cfg!(feature = "k8s"),
+                "--await-k8s-object",
+                "k8s",
+            ),
+            (
+                self.await_lease.is_some() && !cfg!(feature = "k8s"),
+                "--await-lease",
+                "k8s",
+            ),
+            (
+                self.ready_url.as_ref().is_some_and(is_https) && !cfg!(feature = "tls"),
+                "An https --ready-url",
+                "tls",
+            ),
+            (
+                self.ready_url.is_none()
+                    && self.scheme == http::uri::Scheme::HTTPS
+                    && !cfg!(feature = "tls"),
+                "--scheme https",
+                "tls",
+            ),
+            (
+                self.ca_file.is_some() && !cfg!(feature = "tls"),
+                "--ca-file",
+                "tls",
+            ),
+            (
+                self.insecure_skip_verify && !cfg!(feature = "tls"),
+                "--insecure-skip-verify",
+                "tls",
+            ),
+            (
+                self.tls_cert.is_some() && !cfg!(feature = "tls"),
+                "--tls-cert",
+                "tls",
+            ),
+            (
+                self.timeout_warn_webhook.as_ref().is_some_and(is_https) && !cfg!(feature = "tls"),
+                "An https --timeout-warn-webhook",
+                "tls",
+            ),
+        ];
+        flags
+            .iter()
+            .find(|(unsupported, ..)| *unsupported)
+            .map(|(_, flag, feature)| {
+                format!(
+                    "{} requires linkerd-await to be built with the `{}` feature",
+                    flag, feature
+                )
+            })
+    }
+
+    /// Describes flag combinations that are accepted by the parser but that
+    /// would cause linkerd-await to silently misbehave.
+    fn validate(&self, linkerd_disabled: bool) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        match self.timeout {
+            Some(timeout) if timeout.is_zero() => {
+                if self.timeout_fatal.is_some() {
+                    warnings.push(
+                        "--timeout 0 disables the readiness timeout, so --timeout-fatal has no effect"
+                            .to_string(),
+                    );
+                }
+            }
+            Some(timeout) => {
+                let (flag, delay) = match self.check_interval {
+                    Some(interval) => ("--check-interval", interval),
+                    None => ("--backoff", self.backoff),
+                };
+                if delay > timeout {
+                    warnings.push(format!(
+                        "{} {:?} is larger than --timeout {:?}; at most one readiness check will be attempted",
+                        flag, delay, timeout
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        if let (Some(delay), Some(timeout)) = (self.initial_delay, self.timeout) {
+            if !timeout.is_zero() && delay >= timeout {
+                warnings.push(format!(
+                    "--initial-delay {:?} is not shorter than --timeout {:?}; the proxy will never be checked",
+                    delay, timeout
+                ));
+            }
+        }
+
+        let is_https_admin = match self.ready_url.as_ref() {
+            Some(uri) => is_https(uri),
+            None => self.scheme == http::uri::Scheme::HTTPS,
+        };
+        if is_https_admin && self.admin_uds.is_none() && self.http2 {
+            warnings.push(
+                "--http2 has no effect with an https admin server; HTTP/1.1 is negotiated"
+                    .to_string(),
+            );
+        }
+        if !is_https_admin {
+            for (set, flag) in [
+                (self.ca_file.is_some(), "--ca-file"),
+                (self.insecure_skip_verify, "--insecure-skip-verify"),
+                (self.tls_cert.is_some(), "--tls-cert"),
+            ] {
+                if set {
+                    warnings.push(format!(
+                        "{} has no effect unless the admin server is https (see --scheme)",
+                        flag
+                    ));
+                }
+            }
+        }
+
+        if self.drain_time.is_some() && self.mesh.profile().drain_path.is_none() {
+            warnings.push("--drain-time has no effect unless --mesh is app-mesh".to_string());
+        }
+
+        if self.mesh != Mesh::Linkerd {
+            for (set, flag) in [
+                (self.verify_proxy, "--verify-proxy"),
+                (self.verify_listeners, "--verify-listeners"),
+                (self.require_identity, "--require-identity"),
+                (self.check_live, "--check-live"),
+                (!self.metric.is_empty(), "--metric"),
+                (self.min_proxy_version.is_some(), "--min-proxy-version"),
+                (self.annotations_file.is_some(), "--annotations-file"),
+            ] {
+                if set {
+                    warnings.push(format!("{} has no effect unless --mesh is linkerd", flag));
+                }
+            }
+        }
+
+        if !self.verify_listeners {
+            if self.inbound_port.is_some() {
+                warnings
+                    .push("--inbound-port has no effect without --verify-listeners".to_string());
+            }
+            if self.outbound_port.is_some() && self.pre_resolve.is_empty() {
+                warnings.push(
+                    "--outbound-port has no effect without --verify-listeners or --pre-resolve"
+                        .to_string(),
+                );
+            }
+        }
+
+        if self.success_interval.is_some() && self.success_threshold == 1 {
+            warnings.push(
+                "--success-interval has no effect unless --success-threshold is greater than 1"
+                    .to_string(),
+            );
+        }
+
+        if self.backoff_multiplier.is_some()
+            && self.backoff_strategy != BackoffStrategy::Exponential
+        {
+            warnings.push(
+                "--backoff-multiplier has no effect without the exponential --backoff-strategy"
+                    .to_string(),
+            );
+        }
+
+        if let Some(max) = self.backoff_max {
+            if max < self.backoff {
+                warnings.push(format!(
+                    "--backoff-max {:?} is smaller than --backoff {:?}; checks will be retried every {:?}",
+                    max, self.backoff, max
+                ));
+            } else if self.backoff_strategy == BackoffStrategy::Constant {
+                warnings.push(
+                    "--backoff-max has no effect with the constant --backoff-strategy".to_string(),
+                );
+            }
+        }
+
+        if self.shutdown {
+            let port = self
+                .admin_addr
+                .as_ref()
+                .map(|(_, port)| *port)
+                .or(self.port);
+            if self.admin_uds.is_none() && self.shutdown_url.is_none() && port == Some(0) {
+                warnings.push(
+                    "--shutdown requires a reachable admin server, but its port is 0".to_string(),
+                );
+            }
+            if linkerd_disabled {
+                warnings.push(
+                    "--shutdown has no effect when Linkerd is disabled; CMD will be executed directly"
+                        .to_string(),
+                );
+            }
+        } else {
+            for (set, flag) in [
+                (self.shutdown_url.is_some(), "--shutdown-url"),
+                (self.shutdown_path.is_some(), "--shutdown-path"),
+                (self.shutdown_method.is_some(), "--shutdown-method"),
+                (self.shutdown_delay.is_some(), "--shutdown-delay"),
+                (self.drain_time.is_some(), "--drain-time"),
+                (
+                    self.shutdown_on_success_only.is_some(),
+                    "--shutdown-on-success-only",
+                ),
+                (self.verify_shutdown.is_some(), "--verify-shutdown"),
+                (self.signal_proxy.is_some(), "--signal-proxy"),
+                (self.shutdown_timeout.is_some(), "--shutdown-timeout"),
+                (!self.also_shutdown.is_empty(), "--also-shutdown"),
+                (
+                    self.cloud_sql_proxy_shutdown.is_some(),
+                    "--cloud-sql-proxy-shutdown",
+                ),
+            ] {
+                if set {
+                    warnings.push(format!("{} has no effect without --shutdown", flag));
+                }
+            }
+        }
+
+        if self.barrier.is_some() {
+            if self.barrier_name.is_none() && self.barrier_wait_for.is_empty() {
+                warnings.push(
+                    "--barrier has no effect without --barrier-name or --barrier-wait-for"
+                        .to_string(),
+                );
+            }
+            if linkerd_disabled && !self.barrier_wait_for.is_empty() {
+                warnings.push(
+                    "--barrier-wait-for has no effect when Linkerd is disabled; CMD will be executed directly"
+                        .to_string(),
+                );
+            }
+        }
+
+        if self.acquire {
+            if !self.shutdown {
+                warnings.push(
+                    "--acquire without --shutdown can't renew the lease once CMD runs; it expires after --lease-duration"
+                        .to_string(),
+                );
+            }
+            if linkerd_disabled {
+                warnings.push(
+                    "--acquire has no effect when Linkerd is disabled; CMD will be executed directly"
+                        .to_string(),
+                );
+            }
+        }
+
+        if !self.shutdown
+            && !self.background
+            && (!self.forward_signals.is_empty() || !self.block_signals.is_empty())
+        {
+            warnings.push(
+                "--forward-signals and --block-signals have no effect without --shutdown or --background; CMD receives signals directly"
+                    .to_string(),
+            );
+        }
+
+        warnings
+    }
+}
+
+/// Parses a percentage between 1 and 99, with or without a trailing `%`.
+fn parse_percent(s: &str) -> Result<u8, InvalidPercent> {
+    let s = s.trim();
+    let percent = u8::from_str(s.strip_suffix('%').unwrap_or(s)).map_err(|_| InvalidPercent)?;
+    if percent == 0 || percent >= 100 {
+        return Err(InvalidPercent);
+    }
+    Ok(percent)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidPercent;
+
+impl fmt::Display for InvalidPercent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid percentage; expected a value between 1% and 99%")
+    }
+}
+
+impl error::Error for InvalidPercent {}
+
+/// Parses a number of readiness checks, which must be positive.
+fn parse_count(s: &str) -> Result<u32, InvalidCount> {
+    match u32::from_str(s.trim()) {
+        Ok(attempts) if attempts > 0 => Ok(attempts),
+        _ => Err(InvalidCount),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidCount;
+
+impl fmt::Display for InvalidCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid count; expected a positive integer")
+    }
+}
+
+impl error::Error for InvalidCount {}
+
+/// Parses an exponential backoff multiplier, which must be at least 1.
+fn parse_multiplier(s: &str) -> Result<f64, InvalidMultiplier> {
+    match f64::from_str(s.trim()) {
+        Ok(multiplier) if multiplier.is_finite() && multiplier >= 1.0 => Ok(multiplier),
+        _ => Err(InvalidMultiplier),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidMultiplier;
+
+impl fmt::Display for InvalidMultiplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid multiplier; expected a number of at least 1")
+    }
+}
+
+impl error::Error for InvalidMultiplier {}
+
+/// Parses a signal name, with or without the `SIG` prefix (e.g. `SIGUSR1` or
+/// `USR1`).
+fn parse_signal(s: &str) -> Result<Signal, InvalidSignal> {
+    let s = s.trim().to_ascii_uppercase();
+    if s.starts_with("SIG") {
+        Signal::from_name(&s).ok_or(InvalidSignal)
+    } else {
+        Signal::from_name(&format!("SIG{}", s)).ok_or(InvalidSignal)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidSignal;
+
+impl fmt::Display for InvalidSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid signal")
+    }
+}
+
+impl error::Error for InvalidSignal {}
+
+/// Parses a signal that can be forwarded to CMD. Signals that can't be
+/// caught, and those that linkerd-await uses to manage CMD, can't be.
+fn parse_forwarded_signal(s: &str) -> Result<Signal, InvalidForwardedSignal> {
+    match parse_signal(s) {
+        Ok(
+            Signal::SIGKILL
+            | Signal::SIGSTOP
+            | Signal::SIGILL
+            | Signal::SIGFPE
+            | Signal::SIGSEGV
+            | Signal::SIGBUS
+            | Signal::SIGCHLD
+            | Signal::SIGCONT
+            | Signal::SIGTSTP
+            | Signal::SIGTTIN
+            | Signal::SIGTTOU,
+        )
+        | Err(_) => Err(InvalidForwardedSignal),
+        Ok(signal) => Ok(signal),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidForwardedSignal;
+
+impl fmt::Display for InvalidForwardedSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid signal; expected a signal that can be forwarded")
+    }
+}
+
+impl error::Error for InvalidForwardedSignal {}
+
+/// Parses a readiness endpoint URL, which must be an absolute `http` or
+/// `https` URL.
+fn parse_ready_url(s: &str) -> Result<http::Uri, InvalidReadyUrl> {
+    let uri = http::Uri::from_str(s.trim()).map_err(|_| InvalidReadyUrl)?;
+    let scheme = uri.scheme().ok_or(InvalidReadyUrl)?;
+    if *scheme != http::uri::Scheme::HTTP && *scheme != http::uri::Scheme::HTTPS {
+        return Err(InvalidReadyUrl);
+    }
+    if uri.host().is_none() {
+        return Err(InvalidReadyUrl);
+    }
+    Ok(uri)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidReadyUrl;
+
+impl fmt::Display for InvalidReadyUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid URL; expected http[s]://HOST[:PORT]/PATH")
+    }
+}
+
+impl error::Error for InvalidReadyUrl {}
+
+/// Parses an `--http` probe: an `http` or `https` URL whose fragment may set
+/// `status` (as for `--expect-status`) and `body`, e.g.
+/// `http://api:8080/healthz#status=200,204&body=ok`.
+fn parse_http_probe(s: &str) -> Result<HttpProbe, InvalidHttpProbe> {
+    let (url, fragment) = s.split_once('#').unwrap_or((s, ""));
+    let uri = parse_ready_url(url).map_err(|_| InvalidHttpProbe)?;
+    let mut probe = HttpProbe {
+        uri,
+        expected_status: None,
+        expected_body: None,
+    };
+    for option in fragment.split('&').filter(|o| !o.is_empty()) {
+        match option.split_once('=') {
+            Some(("status", statuses)) if probe.expected_status.is_none() => {
+                probe.expected_status = Some(statuses.parse().map_err(|_| InvalidHttpProbe)?);
+            }
+            Some(("body", text)) if probe.expected_body.is_none() && !text.is_empty() => {
+                probe.expected_body = Some(text.to_string());
+            }
+            _ => return Err(InvalidHttpProbe),
+        }
+    }
+    Ok(probe)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidHttpProbe;
+
+impl fmt::Display for InvalidHttpProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid probe; expected http[s]://HOST[:PORT]/PATH[#status=STATUSES][&body=TEXT]"
+        )
+    }
+}
+
+impl error::Error for InvalidHttpProbe {}
+
+/// Parses the path (and optionally the query) of a readiness endpoint.
+fn parse_ready_path(s: &str) -> Result<String, InvalidReadyPath> {
+    if !s.starts_with('/') || http::uri::PathAndQuery::from_str(s).is_err() {
+        return Err(InvalidReadyPath);
+    }
+    Ok(s.to_string())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidReadyPath;
+
+impl fmt::Display for InvalidReadyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid path; expected an absolute path, e.g. /ready")
+    }
+}
+
+impl error::Error for InvalidReadyPath {}
+
+/// Parses a Kubernetes namespace name, which must be a DNS label.
+fn parse_namespace(s: &str) -> Result<String, InvalidNamespace> {
+    let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+    if s.is_empty() || s.len() > 63 || !s.chars().all(valid) {
+        return Err(InvalidNamespace);
+    }
+    Ok(s.to_string())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidNamespace;
+
+impl fmt::Display for InvalidNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid namespace; expected a DNS label")
+    }
+}
+
+impl error::Error for InvalidNamespace {}
+
+/// Parses file permissions in octal, e.g. `0600`.
+fn parse_mode(s: &str) -> Result<u32, InvalidMode> {
+    match u32::from_str_radix(s, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(InvalidMode),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidMode;
+
+impl fmt::Display for InvalidMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid mode; expected octal permissions, e.g. 0600")
+    }
+}
+
+impl error::Error for InvalidMode {}
+
+/// Parses a `--barrier` marker name, which must be a plain file name.
+fn parse_marker(s: &str) -> Result<String, InvalidMarker> {
+    // Hidden names are reserved for the temporary files markers are written to.
+    if s.is_empty() || s.starts_with('.') || s.contains('/') {
+        return Err(InvalidMarker);
+    }
+    Ok(s.to_string())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidMarker;
+
+impl fmt::Display for InvalidMarker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid marker name; expected a file name")
+    }
+}
+
+impl error::Error for InvalidMarker {}
+
+/// Parses a network interface name, which the kernel limits to 15 bytes.
+fn parse_interface(s: &str) -> Result<String, InvalidInterface> {
+    let valid = |c: char| c.is_ascii_graphic() && c != '/' && c != ':';
+    if s.is_empty() || s.len() > 15 || !s.chars().all(valid) {
+        return Err(InvalidInterface);
+    }
+    Ok(s.to_string())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidInterface;
+
+impl fmt::Display for InvalidInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid interface name")
+    }
+}
+
+impl error::Error for InvalidInterface {}
+
+/// Parses a Kubernetes Lease name, which must be a DNS subdomain.
+fn parse_lease_name(s: &str) -> Result<String, InvalidLeaseName> {
+    let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.';
+    if s.is_empty() || s.len() > 253 || !s.chars().all(valid) {
+        return Err(InvalidLeaseName);
+    }
+    Ok(s.to_string())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidLeaseName;
+
+impl fmt::Display for InvalidLeaseName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid lease name; expected a DNS subdomain")
+    }
+}
+
+impl error::Error for InvalidLeaseName {}
+
+/// Parses a `HOST:PORT` admin server address, e.g. `10.0.0.1:4191`,
+/// `linkerd-proxy:4191`, or `[::1]:4191`. IPv6 addresses must be bracketed.
+fn parse_admin_addr(s: &str) -> Result<(String, u16), InvalidAdminAddr> {
+    let (host, port) = match s.strip_prefix('[') {
+        Some(rest) => {
+            let (ip, port) = rest.split_once("]:").ok_or(InvalidAdminAddr)?;
+            let ip = std::net::Ipv6Addr::from_str(ip).map_err(|_| InvalidAdminAddr)?;
+            (ip.to_string(), port)
+        }
+        None => {
+            let (host, port) = s.rsplit_once(':').ok_or(InvalidAdminAddr)?;
+            if host.is_empty() || host.contains(':') {
+                return Err(InvalidAdminAddr);
+            }
+            (host.to_string(), port)
+        }
+    };
+    let port = port.parse().map_err(|_| InvalidAdminAddr)?;
+    Ok((host, port))
+}
+
+/// Returns the admin server address that the proxy is configured to listen
+/// on, if `LINKERD2_PROXY_ADMIN_LISTEN_ADDR` is set.
+fn discover_admin_addr() -> Result<Option<(String, u16)>, Error> {
+    match std::env::var(ADMIN_LISTEN_ADDR_ENV) {
+        Ok(v) if !v.trim().is_empty() => {
+            parse_admin_listen_addr(v.trim()).map(Some).map_err(|_| {
+                Error::InvalidConfig(format!(
+                    "{} is not a valid address: {:?}",
+                    ADMIN_LISTEN_ADDR_ENV, v
+                ))
+            })
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses the proxy's admin listen address, e.g. `0.0.0.0:4191` or
+/// `[::]:4191`. Unspecified addresses are reached through `localhost`. A bare
+/// port is also accepted so that the variable can be populated from the
+/// `config.linkerd.io/admin-port` annotation through the downward API.
+fn parse_admin_listen_addr(s: &str) -> Result<(String, u16), InvalidAdminAddr> {
+    if let Ok(port) = s.parse() {
+        return Ok((DEFAULT_ADMIN_HOST.to_string(), port));
+    }
+    let (host, port) = parse_admin_addr(s)?;
+    let unspecified = std::net::IpAddr::from_str(&host).is_ok_and(|ip| ip.is_unspecified());
+    if unspecified {
+        return Ok((DEFAULT_ADMIN_HOST.to_string(), port));
+    }
+    Ok((host, port))
+}
+
+/// Parses a `--tcp` address, which has the same form as `--admin-addr`.
+fn parse_tcp_addr(s: &str) -> Result<String, InvalidAdminAddr> {
+    parse_admin_addr(s)?;
+    Ok(s.to_string())
+}
+
+/// Parses a `--pre-resolve` destination into the URI of its root.
+fn parse_pre_resolve(s: &str) -> Result<http::Uri, InvalidAdminAddr> {
+    parse_admin_addr(s)?;
+    http::Uri::from_str(&format!("http://{}/", s)).map_err(|_| InvalidAdminAddr)
+}
+
+/// Parses a `--grpc` probe: a `HOST:PORT` address, as for `--admin-addr`,
+/// optionally followed by the `/SERVICE` whose health is checked.
+fn parse_grpc_probe(s: &str) -> Result<(http::Uri, String), InvalidGrpcProbe> {
+    let (addr, service) = s.split_once('/').unwrap_or((s, ""));
+    parse_admin_addr(addr).map_err(|_| InvalidGrpcProbe)?;
+    let authority = http::uri::Authority::from_str(addr).map_err(|_| InvalidGrpcProbe)?;
+    let uri = check::GrpcCheck::uri(authority).map_err(|_| InvalidGrpcProbe)?;
+    Ok((uri, service.to_string()))
+}
+
+/// Parses a `--dns` probe: a host name, optionally followed by the minimum
+/// number of addresses it must resolve to, e.g. `db.example.com/3`.
+fn parse_dns_probe(s: &str) -> Result<(String, usize), InvalidDnsProbe> {
+    let (name, count) = match s.split_once('/') {
+        Some((name, count)) => (name, count.parse().map_err(|_| InvalidDnsProbe)?),
+        None => (s, 1),
+    };
+    let valid = |label: &str| {
+        !label.is_empty()
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    };
+    if count == 0 || !name.trim_end_matches('.').split('.').all(valid) {
+        return Err(InvalidDnsProbe);
+    }
+    Ok((name.to_string(), count))
+}
+
+/// Parses an `--exec-probe` command, which is split into words as
+/// `LINKERD_AWAIT_OPTS` is, e.g. `redis-cli -h cache ping`.
+fn parse_exec_probe(s: &str) -> Result<ExecProbe, InvalidExecProbe> {
+    let mut words = split_opts(s).map_err(|_| InvalidExecProbe)?.into_iter();
+    let cmd = words.next().ok_or(InvalidExecProbe)?;
+    Ok(ExecProbe {
+        cmd,
+        args: words.collect(),
+    })
+}
+
+/// Parses a `--require` expression: a probe, written as its flag and value
+/// (e.g. `tcp=db:5432`), an `all(...)` or `any(...)` group of expressions, or
+/// a `retry(...)` of an expression and its options (e.g.
+/// `retry(tcp=db:5432, timeout=2m)`). Values that contain commas or
+/// parentheses may be quoted.
+fn parse_require(s: &str) -> Result<Probe, InvalidRequire> {
+    let mut rest = s;
+    let probe = parse_require_expr(&mut rest, 0)?;
+    match rest.trim() {
+        "" => Ok(probe),
+        rest => Err(InvalidRequire(format!("unexpected {:?}", rest))),
+    }
+}
+
+/// Parses the expression at the start of `s`, advancing past it. `depth` is
+/// the number of groups that enclose it.
+fn parse_require_expr(s: &mut &str, depth: usize) -> Result<Probe, InvalidRequire> {
+    let (name, rest) = split_require_name(s);
+
+    if let Some(mut rest) = rest.strip_prefix('(') {
+        if depth == MAX_REQUIRE_DEPTH {
+            return Err(InvalidRequire(format!(
+                "groups may be nested at most {} deep",
+                MAX_REQUIRE_DEPTH
+            )));
+        }
+        if name == "retry" {
+            let probe = parse_require_expr(&mut rest, depth + 1)?;
+            let mut retry = ProbeRetry::default();
+            loop {
+                rest = rest.trim_start();
+                if let Some(r) = rest.strip_prefix(')') {
+                    rest = r;
+                    break;
+                }
+                rest = rest
+                    .strip_prefix(',')
+                    .ok_or_else(|| InvalidRequire("expected `,` or `)` in retry(...)".into()))?;
+                let (option, r) = split_require_name(rest);
+                let (value, r) = split_require_value(r)?;
+                rest = r;
+                let invalid = |e: &dyn fmt::Display| InvalidRequire(format!("{}: {}", option, e));
+                match option {
+                    "timeout" => {
+                        retry.timeout = Some(parse_duration(value).map_err(|e| invalid(&e))?)
+                    }
+                    "backoff" => {
+                        retry.backoff = Some(parse_duration(value).map_err(|e| invalid(&e))?)
+                    }
+                    "max-attempts" => {
+                        retry.max_attempts = Some(parse_count(value).map_err(|e| invalid(&e))?)
+                    }
+                    _ => {
+                        return Err(InvalidRequire(format!(
+                            "unknown option {:?}; expected timeout, backoff, or max-attempts",
+                            option
+                        )))
+                    }
+                }
+            }
+            *s = rest;
+            return Ok(Probe::Retry(Box::new(probe), retry));
+        }
+
+        let mut probes = Vec::new();
+        loop {
+            probes.push(parse_require_expr(&mut rest, depth + 1)?);
+            rest = rest.trim_start();
+            if let Some(r) = rest.strip_prefix(',') {
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix(')') {
+                rest = r;
+                break;
+            } else {
+                return Err(InvalidRequire(format!(
+                    "expected `,` or `)` in {}(...)",
+                    name
+                )));
+            }
+        }
+        *s = rest;
+        return match name {
+            "all" => Ok(Probe::All(probes)),
+            "any" => Ok(Probe::Any(probes)),
+            _ => Err(InvalidRequire(format!(
+                "unknown group {:?}; expected all, any, or retry",
+                name
+            ))),
+        };
+    }
+
+    let (value, rest) = split_require_value(rest)?;
+    *s = rest;
+
+    let invalid = |e: &dyn fmt::Display| InvalidRequire(format!("{}: {}", name, e));
+    let probe = match name {
+        "sidecar" => Probe::Sidecar(parse_ready_url(value).map_err(|e| invalid(&e))?),
+        "tcp" => Probe::Tcp(parse_tcp_addr(value).map_err(|e| invalid(&e))?),
+        "http" => Probe::Http(parse_http_probe(value).map_err(|e| invalid(&e))?),
+        "grpc" => {
+            let (uri, service) = parse_grpc_probe(value).map_err(|e| invalid(&e))?;
+            Probe::Grpc(uri, service)
+        }
+        "dns" => {
+            let (name, count) = parse_dns_probe(value).map_err(|e| invalid(&e))?;
+            Probe::Dns(name, count)
+        }
+        "file" if !value.is_empty() => Probe::File(value.into()),
+        "unix-socket" if !value.is_empty() => Probe::UnixSocket(value.into()),
+        "vault-agent-token" if !value.is_empty() => Probe::VaultToken(value.into()),
+        "exec-probe" => Probe::Exec(parse_exec_probe(value).map_err(|e| invalid(&e))?),
+        "postgres" => Probe::Database(parse_postgres_url(value).map_err(|e| invalid(&e))?),
+        "mysql" => Probe::Database(parse_mysql_url(value).map_err(|e| invalid(&e))?),
+        "redis" => Probe::Database(parse_redis_url(value).map_err(|e| invalid(&e))?),
+        "file" | "unix-socket" | "vault-agent-token" => {
+            return Err(invalid(&"path must not be empty"))
+        }
+        _ => {
+            return Err(InvalidRequire(format!(
+                "unknown probe {:?}; expected a probe flag, e.g. tcp",
+                name
+            )))
+        }
+    };
+    Ok(probe)
+}
+
+/// Splits the name of a group, probe, or option from the start of `s`.
+fn split_require_name(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    let end = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(s.len());
+    let (name, rest) = s.split_at(end);
+    (name, rest.trim_start())
+}
+
+/// Splits an `=VALUE` from the start of `s`. Unless it's quoted, the value
+/// ends at the next comma or closing parenthesis.
+fn split_require_value(s: &str) -> Result<(&str, &str), InvalidRequire> {
+    let s = s.strip_prefix('=').ok_or_else(|| {
+        InvalidRequire("expected all(...), any(...), retry(...), or FLAG=VALUE".to_string())
+    })?;
+    let s = s.trim_start();
+    match s.chars().next() {
+        Some(quote @ ('\'' | '"')) => s[1..]
+            .split_once(quote)
+            .ok_or_else(|| InvalidRequire("unterminated quote".to_string())),
+        _ => {
+            let end = s.find([',', ')']).unwrap_or(s.len());
+            Ok((s[..end].trim_end(), &s[end..]))
+        }
+    }
+}
+
+/// Parses a `--stage`: a comma-separated list of `--require` expressions,
+/// which must all succeed.
+fn parse_stage(s: &str) -> Result<Probe, InvalidRequire> {
+    let mut rest = s;
+    let mut probes = vec![parse_require_expr(&mut rest, 0)?];
+    loop {
+        rest = rest.trim_start();
+        match rest.strip_prefix(',') {
+            Some(r) => rest = r,
+            None if rest.is_empty() => return Ok(Probe::All(probes)),
+            None => return Err(InvalidRequire(format!("unexpected {:?}", rest))),
+        }
+        probes.push(parse_require_expr(&mut rest, 0)?);
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct InvalidRequire(String);
+
+impl fmt::Display for InvalidRequire {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid expression: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidRequire {}
+
+/// Parses a `--cloud-sql-proxy` readiness URL or port.
+fn parse_cloud_sql_proxy(s: &str) -> Result<CloudSqlProxy, InvalidReadyUrl> {
+    match u16::from_str(s.trim()) {
+        Ok(port) if port > 0 => Ok(CloudSqlProxy::Port(port)),
+        _ => parse_ready_url(s).map(CloudSqlProxy::Ready),
+    }
+}
+
+/// Parses a `--postgres` connection string.
+fn parse_postgres_url(s: &str) -> Result<check::DatabaseUrl, check::InvalidDatabaseUrl> {
+    parse_database_url(s, check::Database::Postgres)
+}
+
+/// Parses a `--mysql` connection string.
+fn parse_mysql_url(s: &str) -> Result<check::DatabaseUrl, check::InvalidDatabaseUrl> {
+    parse_database_url(s, check::Database::Mysql)
+}
+
+/// Parses a `--redis` connection string.
+fn parse_redis_url(s: &str) -> Result<check::DatabaseUrl, check::InvalidDatabaseUrl> {
+    parse_database_url(s, check::Database::Redis)
+}
+
+/// Parses a connection string whose scheme must match its flag's database.
+fn parse_database_url(
+    s: &str,
+    database: check::Database,
+) -> Result<check::DatabaseUrl, check::InvalidDatabaseUrl> {
+    let url = check::DatabaseUrl::from_str(s)?;
+    if url.database() != database {
+        return Err(check::InvalidDatabaseUrl);
+    }
+    Ok(url)
+}
+
+/// Parses a `wait-for` target: a `HOST:PORT` address, as wait-for-it.sh
+/// accepts, or a URL, as dockerize's `-wait` accepts.
+fn parse_wait_for_target(s: &str) -> Result<Probe, InvalidWaitForTarget> {
+    let s = s.trim();
+    match s.split_once("://") {
+        None => parse_tcp_addr(s)
+            .map(Probe::Tcp)
+            .map_err(|_| InvalidWaitForTarget),
+        Some(("tcp" | "tcp4" | "tcp6", addr)) => parse_tcp_addr(addr.trim_end_matches('/'))
+            .map(Probe::Tcp)
+            .map_err(|_| InvalidWaitForTarget),
+        Some(("http" | "https", _)) => parse_ready_url(s)
+            .map(Probe::Sidecar)
+            .map_err(|_| InvalidWaitForTarget),
+        Some(("unix", path)) if !path.is_empty() => Ok(Probe::UnixSocket(PathBuf::from(path))),
+        Some(("file", path)) if !path.is_empty() => Ok(Probe::File(PathBuf::from(path))),
+        Some(_) => Err(InvalidWaitForTarget),
+    }
+}
+
+/// Parses a `wait-for` timeout: a number of seconds, as wait-for-it.sh
+/// accepts, or a duration, as dockerize accepts.
+fn parse_wait_for_timeout(s: &str) -> Result<time::Duration, InvalidDuration> {
+    match u64::from_str(s.trim()) {
+        Ok(secs) => Ok(time::Duration::from_secs(secs)),
+        Err(_) => parse_duration(s),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidWaitForTarget;
+
+impl fmt::Display for InvalidWaitForTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid target; expected HOST:PORT or a tcp, http, https, unix, or file URL"
+        )
+    }
+}
+
+impl error::Error for InvalidWaitForTarget {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidExecProbe;
+
+impl fmt::Display for InvalidExecProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid probe; expected a command and its arguments")
+    }
+}
+
+impl error::Error for InvalidExecProbe {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidDnsProbe;
+
+impl fmt::Display for InvalidDnsProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid probe; expected NAME[/COUNT]")
+    }
+}
+
+impl error::Error for InvalidDnsProbe {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidGrpcProbe;
+
+impl fmt::Display for InvalidGrpcProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid probe; expected HOST:PORT[/SERVICE]")
+    }
+}
+
+impl error::Error for InvalidGrpcProbe {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidAdminAddr;
+
+impl fmt::Display for InvalidAdminAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid address; expected HOST:PORT or [IPV6]:PORT")
+    }
+}
+
+impl error::Error for InvalidAdminAddr {}
+
+/// Parses a `HOST=IP` override for `--resolve`.
+fn parse_resolve(s: &str) -> Result<(String, IpAddr), InvalidResolve> {
+    let (host, ip) = s.split_once('=').ok_or(InvalidResolve)?;
+    let host = host.trim();
+    if host.is_empty() {
+        return Err(InvalidResolve);
+    }
+    let ip = IpAddr::from_str(ip.trim()).map_err(|_| InvalidResolve)?;
+    Ok((host.to_string(), ip))
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidResolve;
+
+impl fmt::Display for InvalidResolve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid override; expected HOST=IP")
+    }
+}
+
+impl error::Error for InvalidResolve {}
+
+/// Parses the admin server's scheme for `--scheme`.
+fn parse_scheme(s: &str) -> Result<http::uri::Scheme, InvalidScheme> {
+    match s {
+        "http" => Ok(http::uri::Scheme::HTTP),
+        "https" => Ok(http::uri::Scheme::HTTPS),
+        _ => Err(InvalidScheme),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidScheme;
+
+impl fmt::Display for InvalidScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid scheme; expected http or https")
+    }
+}
+
+impl error::Error for InvalidScheme {}
+
+/// Parses an HTTP method for `--shutdown-method`, e.g. `POST` or `GET`.
+fn parse_method(s: &str) -> Result<http::Method, InvalidMethod> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(InvalidMethod);
+    }
+    http::Method::from_bytes(s.as_bytes()).map_err(|_| InvalidMethod)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidMethod;
+
+impl fmt::Display for InvalidMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid method; expected an uppercase HTTP method (e.g. POST)"
+        )
+    }
+}
+
+impl error::Error for InvalidMethod {}
+
+/// Splits `LINKERD_AWAIT_OPTS` into words as a shell would, without
+/// expansions: words are separated by whitespace, which quotes and
+/// backslashes escape.
+fn split_opts(s: &str) -> Result<Vec<String>, InvalidOpts> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            words.extend(word.take());
+            continue;
+        }
+        let word = word.get_or_insert_with(String::new);
+        match c {
+            '\'' => loop {
+                match chars.next().ok_or(InvalidOpts)? {
+                    '\'' => break,
+                    c => word.push(c),
+                }
+            },
+            '"' => loop {
+                match chars.next().ok_or(InvalidOpts)? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or(InvalidOpts)? {
+                        c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                        c => {
+                            word.push('\\');
+                            word.push(c);
+                        }
+                    },
+                    c => word.push(c),
+                }
+            },
+            '\\' => word.push(chars.next().ok_or(InvalidOpts)?),
+            c => word.push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidOpts;
+
+impl fmt::Display for InvalidOpts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unterminated quote or escape")
+    }
+}
+
+impl error::Error for InvalidOpts {}
+
+/// Parses a `Name: value` header for `--header`.
+fn parse_header(s: &str) -> Result<(http::HeaderName, http::HeaderValue), InvalidHeader> {
+    let (name, value) = s.split_once(':').ok_or(InvalidHeader)?;
+    let name = http::HeaderName::from_str(name.trim()).map_err(|_| InvalidHeader)?;
+    let value = http::HeaderValue::from_str(value.trim()).map_err(|_| InvalidHeader)?;
+    Ok((name, value))
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidHeader;
+
+impl fmt::Display for InvalidHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid header; expected 'Name: value'")
+    }
+}
+
+impl error::Error for InvalidHeader {}
+
+/// Parses a DNS server address, defaulting to port 53 (e.g. `10.96.0.10` or
+/// `[fd00::a]:5353`).
+fn parse_dns_server(s: &str) -> Result<SocketAddr, InvalidDnsServer> {
+    let s = s.trim();
+    if let Ok(addr) = SocketAddr::from_str(s) {
+        return Ok(addr);
+    }
+    let ip = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(s);
+    IpAddr::from_str(ip)
+        .map(|ip| SocketAddr::new(ip, 53))
+        .map_err(|_| InvalidDnsServer)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct InvalidDnsServer;
+
+impl fmt::Display for InvalidDnsServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid DNS server; expected IP[:PORT]")
+    }
+}
+
+impl error::Error for InvalidDnsServer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("linkerd-await").chain(args.iter().copied()))
+            .expect("arguments must be valid")
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        assert!(parse_args(&[]).validate(false).is_empty());
+        assert!(parse_args(&["--timeout=2m", "--backoff=1s"])
+            .validate(false)
+            .is_empty());
+        assert!(parse_args(&["--shutdown", "--", "true"])
+            .validate(false)
+            .is_empty());
+        assert!(
+            parse_args(&["--backoff-strategy=exponential", "--backoff-max=10s"])
+                .validate(false)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parse_check_interval() {
+        let args = parse_args(&["--check-interval=500ms", "--timeout=400ms"]);
+        assert_eq!(args.check_interval, Some(time::Duration::from_millis(500)));
+        assert_eq!(
+            args.validate(false),
+            ["--check-interval 500ms is larger than --timeout 400ms; at most one readiness check will be attempted"]
+        );
+        assert!(parse_args(&["--check-interval=100ms", "--timeout=400ms"])
+            .validate(false)
+            .is_empty());
+        assert!(
+            Args::try_parse_from(["linkerd-await", "--check-interval=500ms", "--backoff=1s"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_warnings() {
+        assert_eq!(
+            parse_args(&["--timeout=1s", "--backoff=5s"])
+                .validate(false)
+                .len(),
+            1
+        );
+        // --timeout-fatal defaults to true, which only conflicts with a
+        // disabled timeout when it's set explicitly.
+        assert!(parse_args(&["--timeout=0"]).validate(false).is_empty());
+        assert_eq!(
+            parse_args(&["--timeout=0", "--timeout-fatal", "--", "true"])
+                .validate(false)
+                .len(),
+            1
+        );
+        assert_eq!(
+            parse_args(&["--backoff=5s", "--backoff-max=1s"])
+                .validate(false)
+                .len(),
+            1
+        );
+        assert_eq!(parse_args(&["--backoff-max=10s"]).validate(false).len(), 1);
+        assert_eq!(
+            parse_args(&["--timeout=5s", "--initial-delay=5s"])
+                .validate(false)
+                .len(),
+            1
+        );
+        assert_eq!(
+            parse_args(&["--port=0", "--shutdown", "--", "true"])
+                .validate(false)
+                .len(),
+            1
+        );
+        assert_eq!(
+            parse_args(&["--shutdown", "--", "true"])
+                .validate(true)
+                .len(),
+            1
+        );
+        assert_eq!(
+            parse_args(&["--block-signals=HUP", "--", "true"])
+                .validate(false)
+                .len(),
+            1
+        );
+        // A blocked signal that isn't forwarded anyway is still ignored.
+        assert!(
+            parse_args(&["--shutdown", "--block-signals=HUP,INT", "--", "true"])
+                .validate(false)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(parse_percent("50%"), Ok(50));
+        assert_eq!(parse_percent(" 1 "), Ok(1));
+        assert_eq!(parse_percent("99%"), Ok(99));
+        assert_eq!(parse_percent("0%"), Err(InvalidPercent));
+        assert_eq!(parse_percent("100%"), Err(InvalidPercent));
+        assert_eq!(parse_percent("%"), Err(InvalidPercent));
+        assert_eq!(parse_percent("0.5"), Err(InvalidPercent));
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("30"), Ok(30));
+        assert_eq!(parse_count("0"), Err(InvalidCount));
+        assert_eq!(parse_count("-1"), Err(InvalidCount));
+        assert_eq!(parse_count("x"), Err(InvalidCount));
+
+        assert_eq!(parse_args(&["--success-threshold=3"]).success_threshold, 3);
+        assert_eq!(
+            parse_args(&["--success-interval=100ms"])
+                .validate(false)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_multiplier() {
+        assert_eq!(parse_multiplier("1.5"), Ok(1.5));
+        assert_eq!(parse_multiplier(" 3 "), Ok(3.0));
+        assert_eq!(parse_multiplier("0.5"), Err(InvalidMultiplier));
+        assert_eq!(parse_multiplier("inf"), Err(InvalidMultiplier));
+        assert_eq!(parse_multiplier("NaN"), Err(InvalidMultiplier));
+
+        assert_eq!(
+            parse_args(&["--backoff-multiplier=3"])
+                .validate(false)
+                .len(),
+            1
+        );
+        assert!(parse_args(&[
+            "--backoff-strategy=exponential",
+            "--backoff-multiplier=3",
+            "--backoff-jitter=20%"
+        ])
+        .validate(false)
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("SIGUSR1"), Ok(Signal::SIGUSR1));
+        assert_eq!(parse_signal("usr2"), Ok(Signal::SIGUSR2));
+        assert_eq!(parse_signal(" HUP "), Ok(Signal::SIGHUP));
+        assert_eq!(parse_signal(""), Err(InvalidSignal));
+        assert_eq!(parse_signal("SIGFOO"), Err(InvalidSignal));
+    }
+
+    #[test]
+    fn test_parse_forwarded_signal() {
+        assert_eq!(parse_forwarded_signal("usr1"), Ok(Signal::SIGUSR1));
+        assert_eq!(parse_forwarded_signal("TERM"), Ok(Signal::SIGTERM));
+        assert_eq!(parse_forwarded_signal("KILL"), Err(InvalidForwardedSignal));
+        assert_eq!(
+            parse_forwarded_signal("SIGCHLD"),
+            Err(InvalidForwardedSignal)
+        );
+        assert_eq!(
+            parse_forwarded_signal("SIGFOO"),
+            Err(InvalidForwardedSignal)
+        );
+
+        let args = parse_args(&["--shutdown", "--forward-signals=TERM,USR1", "--", "true"]);
+        assert_eq!(args.forward_signals, [Signal::SIGTERM, Signal::SIGUSR1]);
+        assert_eq!(
+            forwarded_signals(vec![], &[Signal::SIGINT]),
+            [Signal::SIGTERM]
+        );
+    }
+
+    #[test]
+    fn test_mesh_profile() {
+        let linkerd = Mesh::Linkerd.profile();
+        assert_eq!(linkerd.ready_port, 4191);
+        assert_eq!(linkerd.ready_path, "/ready");
+        assert_eq!(linkerd.expected_status, check::StatusSet::default());
+        assert_eq!(linkerd.shutdown_port, None);
+        assert_eq!(linkerd.shutdown_path, "/shutdown");
+        assert_eq!(linkerd.shutdown_method, http::Method::POST);
+
+        let istio = Mesh::Istio.profile();
+        assert_eq!(istio.ready_port, 15021);
+        assert_eq!(istio.shutdown_port, Some(15020));
+        assert_eq!(istio.shutdown_path, "/quitquitquit");
+    }
+
+    #[test]
+    fn test_parse_method() {
+        assert_eq!(parse_method("POST"), Ok(http::Method::POST));
+        assert_eq!(parse_method("GET"), Ok(http::Method::GET));
+        assert_eq!(
+            parse_method("QUIT"),
+            Ok(http::Method::from_bytes(b"QUIT").unwrap())
+        );
+        assert_eq!(parse_method("post"), Err(InvalidMethod));
+        assert_eq!(parse_method(""), Err(InvalidMethod));
+        assert_eq!(parse_method("PO ST"), Err(InvalidMethod));
+        assert_eq!(
+            parse_args(&["--shutdown-method=GET"]).validate(false),
+            ["--shutdown-method has no effect without --shutdown"]
+        );
+        assert!(parse_args(&[
+            "--port=0",
+            "--shutdown",
+            "--shutdown-url=http://127.0.0.1:15000/quitquitquit",
+            "--",
+            "true"
+        ])
+        .validate(false)
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parse_shutdown_path() {
+        let args = parse_args(&["--shutdown", "--shutdown-path=/quitquitquit", "--", "true"]);
+        assert_eq!(args.shutdown_path.as_deref(), Some("/quitquitquit"));
+        assert!(args.validate(false).is_empty());
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--shutdown-path=/quitquitquit",
+            "--shutdown-url=http://127.0.0.1:15000/quitquitquit",
+        ])
+        .is_err());
+        assert!(Args::try_parse_from(["linkerd-await", "--shutdown-path=quit"]).is_err());
+        assert_eq!(
+            parse_args(&["--shutdown-path=/quitquitquit"]).validate(false),
+            ["--shutdown-path has no effect without --shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_parse_signal_proxy() {
+        let args = parse_args(&["--shutdown", "--signal-proxy", "--", "true"]);
+        assert_eq!(args.signal_proxy.as_deref(), Some("linkerd2-proxy"));
+        assert!(args.validate(false).is_empty());
+        assert_eq!(
+            parse_args(&["--signal-proxy=envoy"])
+                .signal_proxy
+                .as_deref(),
+            Some("envoy")
+        );
+        assert_eq!(
+            parse_args(&["--signal-proxy"]).validate(false),
+            ["--signal-proxy has no effect without --shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_parse_verify_shutdown() {
+        let args = parse_args(&["--shutdown", "--verify-shutdown", "--", "true"]);
+        assert_eq!(args.verify_shutdown, Some(time::Duration::from_secs(10)));
+        assert!(args.validate(false).is_empty());
+        assert_eq!(
+            parse_args(&["--verify-shutdown=30s"]).verify_shutdown,
+            Some(time::Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_args(&["--shutdown", "--shutdown-delay=5s", "--", "true"]).shutdown_delay,
+            Some(time::Duration::from_secs(5))
+        );
+        assert_eq!(
+            parse_args(&["--shutdown", "--shutdown-on-success-only", "--", "true"])
+                .shutdown_on_success_only,
+            Some(time::Duration::from_secs(300))
+        );
+        assert_eq!(
+            parse_args(&["--shutdown-on-success-only=30s"]).validate(false),
+            ["--shutdown-on-success-only has no effect without --shutdown"]
+        );
+        assert_eq!(
+            parse_args(&["--shutdown-delay=5s"]).validate(false),
+            ["--shutdown-delay has no effect without --shutdown"]
+        );
+        assert_eq!(
+            parse_args(&["--verify-shutdown"]).validate(false),
+            ["--verify-shutdown has no effect without --shutdown"]
+        );
+        assert_eq!(
+            parse_args(&["--shutdown-timeout=1m"]).shutdown_timeout,
+            Some(time::Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_args(&["--shutdown-timeout=1m"]).validate(false),
+            ["--shutdown-timeout has no effect without --shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_parse_sidecar() {
+        let args = parse_args(&[
+            "--sidecar=http://127.0.0.1:8200/v1/sys/health",
+            "--sidecar",
+            "http://127.0.0.1:9801/readiness",
+        ]);
+        assert_eq!(
+            args.sidecar,
+            [
+                http::Uri::from_static("http://127.0.0.1:8200/v1/sys/health"),
+                http::Uri::from_static("http://127.0.0.1:9801/readiness"),
+            ]
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "--sidecar=127.0.0.1:8200"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_tcp_addr() {
+        assert_eq!(
+            parse_tcp_addr("postgres:5432"),
+            Ok("postgres:5432".to_string())
+        );
+        assert_eq!(parse_tcp_addr("[::1]:6379"), Ok("[::1]:6379".to_string()));
+        assert_eq!(parse_tcp_addr("postgres"), Err(InvalidAdminAddr));
+        assert_eq!(parse_tcp_addr("::1:6379"), Err(InvalidAdminAddr));
+        let args = parse_args(&["--tcp=postgres:5432", "--tcp", "10.0.0.1:6379"]);
+        assert_eq!(args.tcp, ["postgres:5432", "10.0.0.1:6379"]);
+    }
+
+    #[test]
+    fn test_parse_http_probe() {
+        assert_eq!(
+            parse_http_probe("http://api:8080/healthz"),
+            Ok(HttpProbe {
+                uri: http::Uri::from_static("http://api:8080/healthz"),
+                expected_status: None,
+                expected_body: None,
+            })
+        );
+        assert_eq!(
+            parse_http_probe("https://api/ready?full=1#status=200,204&body=ok"),
+            Ok(HttpProbe {
+                uri: http::Uri::from_static("https://api/ready?full=1"),
+                expected_status: Some("200,204".parse().unwrap()),
+                expected_body: Some("ok".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_http_probe("http://api/healthz#body=UP").map(|p| p.expected_body),
+            Ok(Some("UP".to_string()))
+        );
+        for probe in [
+            "api:8080/healthz",
+            "tcp://api:8080",
+            "http://api/healthz#status=ok",
+            "http://api/healthz#body=",
+            "http://api/healthz#status=200&status=204",
+            "http://api/healthz#timeout=1s",
+        ] {
+            assert_eq!(parse_http_probe(probe), Err(InvalidHttpProbe), "{}", probe);
+        }
+        let args = parse_args(&["--http=http://api/healthz", "--http", "http://db/ready"]);
+        assert_eq!(args.http.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_grpc_probe() {
+        let probe = |s| {
+            parse_grpc_probe(s)
+                .map(|(uri, service)| (uri.authority().unwrap().to_string(), service))
+        };
+        assert_eq!(
+            probe("orders:50051"),
+            Ok(("orders:50051".to_string(), String::new()))
+        );
+        assert_eq!(
+            probe("orders:50051/acme.orders.v1.Orders"),
+            Ok((
+                "orders:50051".to_string(),
+                "acme.orders.v1.Orders".to_string()
+            ))
+        );
+        assert_eq!(
+            probe("[::1]:50051/"),
+            Ok(("[::1]:50051".to_string(), String::new()))
+        );
+        assert_eq!(probe("orders"), Err(InvalidGrpcProbe));
+        assert_eq!(probe("orders/Orders"), Err(InvalidGrpcProbe));
+        assert_eq!(probe("orders:grpc"), Err(InvalidGrpcProbe));
+    }
+
+    #[test]
+    fn test_parse_dns_probe() {
+        assert_eq!(
+            parse_dns_probe("db.example.com"),
+            Ok(("db.example.com".to_string(), 1))
+        );
+        assert_eq!(
+            parse_dns_probe("kafka-headless.kafka.svc.cluster.local./3"),
+            Ok(("kafka-headless.kafka.svc.cluster.local.".to_string(), 3))
+        );
+        for probe in ["", "db..example", "db.example/0", "db.example/", "db:5432"] {
+            assert_eq!(parse_dns_probe(probe), Err(InvalidDnsProbe), "{}", probe);
+        }
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let args = parse_args(&["--file=/var/run/secrets/db/password", "--file", "token"]);
+        assert_eq!(
+            args.file,
+            [
+                PathBuf::from("/var/run/secrets/db/password"),
+                PathBuf::from("token")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_unix_socket() {
+        let args = parse_args(&[
+            "--unix-socket=/run/php-fpm.sock",
+            "--unix-socket",
+            "agent.sock",
+        ]);
+        assert_eq!(
+            args.unix_socket,
+            [
+                PathBuf::from("/run/php-fpm.sock"),
+                PathBuf::from("agent.sock")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_exec_probe() {
+        let args = parse_args(&[
+            "--exec-probe",
+            "pg_isready -h db",
+            "--exec-probe=sh -c 'test -S /run/agent.sock'",
+        ]);
+        assert_eq!(
+            args.exec_probe,
+            [
+                ExecProbe {
+                    cmd: "pg_isready".into(),
+                    args: vec!["-h".into(), "db".into()],
+                },
+                ExecProbe {
+                    cmd: "sh".into(),
+                    args: vec!["-c".into(), "test -S /run/agent.sock".into()],
+                },
+            ]
+        );
+        assert_eq!(args.exec_probe_timeout, time::Duration::from_secs(1));
+
+        assert_eq!(parse_exec_probe("  "), Err(InvalidExecProbe));
+        assert_eq!(parse_exec_probe("sh -c 'true"), Err(InvalidExecProbe));
+    }
+
+    #[test]
+    fn test_parse_databases() {
+        let args = parse_args(&[
+            "--postgres=postgresql://app:secret@db/orders",
+            "--mysql",
+            "mysql://mysql:3307",
+            "--redis=redis://:secret@cache",
+        ]);
+        assert_eq!(
+            args.postgres
+                .iter()
+                .chain(&args.mysql)
+                .chain(&args.redis)
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "postgres://db:5432/orders",
+                "mysql://mysql:3307",
+                "redis://cache:6379"
+            ]
+        );
+
+        assert!(parse_postgres_url("mysql://db").is_err());
+        assert!(parse_redis_url("cache:6379").is_err());
+    }
+
+    #[test]
+    fn test_parse_require() {
+        let args = parse_args(&[
+            "--require",
+            "any(postgres=postgres://primary, postgres=postgres://replica)",
+            "--require=all(tcp=cache:6379, any(file=/run/ready, exec-probe='sh -c \"test -S a,b\"'))",
+        ]);
+        assert_eq!(
+            args.require,
+            [
+                Probe::Any(vec![
+                    Probe::Database(parse_postgres_url("postgres://primary").unwrap()),
+                    Probe::Database(parse_postgres_url("postgres://replica").unwrap()),
+                ]),
+                Probe::All(vec![
+                    Probe::Tcp("cache:6379".into()),
+                    Probe::Any(vec![
+                        Probe::File("/run/ready".into()),
+                        Probe::Exec(ExecProbe {
+                            cmd: "sh".into(),
+                            args: vec!["-c".into(), "test -S a,b".into()],
+                        }),
+                    ]),
+                ]),
+            ]
+        );
+
+        assert_eq!(
+            parse_require(" dns = db.example.com/2 "),
+            Ok(Probe::Dns("db.example.com".into(), 2))
+        );
+        for invalid in [
+            "",
+            "all()",
+            "all(tcp=db:5432",
+            "some(tcp=db:5432)",
+            "tcp=db:5432)",
+            "tcp=db",
+            "nope=1",
+            "file=",
+            "exec-probe='true",
+        ] {
+            assert!(parse_require(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_require_depth() {
+        let nested = |depth| format!("{}tcp=db:5432{}", "all(".repeat(depth), ")".repeat(depth));
+        assert!(parse_require(&nested(MAX_REQUIRE_DEPTH)).is_ok());
+        assert_eq!(
+            parse_require(&nested(MAX_REQUIRE_DEPTH + 1)),
+            Err(InvalidRequire(
+                "groups may be nested at most 32 deep".to_string()
+            ))
+        );
+
+        // A pathological expression is rejected as an invalid value rather
+        // than overflowing the stack.
+        let error =
+            Args::try_parse_from(["linkerd-await", "--require", &nested(100_000)]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn test_parse_require_retry() {
+        assert_eq!(
+            parse_require(
+                "retry(any(tcp=a:1, tcp=b:1), timeout=2m, backoff = 5s, max-attempts=10)"
+            ),
+            Ok(Probe::Retry(
+                Box::new(Probe::Any(vec![
+                    Probe::Tcp("a:1".into()),
+                    Probe::Tcp("b:1".into())
+                ])),
+                ProbeRetry {
+                    timeout: Some(time::Duration::from_secs(120)),
+                    backoff: Some(time::Duration::from_secs(5)),
+                    max_attempts: Some(10),
+                }
+            ))
+        );
+        assert_eq!(
+            parse_require("retry(file=/run/a)"),
+            Ok(Probe::Retry(
+                Box::new(Probe::File("/run/a".into())),
+                ProbeRetry::default()
+            ))
+        );
+
+        for invalid in [
+            "retry()",
+            "retry(tcp=a:1, timeout=soon)",
+            "retry(tcp=a:1, max-attempts=0)",
+            "retry(tcp=a:1, jitter=10%)",
+            "retry(tcp=a:1 timeout=1s)",
+        ] {
+            assert!(parse_require(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_vault_agent_token() {
+        assert_eq!(parse_args(&[]).vault_agent_token, None);
+        assert_eq!(
+            parse_args(&["--vault-agent-token", "--", "true"]).vault_agent_token,
+            Some(PathBuf::from(DEFAULT_VAULT_TOKEN))
+        );
+        assert_eq!(
+            parse_args(&["--vault-agent-token=/home/vault/.vault-token"]).vault_agent_token,
+            Some(PathBuf::from("/home/vault/.vault-token"))
+        );
+        assert_eq!(
+            parse_require("vault-agent-token=/vault/secrets/token"),
+            Ok(Probe::VaultToken(DEFAULT_VAULT_TOKEN.into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_pre_resolve() {
+        assert_eq!(
+            parse_pre_resolve("web.emojivoto.svc.cluster.local:80"),
+            Ok(http::Uri::from_static(
+                "http://web.emojivoto.svc.cluster.local:80/"
+            ))
+        );
+        assert!(parse_pre_resolve("web.emojivoto.svc.cluster.local").is_err());
+        assert!(parse_pre_resolve("web:80/path").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            // Requests share a connection.
+            let (mut io, _) = listener.accept().await.unwrap();
+            for _ in 0..3 {
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                io.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+
+        Warmup {
+            connector: proxy::Connector::new(false),
+            uris: vec![uri.parse().unwrap()],
+            requests: 3,
+        }
+        .run()
+        .await;
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_parse_cloud_sql_proxy() {
+        let args = parse_args(&[
+            "--cloud-sql-proxy",
+            "--shutdown",
+            "--cloud-sql-proxy-shutdown",
+            "--",
+            "true",
+        ]);
+        assert_eq!(
+            args.cloud_sql_proxy,
+            Some(CloudSqlProxy::Ready(http::Uri::from_static(
+                DEFAULT_CLOUD_SQL_PROXY_READY
+            )))
+        );
+        assert_eq!(
+            args.cloud_sql_proxy_shutdown,
+            Some(http::Uri::from_static(DEFAULT_CLOUD_SQL_PROXY_SHUTDOWN))
+        );
+        assert!(args.validate(false).is_empty());
+
+        assert_eq!(
+            parse_args(&["--cloud-sql-proxy=5432"]).cloud_sql_proxy,
+            Some(CloudSqlProxy::Port(5432))
+        );
+        assert_eq!(
+            parse_args(&["--cloud-sql-proxy=http://127.0.0.1:8090/readiness"]).cloud_sql_proxy,
+            Some(CloudSqlProxy::Ready(http::Uri::from_static(
+                "http://127.0.0.1:8090/readiness"
+            )))
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "--cloud-sql-proxy=0"]).is_err());
+
+        assert_eq!(
+            parse_args(&["--cloud-sql-proxy-shutdown"]).validate(false),
+            ["--cloud-sql-proxy-shutdown has no effect without --shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_parse_stage() {
+        let args = parse_args(&[
+            "--stage=http=http://localhost:8080/warmup",
+            "--stage",
+            "tcp=db:5432, any(file=/run/a, file=/run/b)",
+        ]);
+        assert_eq!(
+            args.stage,
+            [
+                Probe::All(vec![Probe::Http(
+                    parse_http_probe("http://localhost:8080/warmup").unwrap()
+                )]),
+                Probe::All(vec![
+                    Probe::Tcp("db:5432".into()),
+                    Probe::Any(vec![
+                        Probe::File("/run/a".into()),
+                        Probe::File("/run/b".into())
+                    ]),
+                ]),
+            ]
+        );
+
+        for invalid in [
+            "",
+            "tcp=db:5432,",
+            "tcp=db:5432)",
+            "tcp=db:5432 file=/run/a",
+        ] {
+            assert!(parse_stage(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_probe_concurrency() {
+        assert_eq!(parse_args(&[]).probe_concurrency, 8);
+        assert_eq!(parse_args(&["--probe-concurrency=1"]).probe_concurrency, 1);
+        assert!(Args::try_parse_from(["linkerd-await", "--probe-concurrency=0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_also_shutdown() {
+        let args = parse_args(&[
+            "--shutdown",
+            "--also-shutdown=http://127.0.0.1:15020/quitquitquit",
+            "--also-shutdown",
+            "http://127.0.0.1:9102/quit",
+            "--",
+            "true",
+        ]);
+        assert_eq!(
+            args.also_shutdown,
+            [
+                http::Uri::from_static("http://127.0.0.1:15020/quitquitquit"),
+                http::Uri::from_static("http://127.0.0.1:9102/quit"),
+            ]
+        );
+        assert!(args.validate(false).is_empty());
+
+        let args = parse_args(&["--also-shutdown=http://127.0.0.1:9102/quit"]);
+        assert_eq!(
+            args.validate(false),
+            ["--also-shutdown has no effect without --shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_mesh() {
+        assert_eq!(parse_args(&[]).mesh, Mesh::Linkerd);
+        assert_eq!(parse_args(&["--mesh=istio"]).mesh, Mesh::Istio);
+        assert_eq!(parse_args(&["--mesh=envoy"]).mesh, Mesh::Envoy);
+        assert_eq!(parse_args(&["--mesh=consul"]).mesh, Mesh::Consul);
+        assert_eq!(parse_args(&["--mesh=dapr"]).mesh, Mesh::Dapr);
+        assert_eq!(parse_args(&["--mesh=app-mesh"]).mesh, Mesh::AppMeshEnvoy);
+        assert_eq!(
+            Mesh::AppMeshEnvoy.profile().drain_path,
+            Some("/drain_listeners?graceful")
+        );
+        assert_eq!(Mesh::Envoy.profile().drain_path, None);
+        assert_eq!(
+            parse_args(&["--shutdown", "--drain-time=30s", "--", "true"]).validate(false),
+            ["--drain-time has no effect unless --mesh is app-mesh"]
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "--mesh=kuma"]).is_err());
+        assert_eq!(
+            parse_args(&["--mesh=istio", "--verify-proxy"]).validate(false),
+            ["--verify-proxy has no effect unless --mesh is linkerd"]
+        );
+        assert!(parse_args(&["--verify-proxy"]).validate(false).is_empty());
+    }
 
-    #[clap(
-        short = 'b',
-        long = "backoff",
-        default_value = "1s",
-        value_parser = parse_duration,
-        help = "Time to wait after a failed readiness check",
-    )]
-    backoff: time::Duration,
+    #[test]
+    fn test_parse_min_proxy_version() {
+        assert_eq!(
+            parse_args(&["--min-proxy-version=2.14.10"]).min_proxy_version,
+            Some("2.14.10".parse().unwrap())
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "--min-proxy-version=latest"]).is_err());
+    }
 
-    #[clap(
-        short = 'S',
-        long = "shutdown",
-        help = "Forks the program and triggers proxy shutdown on completion",
-        requires("CMD")
-    )]
-    shutdown: bool,
+    #[test]
+    fn test_parse_metric() {
+        let args = parse_args(&[
+            "--metric",
+            "identity_cert_refresh_count > 0",
+            "--metric=control_response_total{classification=\"success\"} >= 1",
+        ]);
+        assert_eq!(
+            args.metric
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "identity_cert_refresh_count > 0",
+                "control_response_total{classification=\"success\"} >= 1"
+            ]
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "--metric=up"]).is_err());
+    }
 
-    #[clap(
-        short = 'v',
-        long = "verbose",
-        help = "Causes linkerd-await to print an error message when disabled",
-        env = "LINKERD_AWAIT_VERBOSE"
-    )]
-    verbose: bool,
+    #[test]
+    fn test_verify_listeners() {
+        let args = parse_args(&["--verify-listeners", "--outbound-port=4240"]);
+        assert!(args.verify_listeners);
+        assert_eq!(args.inbound_port, None);
+        assert_eq!(args.outbound_port, Some(4240));
+        assert!(args.validate(false).is_empty());
+        assert_eq!(
+            parse_args(&["--inbound-port=4243"]).validate(false),
+            ["--inbound-port has no effect without --verify-listeners"]
+        );
+        assert!(
+            parse_args(&["--outbound-port=4240", "--pre-resolve=web:80"])
+                .validate(false)
+                .is_empty()
+        );
+        assert_eq!(
+            parse_args(&["--mesh=istio", "--check-live"]).validate(false),
+            ["--check-live has no effect unless --mesh is linkerd"]
+        );
+        assert_eq!(
+            parse_args(&["--mesh=istio", "--require-identity"]).validate(false),
+            ["--require-identity has no effect unless --mesh is linkerd"]
+        );
+        assert_eq!(
+            parse_args(&["--mesh=istio", "--verify-listeners"]).validate(false),
+            ["--verify-listeners has no effect unless --mesh is linkerd"]
+        );
+    }
 
-    #[clap(
-        short = 't',
-        long = "timeout",
-        value_parser = parse_duration,
-        help = "Causes linked-await to fail when the timeout elapses before the proxy becomes ready"
-    )]
-    timeout: Option<time::Duration>,
+    #[test]
+    fn test_parse_ready_url() {
+        let uri = parse_ready_url("http://127.0.0.1:4191/ready?verbose").unwrap();
+        assert_eq!(uri.host(), Some("127.0.0.1"));
+        assert_eq!(uri.port_u16(), Some(4191));
+        assert_eq!(
+            with_path(&uri, "/shutdown").unwrap(),
+            "http://127.0.0.1:4191/shutdown"
+        );
+        assert_eq!(
+            with_port(&with_path(&uri, "/quitquitquit").unwrap(), 15020).unwrap(),
+            "http://127.0.0.1:15020/quitquitquit"
+        );
+        assert_eq!(
+            with_port(&parse_ready_url("http://[::1]:15021/").unwrap(), 15020).unwrap(),
+            "http://[::1]:15020/"
+        );
+        assert!(parse_ready_url("https://linkerd-proxy.example/ready").is_ok());
+        assert_eq!(parse_ready_url("/ready"), Err(InvalidReadyUrl));
+        assert_eq!(parse_ready_url("127.0.0.1:4191"), Err(InvalidReadyUrl));
+        assert_eq!(
+            parse_ready_url("ftp://127.0.0.1/ready"),
+            Err(InvalidReadyUrl)
+        );
 
-    #[clap(
-        long,
-        help = "Controls whether a readiness timeout failure prevents CMD from running",
-        default_value("true"),
-        default_missing_value("true"),
-        num_args(0..=1),
-        require_equals(true),
-        action = clap::ArgAction::Set,
-        requires("CMD")
-    )]
-    timeout_fatal: bool,
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--ready-url=http://127.0.0.1:4191/ready",
+            "--port=4192",
+        ])
+        .is_err());
+    }
 
-    #[clap(name = "CMD", help = "The command to run after linkerd is ready")]
-    cmd: Option<String>,
+    #[test]
+    fn test_parse_ready_path() {
+        assert_eq!(parse_ready_path("/live"), Ok("/live".to_string()));
+        assert_eq!(
+            parse_ready_path("/healthz/ready?verbose"),
+            Ok("/healthz/ready?verbose".to_string())
+        );
+        assert_eq!(parse_ready_path("ready"), Err(InvalidReadyPath));
+        assert_eq!(parse_ready_path("/re ady"), Err(InvalidReadyPath));
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--ready-path=/live",
+            "--ready-url=http://127.0.0.1:4191/ready",
+        ])
+        .is_err());
+    }
 
-    #[clap(name = "ARGS", help = "Arguments to pass to CMD if specified")]
-    args: Vec<String>,
-}
+    #[test]
+    fn test_parse_expect_status() {
+        let args = parse_args(&["--expect-status=200-299,503"]);
+        assert_eq!(args.expect_status, Some("200-299,503".parse().unwrap()));
+        assert!(Args::try_parse_from(["linkerd-await", "--expect-status=2xx"]).is_err());
+    }
 
-// From https://man.netbsd.org/sysexits.3
-const EX_OSERR: i32 = 71;
-const EX_UNAVAILABLE: i32 = 69;
+    #[test]
+    fn test_parse_expect_body() {
+        let args = parse_args(&["--expect-body", "ready"]);
+        assert_eq!(args.expect_body.as_deref(), Some("ready"));
+        assert_eq!(parse_args(&[]).expect_body, None);
+    }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() {
-    let Args {
-        port,
-        backoff,
-        shutdown,
-        verbose,
-        timeout,
-        timeout_fatal,
-        cmd,
-        args,
-    } = Args::parse();
+    #[test]
+    fn test_parse_namespace() {
+        assert_eq!(parse_namespace("my-ns"), Ok("my-ns".to_string()));
+        assert_eq!(parse_namespace(""), Err(InvalidNamespace));
+        assert_eq!(parse_namespace("My-NS"), Err(InvalidNamespace));
+        assert_eq!(parse_namespace("a/b"), Err(InvalidNamespace));
 
-    let authority = http::uri::Authority::from_str(&format!("localhost:{}", port))
-        .expect("HTTP authority must be valid");
+        assert!(Args::try_parse_from(["linkerd-await", "-n", "ns"]).is_err());
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "-n",
+            "ns",
+            "--await-k8s-object=secret/my-cert",
+        ])
+        .is_ok());
+    }
 
-    // If linkerd is not explicitly disabled, wait until the proxy is ready
-    // before running the application.
-    match linkerd_disabled_reason() {
-        Some(reason) => {
-            if verbose {
-                eprintln!("Linkerd readiness check skipped: {}", reason);
+    #[test]
+    fn test_parse_healthcheck() {
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "healthcheck",
+            "--app-url=http://localhost:8080/healthz",
+            "-t",
+            "500ms",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::Healthcheck(hc)) => {
+                assert_eq!(
+                    hc.app_url,
+                    Some(http::Uri::from_static("http://localhost:8080/healthz"))
+                );
+                assert_eq!(hc.timeout, time::Duration::from_millis(500));
             }
+            command => panic!("unexpected command: {:?}", command),
         }
-        None => {
-            let await_timeout = async move {
-                if let Some(timeout) = timeout {
-                    if !timeout.is_zero() {
-                        tokio::time::sleep(timeout).await;
-                        return timeout;
-                    }
-                }
-                futures::future::pending().await
-            };
-            tokio::select! {
-                () = await_ready(authority.clone(), backoff) => {},
-                timeout = await_timeout => {
-                    eprintln!(
-                        "linkerd-proxy failed to become ready within {:?} timeout",
-                        timeout
-                    );
 
-                    // Continue running the command when timeouts are configured
-                    // to be non-fatal.
-                    if timeout_fatal {
-                        std::process::exit(EX_UNAVAILABLE)
-                    }
+        // A command named like a subcommand can still be run after `--`.
+        let args = Args::try_parse_from(["linkerd-await", "--", "healthcheck"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.cmd.as_deref(), Some("healthcheck"));
+    }
 
-                }
+    #[test]
+    fn test_parse_check_command() {
+        let args = Args::try_parse_from(["linkerd-await", "check"]).unwrap();
+        match args.command {
+            Some(Command::Check(check)) => {
+                assert_eq!(check.timeout, time::Duration::from_secs(1));
             }
-            if shutdown {
-                let cmd = cmd.expect("Command must be specified with --shutdown");
+            command => panic!("unexpected command: {:?}", command),
+        }
 
-                // If shutdown is configured, fork the process and proxy
-                // SIGTERM.
-                let ex = fork_with_sigterm(cmd, args).await;
+        // The admin server's flags follow the subcommand, as do their
+        // environment variables.
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "check",
+            "-p",
+            "4192",
+            "--ready-path",
+            "/live",
+            "--mesh",
+            "istio",
+            "-t",
+            "5s",
+        ])
+        .unwrap();
+        assert_eq!(args.port, Some(4192));
+        assert_eq!(args.ready_path.as_deref(), Some("/live"));
+        assert_eq!(args.mesh, Mesh::Istio);
+        match args.command {
+            Some(Command::Check(check)) => {
+                assert_eq!(check.timeout, time::Duration::from_secs(5));
+            }
+            command => panic!("unexpected command: {:?}", command),
+        }
+    }
 
-                // Once the process completes, issue a shutdown request to the
-                // proxy.
-                send_shutdown(authority).await;
+    #[test]
+    fn test_parse_shutdown_command() {
+        let args = Args::try_parse_from(["linkerd-await", "shutdown"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Shutdown(_))));
+        assert_eq!(args.shutdown_path, None);
 
-                // Try to exit with the process's original exit code
-                if let Ok(status) = ex {
-                    if let Some(code) = status.code() {
-                        std::process::exit(code);
-                    }
-                }
+        // The shutdown flags follow the subcommand, as the admin server's do.
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "shutdown",
+            "--port",
+            "4192",
+            "--shutdown-path",
+            "/quitquitquit",
+            "--shutdown-timeout",
+            "30s",
+            "--verify-shutdown",
+            "--backoff",
+            "100ms",
+        ])
+        .unwrap();
+        assert!(matches!(args.command, Some(Command::Shutdown(_))));
+        assert_eq!(args.port, Some(4192));
+        assert_eq!(args.shutdown_path.as_deref(), Some("/quitquitquit"));
+        assert_eq!(args.shutdown_timeout, Some(time::Duration::from_secs(30)));
+        assert_eq!(args.verify_shutdown, Some(time::Duration::from_secs(10)));
+        assert_eq!(args.backoff, time::Duration::from_millis(100));
+    }
 
-                // If we didn't get an exit code from the forked program, fail
-                // with an OS error.
-                std::process::exit(EX_OSERR);
+    #[test]
+    fn test_wait_for() {
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "wait-for",
+            "db:5432",
+            "http://localhost:8080/healthz",
+            "-t",
+            "30",
+            "--",
+            "./app",
+            "--port=8080",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::WaitFor(wait_for)) => {
+                assert_eq!(
+                    wait_for.targets,
+                    [
+                        Probe::Tcp("db:5432".to_string()),
+                        Probe::Sidecar(http::Uri::from_static("http://localhost:8080/healthz")),
+                    ]
+                );
+                assert_eq!(wait_for.timeout, time::Duration::from_secs(30));
+                assert!(!wait_for.strict);
+                assert_eq!(wait_for.cmd, ["./app", "--port=8080"]);
             }
+            command => panic!("unexpected command: {:?}", command),
         }
+
+        assert_eq!(
+            parse_wait_for_target("tcp://db:5432"),
+            Ok(Probe::Tcp("db:5432".to_string()))
+        );
+        assert_eq!(
+            parse_wait_for_target("unix:///var/run/app.sock"),
+            Ok(Probe::UnixSocket(PathBuf::from("/var/run/app.sock")))
+        );
+        assert_eq!(
+            parse_wait_for_target("file:///tmp/ready"),
+            Ok(Probe::File(PathBuf::from("/tmp/ready")))
+        );
+        assert_eq!(parse_wait_for_target("db"), Err(InvalidWaitForTarget));
+        assert_eq!(
+            parse_wait_for_target("ftp://db:21"),
+            Err(InvalidWaitForTarget)
+        );
+        assert_eq!(
+            parse_wait_for_timeout("1m"),
+            Ok(time::Duration::from_secs(60))
+        );
+        assert!(Args::try_parse_from(["linkerd-await", "wait-for"]).is_err());
     }
 
-    if let Some(cmd) = cmd {
-        // If Linkerd shutdown is not configured, exec the process directly so
-        // that the we don't have to bother with signal proxying, etc.
-        exec(cmd, args);
+    #[tokio::test]
+    async fn test_wait_for_timeout() {
+        let wait_for = WaitFor {
+            targets: vec![Probe::File(PathBuf::from("/nonexistent/linkerd-await"))],
+            timeout: time::Duration::from_millis(10),
+            strict: false,
+            quiet: true,
+            cmd: vec![],
+        };
+        assert_eq!(wait_for.run().await.unwrap(), EX_UNAVAILABLE);
     }
-}
 
-fn linkerd_disabled_reason() -> Option<String> {
-    std::env::var("LINKERD_AWAIT_DISABLED")
-        .ok()
-        .filter(|v| !v.is_empty())
-        .or_else(|| {
-            std::env::var("LINKERD_DISABLED")
-                .ok()
-                .filter(|v| !v.is_empty())
-        })
-}
+    #[test]
+    fn test_k8s_snippet() {
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "k8s-snippet",
+            "-c",
+            "app",
+            "--shutdown",
+            "--timeout",
+            "120s",
+            "--",
+            "./app",
+            "--name=\"x\"",
+        ])
+        .unwrap();
+        let mut snippet = match args.command {
+            Some(Command::K8sSnippet(snippet)) => snippet,
+            command => panic!("unexpected command: {:?}", command),
+        };
+        assert_eq!(
+            snippet.render(),
+            r#"spec:
+  template:
+    spec:
+      containers:
+      - name: "app"
+        command: ["/linkerd-await"]
+        args: ["--shutdown", "--timeout=2m", "--", "./app", "--name=\"x\""]"#
+        );
 
-/// Execs the process.
-fn exec(cmd: String, args: Vec<String>) {
-    use std::{
-        os::unix::process::CommandExt,
-        process::{self, Command},
-    };
+        snippet.output = SnippetFormat::Json;
+        snippet.shutdown = false;
+        snippet.timeout = Some(time::Duration::from_millis(1500));
+        snippet.cmd.clear();
+        assert_eq!(
+            snippet.render(),
+            r#"{"spec": {"template": {"spec": {"containers": [{"name": "app", "command": ["/linkerd-await"], "args": ["--timeout=1500ms", "--"]}]}}}}"#
+        );
+    }
 
-    // Execute the command (and never return). If the command could not be
-    // executed, just exit with an OS error.
-    let err = Command::new(&cmd).args(args).exec();
-    eprintln!("Failed to exec child program: {}: {}", cmd, err);
-    process::exit(EX_OSERR);
-}
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("0600"), Ok(0o600));
+        assert_eq!(parse_mode("440"), Ok(0o440));
+        assert_eq!(parse_mode("0800"), Err(InvalidMode));
+        assert_eq!(parse_mode("10000"), Err(InvalidMode));
+        assert_eq!(parse_mode(""), Err(InvalidMode));
+    }
 
-/// Forks the specified process, proxying SIGTERM.
-async fn fork_with_sigterm(cmd: String, args: Vec<String>) -> io::Result<ExitStatus> {
-    use nix::{
-        sys::signal::{kill, Signal::SIGTERM},
-        unistd::Pid,
-    };
-    use std::os::unix::process::ExitStatusExt;
-    use tokio::{
-        process::Command,
-        signal::unix::{signal, SignalKind},
-    };
+    #[test]
+    fn test_parse_marker() {
+        assert_eq!(parse_marker("app-b"), Ok("app-b".to_string()));
+        assert_eq!(parse_marker(""), Err(InvalidMarker));
+        assert_eq!(parse_marker(".."), Err(InvalidMarker));
+        assert_eq!(parse_marker("a/b"), Err(InvalidMarker));
 
-    let mut child = match Command::new(&cmd).args(args).spawn() {
-        Ok(child) => child,
-        Err(e) => {
-            eprintln!("Failed to fork child program: {}: {}", cmd, e);
-            return Ok(ExitStatus::from_raw(EX_OSERR));
-        }
-    };
+        let args = Args::try_parse_from([
+            "linkerd-await",
+            "--barrier=/shared/startup",
+            "--barrier-name=app-a",
+            "--barrier-wait-for=app-b,app-c",
+        ])
+        .unwrap();
+        assert_eq!(args.barrier_wait_for, vec!["app-b", "app-c"]);
+        assert!(args.validate(false).is_empty());
+        assert!(Args::try_parse_from(["linkerd-await", "--barrier-name=app-a"]).is_err());
+    }
 
-    // If the process is running, wait until we receive a SIGTERM, which kubelet
-    // uses to initiate graceful shutdown.
-    let mut sigterm = signal(SignalKind::terminate()).expect("Failed to register SIGTERM handler");
-
-    // Wait for the process to exit on its own or, if a SIGTERM is received,
-    // proxy the signal so it begins shutdown.
-    tokio::select! {
-        ex = child.wait() => ex,
-        _ = sigterm.recv() => {
-            if let Some(pid) = child.id() {
-                // If the child hasn't already completed, send a SIGTERM.
-                if let Err(e) = kill(Pid::from_raw(pid.try_into().expect("Invalid PID")), SIGTERM) {
-                    eprintln!("Failed to forward SIGTERM to child process: {}", e);
-                }
-            }
-            // Wait to get the child's exit code.
-            child.wait().await
-        }
+    #[test]
+    fn test_parse_interface() {
+        assert_eq!(parse_interface("eth0"), Ok("eth0".to_string()));
+        assert_eq!(parse_interface("net1.100"), Ok("net1.100".to_string()));
+        assert_eq!(parse_interface(""), Err(InvalidInterface));
+        assert_eq!(parse_interface("eth0:1"), Err(InvalidInterface));
+        assert_eq!(parse_interface("a-very-long-name0"), Err(InvalidInterface));
+
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--wait-for-interface=eth0",
+            "--wait-for-route=default",
+            "--wait-for-route=10.0.0.1",
+        ])
+        .is_ok());
+        assert!(Args::try_parse_from(["linkerd-await", "--wait-for-route=10.0.0.0/8"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lease_name() {
+        assert_eq!(
+            parse_lease_name("my-lease.v1"),
+            Ok("my-lease.v1".to_string())
+        );
+        assert_eq!(parse_lease_name(""), Err(InvalidLeaseName));
+        assert_eq!(parse_lease_name("My_Lease"), Err(InvalidLeaseName));
+
+        assert!(Args::try_parse_from(["linkerd-await", "--acquire"]).is_err());
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--await-lease=migrations",
+            "--acquire",
+            "--background",
+        ])
+        .is_err());
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--await-lease=migrations",
+            "--acquire",
+            "--lease-duration=30s",
+            "-n",
+            "ns",
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_parse_scheme() {
+        assert_eq!(parse_scheme("http"), Ok(http::uri::Scheme::HTTP));
+        assert_eq!(parse_scheme("https"), Ok(http::uri::Scheme::HTTPS));
+        assert_eq!(parse_scheme("HTTPS"), Err(InvalidScheme));
+        assert_eq!(parse_scheme("ftp"), Err(InvalidScheme));
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--scheme=https",
+            "--ready-url=https://127.0.0.1:4191/ready"
+        ])
+        .is_err());
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--ca-file=/ca.pem",
+            "--insecure-skip-verify"
+        ])
+        .is_err());
+        assert!(Args::try_parse_from(["linkerd-await", "--tls-cert=/tls.crt"]).is_err());
+        assert!(Args::try_parse_from(["linkerd-await", "--tls-key=/tls.key"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_http2_with_https() {
+        assert_eq!(
+            parse_args(&["--scheme=https", "--http2"]).validate(false),
+            vec!["--http2 has no effect with an https admin server; HTTP/1.1 is negotiated"]
+        );
+        assert!(parse_args(&["--http2"]).validate(false).is_empty());
+    }
+
+    #[test]
+    fn test_validate_tls_without_https() {
+        let args = parse_args(&["--insecure-skip-verify"]);
+        assert_eq!(
+            args.validate(false),
+            vec![
+                "--insecure-skip-verify has no effect unless the admin server is https (see --scheme)"
+            ]
+        );
+        assert!(parse_args(&["--scheme=https", "--ca-file=/ca.pem"])
+            .validate(false)
+            .is_empty());
+        assert!(parse_args(&[
+            "--ready-url=https://proxy:4191/ready",
+            "--insecure-skip-verify"
+        ])
+        .validate(false)
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("Authorization: Bearer a:b"),
+            Ok((
+                http::header::AUTHORIZATION,
+                http::HeaderValue::from_static("Bearer a:b")
+            ))
+        );
+        assert_eq!(
+            parse_header("x-route:admin"),
+            Ok((
+                http::HeaderName::from_static("x-route"),
+                http::HeaderValue::from_static("admin")
+            ))
+        );
+        assert_eq!(parse_header("x-route"), Err(InvalidHeader));
+        assert_eq!(parse_header(": admin"), Err(InvalidHeader));
+        assert_eq!(parse_header("x route: admin"), Err(InvalidHeader));
+        assert_eq!(parse_header("x-route: a\nb"), Err(InvalidHeader));
+    }
+
+    #[test]
+    fn test_parse_admin_addr() {
+        assert_eq!(
+            parse_admin_addr("127.0.0.1:4191"),
+            Ok(("127.0.0.1".to_string(), 4191))
+        );
+        assert_eq!(
+            parse_admin_addr("linkerd-proxy:4191"),
+            Ok(("linkerd-proxy".to_string(), 4191))
+        );
+        assert_eq!(
+            parse_admin_addr("[::1]:4191"),
+            Ok(("::1".to_string(), 4191))
+        );
+        assert_eq!(parse_admin_addr("::1:4191"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_addr("[::1]"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_addr("[proxy]:4191"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_addr("localhost"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_addr(":4191"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_addr("localhost:http"), Err(InvalidAdminAddr));
+        assert!(
+            Args::try_parse_from(["linkerd-await", "--admin-addr=[::1]:4191", "--port=4192"])
+                .is_err()
+        );
     }
-}
 
-async fn await_ready(auth: http::uri::Authority, backoff: time::Duration) {
-    const TIMEOUT: time::Duration = time::Duration::from_secs(5);
+    #[test]
+    fn test_load_args_config() {
+        let dir = std::env::temp_dir().join(format!("linkerd-await-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let argv = |path: &std::path::Path, flags: &[&str]| {
+            ["linkerd-await", "--config"]
+                .into_iter()
+                .map(OsString::from)
+                .chain(Some(path.as_os_str().to_owned()))
+                .chain(flags.iter().map(OsString::from))
+                .collect::<Vec<_>>()
+        };
 
-    let uri = hyper::Uri::builder()
-        .scheme(http::uri::Scheme::HTTP)
-        .authority(auth)
-        .path_and_query("/ready")
-        .build()
+        let toml = dir.join("config.toml");
+        std::fs::write(
+            &toml,
+            "timeout = \"2m\"\nbackoff = \"3s\"\nheader = [\"x-a: 1\", \"x-b: 2\"]\nshutdown = true\nverbose = false\n",
+        )
         .unwrap();
+        let args = load_args(argv(&toml, &["--backoff=1s", "--", "true"]), None).unwrap();
+        assert_eq!(args.timeout, Some(time::Duration::from_secs(120)));
+        assert_eq!(args.backoff, time::Duration::from_secs(1));
+        assert_eq!(args.header.len(), 2);
+        assert!(args.shutdown);
+        assert!(!args.verbose);
+        assert_eq!(args.cmd.as_deref(), Some("true"));
 
-    let client = client::Client::builder(TokioExecutor::new()).build_http::<Empty<Bytes>>();
-    loop {
-        match time::timeout(TIMEOUT, client.get(uri.clone())).await {
-            Ok(Ok(ref rsp)) if rsp.status().is_success() => return,
-            _ => time::sleep(backoff).await,
+        let yaml = dir.join("config.yaml");
+        std::fs::write(&yaml, "port: 4192\nready_path: /live\n").unwrap();
+        let args = load_args(argv(&yaml, &[]), None).unwrap();
+        assert_eq!(args.port, Some(4192));
+        assert_eq!(args.ready_path.as_deref(), Some("/live"));
+
+        for (contents, error) in [
+            ("bogus: 1", "line 1: unknown setting \"bogus\""),
+            ("shutdown: yes", "line 1: shutdown must be true or false"),
+            ("timeout: [1s, 2s]", "line 1: timeout takes a single value"),
+        ] {
+            std::fs::write(&yaml, contents).unwrap();
+            match load_args(argv(&yaml, &[]), None) {
+                Err(Error::ConfigFile(path, e)) => {
+                    assert_eq!(path, yaml);
+                    assert_eq!(e.to_string(), error);
+                }
+                result => panic!("unexpected result: {:?}", result.map(|_| ())),
+            }
         }
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
-}
 
-async fn send_shutdown(auth: http::uri::Authority) {
-    let uri = hyper::Uri::builder()
-        .scheme(http::uri::Scheme::HTTP)
-        .authority(auth)
-        .path_and_query("/shutdown")
-        .build()
+    #[test]
+    fn test_split_opts() {
+        assert_eq!(split_opts(""), Ok(vec![]));
+        assert_eq!(
+            split_opts("  --timeout=2m\t-p 4192 "),
+            Ok(vec!["--timeout=2m".into(), "-p".into(), "4192".into()])
+        );
+        assert_eq!(
+            split_opts(r#"--header 'x-a: 1' --expect-body="it's \"ready\"" --header=x-b:\ 2"#),
+            Ok(vec![
+                "--header".into(),
+                "x-a: 1".into(),
+                r#"--expect-body=it's "ready""#.into(),
+                "--header=x-b: 2".into(),
+            ])
+        );
+        assert_eq!(split_opts("''"), Ok(vec!["".into()]));
+        assert_eq!(split_opts("--header 'x-a: 1"), Err(InvalidOpts));
+        assert_eq!(split_opts("--expect-body \"ready"), Err(InvalidOpts));
+        assert_eq!(split_opts("--timeout\\"), Err(InvalidOpts));
+    }
+
+    #[test]
+    fn test_load_args_opts() {
+        let argv = |flags: &[&str]| {
+            std::iter::once("linkerd-await")
+                .chain(flags.iter().copied())
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        };
+
+        let args = load_args(
+            argv(&["--backoff=1s", "--", "true"]),
+            Some("--timeout 2m --backoff=3s -p 4192 -S --timeout-fatal=false --header 'x-a: 1'"),
+        )
         .unwrap();
+        assert_eq!(args.timeout, Some(time::Duration::from_secs(120)));
+        assert_eq!(args.backoff, time::Duration::from_secs(1));
+        assert_eq!(args.port, Some(4192));
+        assert!(args.shutdown);
+        assert_eq!(args.timeout_fatal, Some(false));
+        assert_eq!(args.header.len(), 1);
+        assert_eq!(args.cmd.as_deref(), Some("true"));
 
-    let req = http::Request::builder()
-        .method(http::Method::POST)
-        .uri(uri)
-        .body(Empty::<Bytes>::default())
-        .expect("shutdown request must be valid");
+        let args = load_args(argv(&["-p", "4193"]), Some("-p4192")).unwrap();
+        assert_eq!(args.port, Some(4193));
 
-    let _ = client::Client::builder(TokioExecutor::new())
-        .build_http()
-        .request(req)
-        .await;
-}
+        for (opts, error) in [
+            ("--timeout 2m true", "\"true\" is not a flag"),
+            ("--bogus", "unknown flag \"--bogus\""),
+            ("--timeout '2m", "unterminated quote"),
+        ] {
+            match load_args(argv(&[]), Some(opts)) {
+                Err(Error::InvalidConfig(e)) => assert!(e.contains(error), "{}", e),
+                result => panic!("unexpected result: {:?}", result.map(|_| ())),
+            }
+        }
+    }
 
-fn parse_duration(s: &str) -> Result<time::Duration, InvalidDuration> {
-    use tokio::time::Duration;
-    let s = s.trim();
-    let milliseconds = match s.rfind(|c: char| c.is_ascii_digit()) {
-        None => return Err(InvalidDuration),
-        Some(index) => {
-            let (magnitude, unit) = s.split_at(index + 1);
-            let magnitude = u64::from_str(magnitude).map_err(|_| InvalidDuration)?;
-            let multiplier = match unit {
-                "" if magnitude == 0 => 0,
-                "ms" => 1,
-                "s" => 1000,
-                "m" => 1000 * 60,
-                "h" => 1000 * 60 * 60,
-                "d" => 1000 * 60 * 60 * 24,
-                _ => return Err(InvalidDuration),
+    #[test]
+    fn test_env_bindings() {
+        let cmd = Args::command();
+        for arg in cmd.get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
             };
-            magnitude.checked_mul(multiplier).ok_or(InvalidDuration)?
+            if long == "help" || long == "version" {
+                continue;
+            }
+            let env = format!("LINKERD_AWAIT_{}", long.to_uppercase().replace('-', "_"));
+            assert_eq!(
+                arg.get_env().and_then(|e| e.to_str()),
+                Some(env.as_str()),
+                "--{} must be bound to {}",
+                long,
+                env
+            );
         }
-    };
-    Ok(Duration::from_millis(milliseconds))
-}
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-struct InvalidDuration;
-
-impl fmt::Display for InvalidDuration {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "invalid duration")
     }
-}
 
-impl error::Error for InvalidDuration {}
+    #[test]
+    fn test_parse_admin_listen_addr() {
+        assert_eq!(
+            parse_admin_listen_addr("0.0.0.0:4191"),
+            Ok(("localhost".to_string(), 4191))
+        );
+        assert_eq!(
+            parse_admin_listen_addr("[::]:14191"),
+            Ok(("localhost".to_string(), 14191))
+        );
+        assert_eq!(
+            parse_admin_listen_addr("127.0.0.1:4192"),
+            Ok(("127.0.0.1".to_string(), 4192))
+        );
+        assert_eq!(
+            parse_admin_listen_addr("[::1]:4191"),
+            Ok(("::1".to_string(), 4191))
+        );
+        assert_eq!(
+            parse_admin_listen_addr("4193"),
+            Ok(("localhost".to_string(), 4193))
+        );
+        assert_eq!(parse_admin_listen_addr("0.0.0.0"), Err(InvalidAdminAddr));
+        assert_eq!(parse_admin_listen_addr(":::4191"), Err(InvalidAdminAddr));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_parse_admin_uds() {
+        let args = parse_args(&["--admin-uds=/var/run/linkerd/admin.sock"]);
+        assert_eq!(
+            args.admin_uds,
+            Some(PathBuf::from("/var/run/linkerd/admin.sock"))
+        );
+        assert!(Args::try_parse_from([
+            "linkerd-await",
+            "--admin-uds=/admin.sock",
+            "--scheme=https"
+        ])
+        .is_err());
+    }
 
     #[test]
-    fn test_parse_duration_invalid() {
-        assert_eq!(parse_duration(""), Err(InvalidDuration));
-        assert_eq!(parse_duration("  "), Err(InvalidDuration));
-        assert_eq!(parse_duration("\t\n"), Err(InvalidDuration));
-        assert_eq!(parse_duration("x"), Err(InvalidDuration));
-        assert_eq!(parse_duration("1"), Err(InvalidDuration));
-        assert_eq!(parse_duration("0x"), Err(InvalidDuration));
-        assert_eq!(parse_duration("123x"), Err(InvalidDuration));
-        assert_eq!(parse_duration("  123x  "), Err(InvalidDuration));
+    fn test_parse_resolve() {
+        assert_eq!(
+            parse_resolve("proxy.example=10.0.0.1"),
+            Ok(("proxy.example".to_string(), [10, 0, 0, 1].into()))
+        );
         assert_eq!(
-            parse_duration(&format!("{}s", u64::MAX)),
-            Err(InvalidDuration),
+            parse_resolve("proxy.example=fd00::1"),
+            Ok(("proxy.example".to_string(), "fd00::1".parse().unwrap()))
         );
+        assert_eq!(parse_resolve("proxy.example"), Err(InvalidResolve));
+        assert_eq!(parse_resolve("=10.0.0.1"), Err(InvalidResolve));
+        assert_eq!(parse_resolve("proxy.example=proxy"), Err(InvalidResolve));
     }
 
     #[test]
-    fn test_parse_duration_valid() {
-        use tokio::time::Duration;
-        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
-        assert_eq!(parse_duration("0s"), Ok(Duration::from_secs(0)));
-        assert_eq!(parse_duration("1ms"), Ok(Duration::from_millis(1)));
-        assert_eq!(parse_duration("1s"), Ok(Duration::from_secs(1)));
-        assert_eq!(parse_duration(" \n12s  \t"), Ok(Duration::from_secs(12)));
-        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
-        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(10 * 60)));
-        assert_eq!(parse_duration("10h"), Ok(Duration::from_secs(10 * 60 * 60)));
+    fn test_parse_dns_server() {
         assert_eq!(
-            parse_duration("10d"),
-            Ok(Duration::from_secs(10 * 60 * 60 * 24))
+            parse_dns_server("10.96.0.10"),
+            Ok("10.96.0.10:53".parse().unwrap())
         );
         assert_eq!(
-            parse_duration(&format!("{}ms", u64::MAX)),
-            Ok(Duration::from_millis(u64::MAX)),
+            parse_dns_server("10.96.0.10:5353"),
+            Ok("10.96.0.10:5353".parse().unwrap())
         );
+        assert_eq!(
+            parse_dns_server("fd00::a"),
+            Ok("[fd00::a]:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_dns_server("[fd00::a]"),
+            Ok("[fd00::a]:53".parse().unwrap())
+        );
+        assert_eq!(parse_dns_server("kube-dns"), Err(InvalidDnsServer));
+    }
+
+    #[tokio::test]
+    async fn test_prober_retry_attempt_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let _ = io.read(&mut [0; 1024]).await.unwrap();
+            time::sleep(time::Duration::from_millis(5_200)).await;
+            io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        // A target with its own timeout may take longer to answer than others.
+        let prober = Prober {
+            connector: proxy::Connector::new(false),
+            resolver: Resolver::default(),
+            exec_timeout: time::Duration::from_secs(1),
+            concurrency: 1,
+        };
+        let probe = parse_require(&format!("retry(http={}, timeout=10s)", uri)).unwrap();
+        let retry = Retry {
+            max_attempts: Some(1),
+            ..Retry::new()
+        };
+        await_ready(&prober.check(probe), &retry).await.unwrap();
     }
 }
@@ -0,0 +1,273 @@
+//! Platform-specific process management: replacing linkerd-await with CMD,
+//! relaying requests to terminate to CMD, and signaling it.
+//!
+//! The rest of linkerd-await uses the platform-neutral interface in this
+//! module. On Unix, termination requests are SIGTERM and notifications are
+//! arbitrary signals. Windows has neither signals nor process groups, so
+//! there these operations fail as unsupported.
+
+use crate::Error;
+use std::{fmt, io};
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+use self::unix as imp;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as imp;
+
+/// A signal, named as in POSIX, e.g. to notify CMD or to be forwarded to it.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Signal {
+    SIGHUP,
+    SIGINT,
+    SIGQUIT,
+    SIGILL,
+    SIGTRAP,
+    SIGABRT,
+    SIGBUS,
+    SIGFPE,
+    SIGKILL,
+    SIGUSR1,
+    SIGSEGV,
+    SIGUSR2,
+    SIGPIPE,
+    SIGALRM,
+    SIGTERM,
+    SIGCHLD,
+    SIGCONT,
+    SIGSTOP,
+    SIGTSTP,
+    SIGTTIN,
+    SIGTTOU,
+    SIGURG,
+    SIGXCPU,
+    SIGXFSZ,
+    SIGVTALRM,
+    SIGPROF,
+    SIGWINCH,
+    SIGIO,
+    SIGSYS,
+}
+
+/// Replaces the current process with the command, returning only if the
+/// command could not be executed.
+pub fn exec(cmd: String, args: Vec<String>) -> Error {
+    imp::exec(cmd, args)
+}
+
+/// Asks the process to terminate gracefully, as a process manager like the
+/// kubelet would.
+pub fn terminate(pid: u32) -> io::Result<()> {
+    imp::signal(pid, imp::TERMINATE)
+}
+
+/// Sends a signal to the process.
+pub fn signal(pid: u32, signal: Signal) -> io::Result<()> {
+    imp::signal(pid, signal)
+}
+
+/// Finds the processes, other than linkerd-await, whose executable has the
+/// given name, e.g. `linkerd2-proxy`.
+///
+/// Processes in other containers are only visible when the pod shares its
+/// process namespace.
+pub fn find(name: &str) -> io::Result<Vec<u32>> {
+    imp::find(name)
+}
+
+/// Relays requests for linkerd-await to terminate to the given process
+/// without an async runtime. `None` stops relaying.
+///
+/// Requests received while no process is set are ignored.
+pub fn relay_termination(pid: Option<u32>) -> io::Result<()> {
+    imp::relay_termination(pid)
+}
+
+/// The controlling terminal of an interactive shell in which linkerd-await
+/// runs in the foreground.
+///
+/// CMD runs in its own process group, which is given the terminal so that
+/// job control (e.g. Ctrl-C and Ctrl-Z) reaches CMD instead of only
+/// linkerd-await. The terminal and its settings are restored to linkerd-await
+/// when this is dropped.
+pub struct Terminal(imp::Terminal);
+
+/// Receives requests for linkerd-await to terminate, i.e. SIGTERM or SIGINT
+/// on Unix.
+#[cfg(feature = "runtime")]
+pub struct Termination(imp::Termination);
+
+/// Receives signals that are forwarded to CMD, other than termination
+/// requests, which are received by [`Termination`].
+#[cfg(feature = "runtime")]
+pub struct Forwarding(imp::Forwarding);
+
+/// Indicates whether the process has stopped, e.g. because Ctrl-Z was
+/// pressed, without waiting. The stop is only reported once.
+pub fn try_wait_stopped(pid: u32) -> io::Result<bool> {
+    imp::wait_stopped(pid, false)
+}
+
+/// Waits for the process to exit or stop, returning true if it stopped. An
+/// exited process is left to be reaped by the caller.
+pub fn wait_stopped(pid: u32) -> io::Result<bool> {
+    imp::wait_stopped(pid, true)
+}
+
+// === impl Signal ===
+
+impl Signal {
+    const ALL: [Self; 29] = [
+        Self::SIGHUP,
+        Self::SIGINT,
+        Self::SIGQUIT,
+        Self::SIGILL,
+        Self::SIGTRAP,
+        Self::SIGABRT,
+        Self::SIGBUS,
+        Self::SIGFPE,
+        Self::SIGKILL,
+        Self::SIGUSR1,
+        Self::SIGSEGV,
+        Self::SIGUSR2,
+        Self::SIGPIPE,
+        Self::SIGALRM,
+        Self::SIGTERM,
+        Self::SIGCHLD,
+        Self::SIGCONT,
+        Self::SIGSTOP,
+        Self::SIGTSTP,
+        Self::SIGTTIN,
+        Self::SIGTTOU,
+        Self::SIGURG,
+        Self::SIGXCPU,
+        Self::SIGXFSZ,
+        Self::SIGVTALRM,
+        Self::SIGPROF,
+        Self::SIGWINCH,
+        Self::SIGIO,
+        Self::SIGSYS,
+    ];
+
+    /// Returns the signal with the given name, e.g. `SIGUSR1`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// Returns the signal's name, e.g. `SIGUSR1`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SIGHUP => "SIGHUP",
+            Self::SIGINT => "SIGINT",
+            Self::SIGQUIT => "SIGQUIT",
+            Self::SIGILL => "SIGILL",
+            Self::SIGTRAP => "SIGTRAP",
+            Self::SIGABRT => "SIGABRT",
+            Self::SIGBUS => "SIGBUS",
+            Self::SIGFPE => "SIGFPE",
+            Self::SIGKILL => "SIGKILL",
+            Self::SIGUSR1 => "SIGUSR1",
+            Self::SIGSEGV => "SIGSEGV",
+            Self::SIGUSR2 => "SIGUSR2",
+            Self::SIGPIPE => "SIGPIPE",
+            Self::SIGALRM => "SIGALRM",
+            Self::SIGTERM => "SIGTERM",
+            Self::SIGCHLD => "SIGCHLD",
+            Self::SIGCONT => "SIGCONT",
+            Self::SIGSTOP => "SIGSTOP",
+            Self::SIGTSTP => "SIGTSTP",
+            Self::SIGTTIN => "SIGTTIN",
+            Self::SIGTTOU => "SIGTTOU",
+            Self::SIGURG => "SIGURG",
+            Self::SIGXCPU => "SIGXCPU",
+            Self::SIGXFSZ => "SIGXFSZ",
+            Self::SIGVTALRM => "SIGVTALRM",
+            Self::SIGPROF => "SIGPROF",
+            Self::SIGWINCH => "SIGWINCH",
+            Self::SIGIO => "SIGIO",
+            Self::SIGSYS => "SIGSYS",
+        }
+    }
+
+    /// Returns the signal's number on this platform, e.g. to report the exit
+    /// status of a process that it terminated.
+    pub fn number(self) -> i32 {
+        imp::number(self)
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// === impl Terminal ===
+
+impl Terminal {
+    /// Returns the terminal if linkerd-await is in the foreground of an
+    /// interactive session.
+    pub fn foreground() -> Option<Self> {
+        imp::Terminal::foreground().map(Self)
+    }
+
+    /// Configures the command to run in its own process group, in the
+    /// foreground.
+    pub fn configure(&self, cmd: &mut std::process::Command) {
+        self.0.configure(cmd)
+    }
+
+    /// Puts the process, which must have been spawned by a command passed to
+    /// [`Terminal::configure`], in the foreground.
+    pub fn attach(&mut self, pid: u32) -> io::Result<()> {
+        self.0.attach(pid)
+    }
+
+    /// Handles the attached process stopping by stopping linkerd-await too,
+    /// so that the shell regains the terminal. Once linkerd-await is
+    /// continued, the process is put back in the foreground and continued.
+    pub fn suspend(&mut self) -> io::Result<()> {
+        self.0.suspend()
+    }
+
+    /// Handles each time the attached process stops with
+    /// [`Terminal::suspend`], returning only on failure.
+    #[cfg(feature = "runtime")]
+    pub async fn relay_stops(&mut self) -> io::Result<()> {
+        self.0.relay_stops().await
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Termination {
+    /// Starts listening for termination requests. Until this is dropped, they
+    /// no longer terminate linkerd-await.
+    pub fn register() -> io::Result<Self> {
+        imp::Termination::register().map(Self)
+    }
+
+    /// Waits for the next termination request, returning the signal that
+    /// requested it.
+    pub async fn recv(&mut self) -> Signal {
+        self.0.recv().await
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Forwarding {
+    /// Starts listening for the signals. Until this is dropped, they no
+    /// longer have their default effect on linkerd-await.
+    pub fn register(signals: &[Signal]) -> io::Result<Self> {
+        imp::Forwarding::register(signals).map(Self)
+    }
+
+    /// Waits for the next signal.
+    pub async fn recv(&mut self) -> Signal {
+        self.0.recv().await
+    }
+}
@@ -0,0 +1,387 @@
+use super::Signal;
+use crate::Error;
+use nix::{
+    sys::{
+        signal::{
+            kill, killpg, pthread_sigmask, sigaction, SaFlags, SigAction, SigHandler, SigSet,
+            SigmaskHow, Signal as NixSignal,
+        },
+        termios::{tcgetattr, tcsetattr, SetArg, Termios},
+    },
+    unistd::{getpgrp, getpid, isatty, tcgetpgrp, tcsetpgrp, Pid},
+};
+use std::{
+    io,
+    os::unix::io::{AsRawFd, RawFd},
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+/// Kubelet uses SIGTERM to initiate graceful shutdown.
+pub const TERMINATE: Signal = Signal::SIGTERM;
+
+/// The process to which SIGTERM is relayed by [`relay_termination`].
+static RELAY_PID: AtomicI32 = AtomicI32::new(0);
+
+pub fn exec(cmd: String, args: Vec<String>) -> Error {
+    use std::{os::unix::process::CommandExt, process::Command};
+
+    let err = Command::new(&cmd).args(args).exec();
+    Error::Exec(cmd, err)
+}
+
+pub fn signal(pid: u32, signal: Signal) -> io::Result<()> {
+    let pid = i32::try_from(pid).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    kill(Pid::from_raw(pid), NixSignal::from(signal))?;
+    Ok(())
+}
+
+pub fn number(signal: Signal) -> i32 {
+    NixSignal::from(signal) as i32
+}
+
+/// The longest process name that Linux records, in `/proc/PID/comm`.
+const COMM_LEN: usize = 15;
+
+pub fn find(name: &str) -> io::Result<Vec<u32>> {
+    let name = &name.as_bytes()[..name.len().min(COMM_LEN)];
+    let own = std::process::id();
+    let mut pids = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            Some(pid) if pid != own => pid,
+            _ => continue,
+        };
+        // A process may exit while the others are listed.
+        let Ok(comm) = std::fs::read(entry.path().join("comm")) else {
+            continue;
+        };
+        if comm.strip_suffix(b"\n").unwrap_or(&comm) == name {
+            pids.push(pid);
+        }
+    }
+    Ok(pids)
+}
+
+pub fn relay_termination(pid: Option<u32>) -> io::Result<()> {
+    extern "C" fn relay(signal: nix::libc::c_int) {
+        let pid = RELAY_PID.load(Ordering::SeqCst);
+        if pid > 0 {
+            // Safety: kill(2) is async-signal-safe.
+            unsafe { nix::libc::kill(pid, signal) };
+        }
+    }
+
+    let pid = match pid {
+        Some(pid) => {
+            i32::try_from(pid).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?
+        }
+        None => 0,
+    };
+    let action = SigAction::new(
+        SigHandler::Handler(relay),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // Safety: the handler only reads an atomic and calls kill(2).
+    unsafe { sigaction(TERMINATE.into(), &action) }?;
+    RELAY_PID.store(pid, Ordering::SeqCst);
+    Ok(())
+}
+
+pub struct Terminal {
+    fd: RawFd,
+    pgrp: Pid,
+    termios: Termios,
+    child: Option<Pid>,
+}
+
+#[cfg(feature = "runtime")]
+pub struct Termination {
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(feature = "runtime")]
+pub struct Forwarding {
+    signals: Vec<(Signal, tokio::signal::unix::Signal)>,
+}
+
+pub fn wait_stopped(pid: u32, block: bool) -> io::Result<bool> {
+    use nix::libc::{waitid, CLD_STOPPED, P_PID, WEXITED, WNOHANG, WNOWAIT, WSTOPPED};
+
+    // Exits are only observed (with WNOWAIT) so that the caller can reap the
+    // process, but stops are consumed so that each is reported once.
+    let flags = if block {
+        WEXITED | WSTOPPED | WNOWAIT
+    } else {
+        WSTOPPED | WNOHANG
+    };
+    loop {
+        // Safety: the zeroed siginfo_t is only written by waitid(2).
+        let mut info = unsafe { std::mem::zeroed::<nix::libc::siginfo_t>() };
+        if unsafe { waitid(P_PID, pid, &mut info, flags) } < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        let stopped = info.si_code == CLD_STOPPED;
+        if stopped && block {
+            // Safety: as above.
+            let mut info = unsafe { std::mem::zeroed::<nix::libc::siginfo_t>() };
+            unsafe { waitid(P_PID, pid, &mut info, WSTOPPED | WNOHANG) };
+        }
+        return Ok(stopped);
+    }
+}
+
+fn pid_of(pid: u32) -> io::Result<Pid> {
+    i32::try_from(pid)
+        .map(Pid::from_raw)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+// === impl Terminal ===
+
+impl Terminal {
+    pub fn foreground() -> Option<Self> {
+        let fd = io::stdin().as_raw_fd();
+        if !isatty(fd).unwrap_or(false) {
+            return None;
+        }
+        let pgrp = getpgrp();
+        if tcgetpgrp(fd).ok()? != pgrp {
+            return None;
+        }
+        let termios = tcgetattr(fd).ok()?;
+        Some(Self {
+            fd,
+            pgrp,
+            termios,
+            child: None,
+        })
+    }
+
+    pub fn configure(&self, cmd: &mut std::process::Command) {
+        use nix::unistd::setpgid;
+        use std::os::unix::process::CommandExt;
+
+        let fd = self.fd;
+        // The child also puts itself in the foreground so that it can't read
+        // from the terminal before the parent does so.
+        // Safety: setpgid(2), tcsetpgrp(3) and sigprocmask(2) are
+        // async-signal-safe.
+        unsafe {
+            cmd.pre_exec(move || {
+                setpgid(Pid::from_raw(0), Pid::from_raw(0))?;
+                set_foreground(fd, getpid())
+            })
+        };
+    }
+
+    pub fn attach(&mut self, pid: u32) -> io::Result<()> {
+        let pgid = pid_of(pid)?;
+        self.child = Some(pgid);
+        set_foreground(self.fd, pgid)
+    }
+
+    pub fn suspend(&mut self) -> io::Result<()> {
+        let child = match self.child {
+            Some(child) => child,
+            None => return Ok(()),
+        };
+
+        // CMD may have changed the terminal's settings, e.g. an editor that
+        // uses raw mode, so they are restored when it's continued.
+        let settings = tcgetattr(self.fd)?;
+        set_foreground(self.fd, self.pgrp)?;
+        tcsetattr(self.fd, SetArg::TCSADRAIN, &self.termios)?;
+        kill(getpid(), NixSignal::SIGSTOP)?;
+
+        // If the shell continued the job in the background, CMD stays there
+        // too.
+        if tcgetpgrp(self.fd)? == self.pgrp {
+            tcsetattr(self.fd, SetArg::TCSADRAIN, &settings)?;
+            set_foreground(self.fd, child)?;
+        }
+        killpg(child, NixSignal::SIGCONT)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "runtime")]
+    pub async fn relay_stops(&mut self) -> io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let pid = match self.child {
+            Some(child) => child.as_raw() as u32,
+            None => return futures::future::pending().await,
+        };
+        // A stop before the handler is registered is caught by checking
+        // before waiting.
+        let mut sigchld = signal(SignalKind::child())?;
+        loop {
+            if wait_stopped(pid, false)? {
+                self.suspend()?;
+            }
+            sigchld.recv().await;
+        }
+    }
+}
+
+/// Changes the terminal's foreground process group. SIGTTOU is blocked since
+/// it's sent when a background process does this.
+fn set_foreground(fd: RawFd, pgid: Pid) -> io::Result<()> {
+    let mut ttou = SigSet::empty();
+    ttou.add(NixSignal::SIGTTOU);
+    let mut mask = SigSet::empty();
+    pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&ttou), Some(&mut mask))?;
+    let res = tcsetpgrp(fd, pgid);
+    pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&mask), None)?;
+    res.map_err(Into::into)
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.child.is_some() {
+            let _ = set_foreground(self.fd, self.pgrp);
+            let _ = tcsetattr(self.fd, SetArg::TCSADRAIN, &self.termios);
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Termination {
+    pub fn register() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    pub async fn recv(&mut self) -> Signal {
+        tokio::select! {
+            _ = self.terminate.recv() => Signal::SIGTERM,
+            _ = self.interrupt.recv() => Signal::SIGINT,
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Forwarding {
+    pub fn register(signals: &[Signal]) -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        // Termination requests are received by `Termination`.
+        let signals = signals
+            .iter()
+            .filter(|&&s| s != Signal::SIGTERM && s != Signal::SIGINT)
+            .map(|&s| Ok((s, signal(SignalKind::from_raw(number(s)))?)))
+            .collect::<io::Result<_>>()?;
+        Ok(Self { signals })
+    }
+
+    pub async fn recv(&mut self) -> Signal {
+        std::future::poll_fn(|cx| {
+            for (signal, stream) in &mut self.signals {
+                if stream.poll_recv(cx).is_ready() {
+                    return std::task::Poll::Ready(*signal);
+                }
+            }
+            std::task::Poll::Pending
+        })
+        .await
+    }
+}
+
+// === impl Signal ===
+
+impl From<Signal> for NixSignal {
+    fn from(signal: Signal) -> Self {
+        match signal {
+            Signal::SIGHUP => Self::SIGHUP,
+            Signal::SIGINT => Self::SIGINT,
+            Signal::SIGQUIT => Self::SIGQUIT,
+            Signal::SIGILL => Self::SIGILL,
+            Signal::SIGTRAP => Self::SIGTRAP,
+            Signal::SIGABRT => Self::SIGABRT,
+            Signal::SIGBUS => Self::SIGBUS,
+            Signal::SIGFPE => Self::SIGFPE,
+            Signal::SIGKILL => Self::SIGKILL,
+            Signal::SIGUSR1 => Self::SIGUSR1,
+            Signal::SIGSEGV => Self::SIGSEGV,
+            Signal::SIGUSR2 => Self::SIGUSR2,
+            Signal::SIGPIPE => Self::SIGPIPE,
+            Signal::SIGALRM => Self::SIGALRM,
+            Signal::SIGTERM => Self::SIGTERM,
+            Signal::SIGCHLD => Self::SIGCHLD,
+            Signal::SIGCONT => Self::SIGCONT,
+            Signal::SIGSTOP => Self::SIGSTOP,
+            Signal::SIGTSTP => Self::SIGTSTP,
+            Signal::SIGTTIN => Self::SIGTTIN,
+            Signal::SIGTTOU => Self::SIGTTOU,
+            Signal::SIGURG => Self::SIGURG,
+            Signal::SIGXCPU => Self::SIGXCPU,
+            Signal::SIGXFSZ => Self::SIGXFSZ,
+            Signal::SIGVTALRM => Self::SIGVTALRM,
+            Signal::SIGPROF => Self::SIGPROF,
+            Signal::SIGWINCH => Self::SIGWINCH,
+            Signal::SIGIO => Self::SIGIO,
+            Signal::SIGSYS => Self::SIGSYS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{os::unix::process::ExitStatusExt, process::Command};
+
+    #[test]
+    fn test_signal() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        signal(child.id(), TERMINATE).unwrap();
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(NixSignal::SIGTERM as i32));
+    }
+
+    #[test]
+    fn test_signal_names() {
+        for signal in Signal::ALL {
+            assert_eq!(NixSignal::from(signal).as_str(), signal.name());
+            assert_eq!(Signal::from_name(signal.name()), Some(signal));
+        }
+        assert_eq!(Signal::from_name("SIGFOO"), None);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        // The child may not have been exec'd yet.
+        let mut found = false;
+        for _ in 0..100 {
+            if find("sleep").unwrap().contains(&child.id()) {
+                found = true;
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(found);
+        assert!(find("linkerd-await-nonexistent").unwrap().is_empty());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_exec_error() {
+        let error = exec("/nonexistent/linkerd-await-cmd".to_string(), vec![]);
+        assert!(matches!(error, Error::Exec(_, e) if e.kind() == io::ErrorKind::NotFound));
+    }
+}
@@ -0,0 +1,129 @@
+//! Windows has neither signals nor process groups, so CMD can't be signaled,
+//! replaced, or given the terminal. Each operation fails as unsupported,
+//! which linkerd-await reports as it would any other failure to manage CMD.
+
+use super::Signal;
+use crate::Error;
+use std::{convert::Infallible, io};
+
+/// Termination requests are named as on Unix.
+pub const TERMINATE: Signal = Signal::SIGTERM;
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "signals are not supported on Windows",
+    )
+}
+
+pub fn exec(cmd: String, _: Vec<String>) -> Error {
+    Error::Exec(cmd, unsupported())
+}
+
+pub fn signal(_: u32, _: Signal) -> io::Result<()> {
+    Err(unsupported())
+}
+
+/// Signals are numbered as on Linux, so that exit codes are reported
+/// consistently.
+pub fn number(signal: Signal) -> i32 {
+    match signal {
+        Signal::SIGHUP => 1,
+        Signal::SIGINT => 2,
+        Signal::SIGQUIT => 3,
+        Signal::SIGILL => 4,
+        Signal::SIGTRAP => 5,
+        Signal::SIGABRT => 6,
+        Signal::SIGBUS => 7,
+        Signal::SIGFPE => 8,
+        Signal::SIGKILL => 9,
+        Signal::SIGUSR1 => 10,
+        Signal::SIGSEGV => 11,
+        Signal::SIGUSR2 => 12,
+        Signal::SIGPIPE => 13,
+        Signal::SIGALRM => 14,
+        Signal::SIGTERM => 15,
+        Signal::SIGCHLD => 17,
+        Signal::SIGCONT => 18,
+        Signal::SIGSTOP => 19,
+        Signal::SIGTSTP => 20,
+        Signal::SIGTTIN => 21,
+        Signal::SIGTTOU => 22,
+        Signal::SIGURG => 23,
+        Signal::SIGXCPU => 24,
+        Signal::SIGXFSZ => 25,
+        Signal::SIGVTALRM => 26,
+        Signal::SIGPROF => 27,
+        Signal::SIGWINCH => 28,
+        Signal::SIGIO => 29,
+        Signal::SIGSYS => 31,
+    }
+}
+
+pub fn find(_: &str) -> io::Result<Vec<u32>> {
+    Err(unsupported())
+}
+
+pub fn relay_termination(_: Option<u32>) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn wait_stopped(_: u32, _: bool) -> io::Result<bool> {
+    Err(unsupported())
+}
+
+/// There is never a terminal to share with CMD.
+pub struct Terminal(Infallible);
+
+#[cfg(feature = "runtime")]
+pub struct Termination(Infallible);
+
+#[cfg(feature = "runtime")]
+pub struct Forwarding(Infallible);
+
+// === impl Terminal ===
+
+impl Terminal {
+    pub fn foreground() -> Option<Self> {
+        None
+    }
+
+    pub fn configure(&self, _: &mut std::process::Command) {
+        match self.0 {}
+    }
+
+    pub fn attach(&mut self, _: u32) -> io::Result<()> {
+        match self.0 {}
+    }
+
+    pub fn suspend(&mut self) -> io::Result<()> {
+        match self.0 {}
+    }
+
+    #[cfg(feature = "runtime")]
+    pub async fn relay_stops(&mut self) -> io::Result<()> {
+        match self.0 {}
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Termination {
+    pub fn register() -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    pub async fn recv(&mut self) -> Signal {
+        match self.0 {}
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Forwarding {
+    pub fn register(_: &[Signal]) -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    pub async fn recv(&mut self) -> Signal {
+        match self.0 {}
+    }
+}
@@ -0,0 +1,288 @@
+//! Optional support for HTTP proxies configured via the environment.
+//!
+//! Probes target the local proxy's admin server, so a `HTTP_PROXY` configured
+//! for the application would only get in the way. Proxy environment variables
+//! are therefore ignored unless `--use-env-proxy` is set. Without the
+//! `env-proxy` feature, they are always ignored.
+
+use crate::dial::Resolver;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::net::TcpStream;
+
+/// A connector that dials an HTTP proxy when one is configured for the
+/// destination.
+#[derive(Clone, Debug)]
+pub struct Connector {
+    #[cfg(feature = "env-proxy")]
+    proxy: Option<EnvProxy>,
+    resolver: Resolver,
+    #[cfg(feature = "tls")]
+    tls: crate::TlsConnector,
+    unix_socket: Option<PathBuf>,
+    http2: bool,
+}
+
+/// Proxy configuration read from `http_proxy`/`HTTP_PROXY` and
+/// `no_proxy`/`NO_PROXY`.
+#[cfg(feature = "env-proxy")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct EnvProxy {
+    proxy: Option<http::Uri>,
+    no_proxy: Vec<String>,
+}
+
+// === impl Connector ===
+
+impl Connector {
+    /// Creates a connector that only uses the proxy environment variables if
+    /// `use_env_proxy` is set.
+    pub fn new(use_env_proxy: bool) -> Self {
+        #[cfg(not(feature = "env-proxy"))]
+        let _ = use_env_proxy;
+        Self {
+            #[cfg(feature = "env-proxy")]
+            proxy: use_env_proxy.then(EnvProxy::from_env),
+            resolver: Resolver::default(),
+            #[cfg(feature = "tls")]
+            tls: crate::TlsConnector::new(),
+            unix_socket: None,
+            http2: false,
+        }
+    }
+
+    /// Resolves destination (and proxy) host names with the given resolver
+    /// instead of the system resolver.
+    pub fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Establishes TLS sessions for `https` destinations with the given
+    /// connector.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: crate::TlsConnector) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) fn tls(&self) -> &crate::TlsConnector {
+        &self.tls
+    }
+
+    /// Connects to the Unix domain socket instead of to the destination's
+    /// authority, which is only used for the `Host` header. Neither proxies
+    /// nor TLS are used.
+    pub fn with_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    pub(crate) fn unix_socket(&self) -> Option<&Path> {
+        self.unix_socket.as_deref()
+    }
+
+    /// Sends requests over cleartext connections with HTTP/2, assuming that
+    /// the server supports it, rather than HTTP/1.1. `https` destinations and
+    /// proxies are still sent HTTP/1.1 requests.
+    pub fn with_http2(mut self) -> Self {
+        self.http2 = true;
+        self
+    }
+
+    pub(crate) fn is_http2(&self) -> bool {
+        self.http2
+    }
+
+    /// Connects to the destination, or to the proxy configured for it. The
+    /// returned flag indicates whether the connection is to a proxy.
+    pub async fn connect(&self, dst: &http::Uri) -> io::Result<(TcpStream, bool)> {
+        let proxy = self.proxy_for(dst);
+        let is_proxied = proxy.is_some();
+        let target = proxy.as_ref().unwrap_or(dst);
+
+        let host = target
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URI has no host"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = target.port_u16().unwrap_or_else(|| default_port(target));
+        let io = crate::dial::dial(&self.resolver, host, port).await?;
+        io.set_nodelay(true)?;
+        Ok((io, is_proxied))
+    }
+
+    #[cfg(feature = "env-proxy")]
+    fn proxy_for(&self, dst: &http::Uri) -> Option<http::Uri> {
+        self.proxy.as_ref().and_then(|p| p.proxy_for(dst))
+    }
+
+    #[cfg(not(feature = "env-proxy"))]
+    fn proxy_for(&self, _: &http::Uri) -> Option<http::Uri> {
+        None
+    }
+}
+
+/// Returns the port implied by the URI's scheme.
+pub(crate) fn default_port(uri: &http::Uri) -> u16 {
+    if uri.scheme() == Some(&http::uri::Scheme::HTTPS) {
+        443
+    } else {
+        80
+    }
+}
+
+// === impl EnvProxy ===
+
+#[cfg(feature = "env-proxy")]
+impl EnvProxy {
+    fn from_env() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+        };
+        Self::parse(
+            var(&["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]).as_deref(),
+            var(&["no_proxy", "NO_PROXY"]).as_deref(),
+        )
+    }
+
+    fn parse(proxy: Option<&str>, no_proxy: Option<&str>) -> Self {
+        let proxy = proxy.and_then(|p| match p.parse::<http::Uri>() {
+            // Proxies are commonly configured without a scheme.
+            Ok(uri) if uri.scheme().is_none() => format!("http://{}", p).parse().ok(),
+            Ok(uri) => Some(uri),
+            Err(e) => {
+                eprintln!("Ignoring invalid HTTP proxy: {}: {}", p, e);
+                None
+            }
+        });
+        let no_proxy = no_proxy
+            .unwrap_or_default()
+            .split(',')
+            .map(|entry| entry.trim().to_ascii_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        Self { proxy, no_proxy }
+    }
+
+    /// Returns the proxy to use for the destination, if any.
+    fn proxy_for(&self, dst: &http::Uri) -> Option<http::Uri> {
+        // `https` requests would have to be tunneled with CONNECT, which isn't
+        // supported, so they always connect directly.
+        if dst.scheme() != Some(&http::uri::Scheme::HTTP) {
+            return None;
+        }
+        let proxy = self.proxy.clone()?;
+        let host = dst.host()?.trim_start_matches('[').trim_end_matches(']');
+        let host = host.to_ascii_lowercase();
+        let port = dst.port_u16();
+
+        let excluded = self.no_proxy.iter().any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+
+            // Entries may be qualified with a port, in which case only that
+            // port is excluded.
+            let (name, entry_port) = match entry.rsplit_once(':') {
+                Some((name, p)) if !name.ends_with(':') && !name.is_empty() => {
+                    match p.parse::<u16>() {
+                        Ok(p) => (name, Some(p)),
+                        Err(_) => (entry.as_str(), None),
+                    }
+                }
+                _ => (entry.as_str(), None),
+            };
+            if entry_port.is_some() && entry_port != port {
+                return false;
+            }
+
+            let name = name
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .trim_start_matches('.');
+            host == name || host.ends_with(&format!(".{}", name))
+        });
+        if excluded {
+            return None;
+        }
+
+        Some(proxy)
+    }
+}
+
+#[cfg(all(test, feature = "env-proxy"))]
+mod tests {
+    use super::*;
+
+    fn proxy_for(proxy: &str, no_proxy: &str, dst: &str) -> Option<String> {
+        EnvProxy::parse(Some(proxy), Some(no_proxy))
+            .proxy_for(&dst.parse().unwrap())
+            .map(|uri| uri.to_string())
+    }
+
+    #[test]
+    fn test_no_proxy() {
+        let proxied = Some("http://proxy:3128/".to_string());
+        assert_eq!(
+            proxy_for("proxy:3128", "", "http://localhost:4191/ready"),
+            proxied
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "localhost", "http://localhost:4191"),
+            None
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "*", "http://example.com"),
+            None
+        );
+        assert_eq!(
+            proxy_for(
+                "http://proxy:3128",
+                ".svc,example.com",
+                "http://a.example.com"
+            ),
+            None
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "a.svc", "http://b.svc"),
+            proxied
+        );
+        assert_eq!(
+            proxy_for(
+                "http://proxy:3128",
+                "localhost:4191",
+                "http://localhost:4191"
+            ),
+            None
+        );
+        assert_eq!(
+            proxy_for(
+                "http://proxy:3128",
+                "localhost:4191",
+                "http://localhost:8080"
+            ),
+            proxied
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "::1", "http://[::1]:4191"),
+            None
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "[::1]:4191", "http://[::1]:4191"),
+            None
+        );
+        assert_eq!(
+            proxy_for("http://proxy:3128", "", "https://example.com"),
+            None
+        );
+        assert_eq!(
+            EnvProxy::parse(None, Some("*")).proxy_for(&"http://localhost".parse().unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use crate::{
+    check::{BoxError, Check},
+    client, proxy, Backoff, BackoffStrategy, CheckError, Error,
+};
+use tokio::time;
+
+/// Configures how a readiness check is retried.
+#[derive(Clone, Debug)]
+pub struct Retry {
+    /// Determines how long to wait after a failed check.
+    pub backoff: Backoff,
+
+    /// Determines how long to wait after a check fails to connect.
+    pub connect_backoff: Backoff,
+
+    /// Checks on a fixed schedule, measured from the start of each attempt,
+    /// instead of backing off after each failure.
+    pub interval: Option<time::Duration>,
+
+    /// Time to wait before the first check.
+    pub initial_delay: Option<time::Duration>,
+
+    /// Bounds the number of checks.
+    pub max_attempts: Option<u32>,
+
+    /// The number of consecutive successful checks required, so that a
+    /// single success during a restart isn't trusted.
+    pub success_threshold: u32,
+
+    /// Time to wait after a successful check until the threshold is reached.
+    pub success_interval: time::Duration,
+
+    /// Bounds the time spent waiting. A zero timeout never elapses.
+    pub timeout: Option<time::Duration>,
+
+    /// Warns when the check is slow to succeed.
+    pub timeout_warning: Option<TimeoutWarning>,
+}
+
+/// Configures an early warning for when the proxy is slow to become ready.
+#[derive(Clone, Debug)]
+pub struct TimeoutWarning {
+    /// The percentage of the timeout after which the warning is emitted.
+    pub percent: u8,
+
+    /// Receives a JSON event describing the warning.
+    pub webhook: Option<http::Uri>,
+
+    /// Connects to the webhook.
+    pub connector: proxy::Connector,
+}
+
+/// Retries the check until it succeeds, failing if the timeout elapses or the
+/// attempts are exhausted first, or if the check can never succeed.
+pub async fn await_ready(check: &dyn Check, retry: &Retry) -> Result<(), Error> {
+    let ready = match retry.timeout {
+        Some(timeout) if !timeout.is_zero() => {
+            let warn = async {
+                match retry.timeout_warning.as_ref() {
+                    Some(warning) => warning.warn_after(timeout).await,
+                    None => futures::future::pending().await,
+                }
+            };
+            // The last failure describes which check wasn't ready.
+            let mut last = None;
+            let poll = async {
+                tokio::select! {
+                    ready = retry.poll(check, &mut last) => ready,
+                    never = warn => match never {},
+                }
+            };
+            match time::timeout(timeout, poll).await {
+                Ok(ready) => ready,
+                Err(_) => {
+                    let check = last.unwrap_or_else(|| check.to_string());
+                    return Err(Error::Timeout(check, timeout));
+                }
+            }
+        }
+        _ => retry.poll(check, &mut None).await,
+    };
+    ready
+}
+
+impl Retry {
+    /// Creates a configuration that retries a check every second until it
+    /// succeeds.
+    pub fn new() -> Self {
+        let backoff = Backoff {
+            strategy: BackoffStrategy::Constant,
+            base: time::Duration::from_secs(1),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 0,
+        };
+        Self {
+            backoff,
+            connect_backoff: backoff,
+            interval: None,
+            initial_delay: None,
+            max_attempts: None,
+            success_threshold: 1,
+            success_interval: time::Duration::from_secs(1),
+            timeout: None,
+            timeout_warning: None,
+        }
+    }
+
+    /// Checks readiness until the check succeeds, fails in a way that can't be
+    /// retried, or has been attempted the maximum number of times, recording
+    /// each failure in `last`.
+    async fn poll(&self, check: &dyn Check, last: &mut Option<String>) -> Result<(), Error> {
+        // Avoid a check that is bound to fail when the target is known to take
+        // a while to start.
+        if let Some(delay) = self.initial_delay {
+            time::sleep(delay).await;
+        }
+
+        // Failures are counted per class so that, e.g., a proxy that starts
+        // listening but isn't yet ready doesn't inherit a long connection
+        // backoff.
+        let mut connect_failures = 0;
+        let mut failures = 0;
+        let mut attempts = 0;
+        let mut successes = 0;
+        loop {
+            attempts += 1;
+            let start = time::Instant::now();
+            // Each check bounds its own attempts, e.g. with `Limited`, since
+            // they may legitimately take longer than others.
+            let error = match check.check().await {
+                Ok(()) => {
+                    successes += 1;
+                    if successes >= self.success_threshold {
+                        return Ok(());
+                    }
+                    time::sleep(self.success_interval).await;
+                    continue;
+                }
+                Err(error) if error.is_unrecoverable() => return Err(Error::Unrecoverable(error)),
+                Err(error) if error.is_timeout() => return Err(Error::CheckTimeout(error)),
+                Err(error) if self.max_attempts.is_some_and(|max| attempts >= max) => {
+                    return Err(Error::Attempts(attempts, error))
+                }
+                Err(error) => error,
+            };
+            *last = Some(error.to_string());
+            successes = 0;
+            let delay = match error {
+                CheckError::Connect(_) => {
+                    failures = 0;
+                    connect_failures += 1;
+                    self.connect_backoff.delay(connect_failures)
+                }
+                _ => {
+                    connect_failures = 0;
+                    failures += 1;
+                    self.backoff.delay(failures)
+                }
+            };
+            match self.interval {
+                // An attempt that outlasts the interval is followed
+                // immediately by the next one.
+                Some(interval) => time::sleep_until(start + interval).await,
+                None => time::sleep(delay).await,
+            }
+        }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeoutWarning {
+    /// Emits the warning once its share of the timeout has elapsed and then
+    /// never completes.
+    async fn warn_after(&self, timeout: time::Duration) -> std::convert::Infallible {
+        let elapsed = timeout * u32::from(self.percent) / 100;
+        time::sleep(elapsed).await;
+
+        eprintln!(
+            "Warning: linkerd-proxy is not ready after {:?} ({}% of the {:?} timeout)",
+            elapsed, self.percent, timeout
+        );
+
+        if let Some(uri) = self.webhook.clone() {
+            let body = format!(
+                r#"{{"event":"TimeoutWarning","elapsedMs":{},"timeoutMs":{}}}"#,
+                elapsed.as_millis(),
+                timeout.as_millis()
+            );
+            let req = http::Request::post(uri)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(body.into_bytes());
+            let error: Option<BoxError> = match req {
+                Ok(req) => client::request(&self.connector, req)
+                    .await
+                    .err()
+                    .map(Into::into),
+                Err(e) => Some(Error::InvalidRequest(e).into()),
+            };
+            if let Some(e) = error {
+                eprintln!("Failed to send timeout warning webhook: {}", e);
+            }
+        }
+
+        futures::future::pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::CheckFuture;
+    use std::{cell::RefCell, collections::VecDeque, fmt};
+
+    /// Replays a fixed sequence of results, recording when each attempt runs.
+    struct Mock {
+        results: RefCell<VecDeque<Result<(), CheckError>>>,
+        attempts: RefCell<Vec<time::Instant>>,
+        latency: time::Duration,
+    }
+
+    impl Mock {
+        fn new(results: Vec<Result<(), CheckError>>) -> Self {
+            Self {
+                results: RefCell::new(results.into()),
+                attempts: RefCell::default(),
+                latency: time::Duration::ZERO,
+            }
+        }
+
+        fn with_latency(self, latency: time::Duration) -> Self {
+            Self { latency, ..self }
+        }
+
+        fn delays(&self, start: time::Instant) -> Vec<time::Duration> {
+            self.attempts.borrow().iter().map(|t| *t - start).collect()
+        }
+    }
+
+    impl Check for Mock {
+        fn check(&self) -> CheckFuture<'_> {
+            self.attempts.borrow_mut().push(time::Instant::now());
+            let result = self.results.borrow_mut().pop_front().expect("no results");
+            Box::pin(async move {
+                time::sleep(self.latency).await;
+                result
+            })
+        }
+    }
+
+    impl fmt::Display for Mock {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("mock")
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_backoff_per_class() {
+        let mut retry = Retry::new();
+        retry.connect_backoff.base = time::Duration::from_secs(3);
+        let check = Mock::new(vec![
+            Err(CheckError::connect("refused")),
+            Err(CheckError::connect("refused")),
+            Err(CheckError::not_ready("503")),
+            Ok(()),
+        ]);
+        let start = time::Instant::now();
+        await_ready(&check, &retry).await.unwrap();
+        assert_eq!(
+            check.delays(start),
+            [0, 3, 6, 7].map(time::Duration::from_secs)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_interval() {
+        let retry = Retry {
+            interval: Some(time::Duration::from_millis(500)),
+            ..Retry::new()
+        };
+        let check = Mock::new(vec![
+            Err(CheckError::connect("refused")),
+            Err(CheckError::not_ready("503")),
+            Ok(()),
+        ])
+        .with_latency(time::Duration::from_millis(200));
+        let start = time::Instant::now();
+        await_ready(&check, &retry).await.unwrap();
+        assert_eq!(
+            check.delays(start),
+            [0, 500, 1000].map(time::Duration::from_millis)
+        );
+
+        // A slow attempt is followed immediately by the next one.
+        let check = Mock::new(vec![Err(CheckError::not_ready("503")), Ok(())])
+            .with_latency(time::Duration::from_millis(800));
+        let start = time::Instant::now();
+        await_ready(&check, &retry).await.unwrap();
+        assert_eq!(
+            check.delays(start),
+            [0, 800].map(time::Duration::from_millis)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_unrecoverable() {
+        let check = Mock::new(vec![
+            Err(CheckError::not_ready("503")),
+            Err(CheckError::unrecoverable("404")),
+        ]);
+        let error = await_ready(&check, &Retry::new()).await.unwrap_err();
+        assert!(matches!(error, Error::Unrecoverable(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_check_timeout() {
+        let check = Mock::new(vec![
+            Err(CheckError::not_ready("503")),
+            Err(CheckError::timeout("not ready within 1m: 503")),
+        ]);
+        let error = await_ready(&check, &Retry::new()).await.unwrap_err();
+        assert!(matches!(error, Error::CheckTimeout(_)));
+        assert_eq!(error.exit_code(), crate::EX_UNAVAILABLE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_success_threshold() {
+        let retry = Retry {
+            success_threshold: 2,
+            success_interval: time::Duration::from_millis(100),
+            ..Retry::new()
+        };
+        let check = Mock::new(vec![
+            Ok(()),
+            Err(CheckError::not_ready("503")),
+            Ok(()),
+            Ok(()),
+        ]);
+        let start = time::Instant::now();
+        await_ready(&check, &retry).await.unwrap();
+        // A failure resets the count.
+        assert_eq!(
+            check.delays(start),
+            [0, 100, 1100, 1200].map(time::Duration::from_millis)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_max_attempts() {
+        let retry = Retry {
+            max_attempts: Some(2),
+            ..Retry::new()
+        };
+        let check = Mock::new(vec![
+            Err(CheckError::connect("refused")),
+            Err(CheckError::not_ready("503")),
+        ]);
+        let error = await_ready(&check, &retry).await.unwrap_err();
+        assert!(matches!(error, Error::Attempts(2, CheckError::NotReady(_))));
+        // The last attempt isn't followed by a backoff.
+        assert_eq!(
+            check.delays(time::Instant::now() - time::Duration::from_secs(1)),
+            [0, 1].map(time::Duration::from_secs)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_timeout() {
+        let retry = Retry {
+            timeout: Some(time::Duration::from_secs(2)),
+            ..Retry::new()
+        };
+        let check = Mock::new((0..3).map(|_| Err(CheckError::not_ready("503"))).collect());
+        let error = await_ready(&check, &retry).await.unwrap_err();
+        assert!(matches!(error, Error::Timeout(..)));
+        // The check's last failure is reported.
+        assert_eq!(error.to_string(), "Not ready within 2s timeout: 503");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_timeout_boundary() {
+        let retry = Retry {
+            timeout: Some(time::Duration::from_secs(3)),
+            ..Retry::new()
+        };
+
+        // A check that succeeds just before the timeout elapses is ready.
+        let check = Mock::new(vec![
+            Err(CheckError::not_ready("503")),
+            Err(CheckError::not_ready("503")),
+            Ok(()),
+        ])
+        .with_latency(time::Duration::from_millis(333));
+        let start = time::Instant::now();
+        await_ready(&check, &retry).await.unwrap();
+        assert_eq!(start.elapsed(), time::Duration::from_millis(2_999));
+
+        // One that would succeed just after it isn't.
+        let check = Mock::new(vec![
+            Err(CheckError::not_ready("503")),
+            Err(CheckError::not_ready("503")),
+            Ok(()),
+        ])
+        .with_latency(time::Duration::from_millis(334));
+        let start = time::Instant::now();
+        let error = await_ready(&check, &retry).await.unwrap_err();
+        assert!(matches!(error, Error::Timeout(..)));
+        assert_eq!(start.elapsed(), time::Duration::from_secs(3));
+    }
+}
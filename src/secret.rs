@@ -0,0 +1,205 @@
+//! Copies mounted secrets to the paths an application expects, optionally
+//! decoding them from base64, so that entrypoints don't need a shell wrapper
+//! to do so.
+
+use crate::Error;
+use std::{error, fmt, io, path::PathBuf, str::FromStr};
+
+/// A secret file to copy before CMD runs, parsed from `SRC:DST[:base64]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeSecret {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub base64: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidDecodeSecret(String);
+
+// === impl DecodeSecret ===
+
+impl DecodeSecret {
+    /// Writes the (decoded) secret to its destination with the given
+    /// permissions.
+    ///
+    /// The secret is written to a temporary file that is renamed into place,
+    /// so its contents are never readable with other permissions.
+    pub fn write(&self, mode: u32) -> Result<(), Error> {
+        use std::{
+            fs::{OpenOptions, Permissions},
+            io::Write,
+            os::unix::fs::{OpenOptionsExt, PermissionsExt},
+        };
+
+        let read = |e| Error::SecretRead(self.src.clone(), e);
+        let contents = std::fs::read(&self.src).map_err(read)?;
+        let contents = if self.base64 {
+            decode_base64(&contents)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid base64"))
+                .map_err(read)?
+        } else {
+            contents
+        };
+
+        let name = self.dst.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = self.dst.with_file_name(format!(".{}.tmp", name));
+        let write = || {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(mode)
+                .open(&tmp)?;
+            // The mode passed to open(2) is masked by the umask.
+            file.set_permissions(Permissions::from_mode(mode))?;
+            file.write_all(&contents)?;
+            file.sync_all()?;
+            std::fs::rename(&tmp, &self.dst)
+        };
+        write().map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            Error::SecretWrite(self.dst.clone(), e)
+        })
+    }
+}
+
+impl FromStr for DecodeSecret {
+    type Err = InvalidDecodeSecret;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidDecodeSecret(s.to_string());
+        let (src, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (dst, base64) = match rest.rsplit_once(':') {
+            Some((dst, "base64")) => (dst, true),
+            Some(_) => return Err(invalid()),
+            None => (rest, false),
+        };
+        // The destination is replaced by renaming a sibling file into place,
+        // so it must name a file.
+        if src.is_empty() || dst.is_empty() || dst.ends_with('/') {
+            return Err(invalid());
+        }
+        Ok(Self {
+            src: src.into(),
+            dst: dst.into(),
+            base64,
+        })
+    }
+}
+
+impl fmt::Display for InvalidDecodeSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid secret {:?}; expected SRC:DST[:base64]", self.0)
+    }
+}
+
+impl error::Error for InvalidDecodeSecret {}
+
+/// Decodes standard or URL-safe base64, with or without padding. Whitespace,
+/// e.g. line wrapping, is ignored.
+fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+    let value = |b: u8| match b {
+        b'A'..=b'Z' => Some(b - b'A'),
+        b'a'..=b'z' => Some(b - b'a' + 26),
+        b'0'..=b'9' => Some(b - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+
+    let input = input
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    let data = match input.iter().position(|&b| b == b'=') {
+        // Padding may only complete the final quantum.
+        Some(i) if input[i..].iter().all(|&b| b == b'=') && input.len() % 4 == 0 => &input[..i],
+        Some(_) => return None,
+        None => &input[..],
+    };
+    if data.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut acc = 0u32;
+        for &b in chunk {
+            acc = acc << 6 | u32::from(value(b)?);
+        }
+        acc <<= 6 * (4 - chunk.len() as u32);
+        out.extend_from_slice(&acc.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "/secrets/token:/app/token".parse(),
+            Ok(DecodeSecret {
+                src: "/secrets/token".into(),
+                dst: "/app/token".into(),
+                base64: false,
+            })
+        );
+        assert_eq!(
+            "/secrets/key:/app/key.pem:base64".parse(),
+            Ok(DecodeSecret {
+                src: "/secrets/key".into(),
+                dst: "/app/key.pem".into(),
+                base64: true,
+            })
+        );
+        assert!("/secrets/key".parse::<DecodeSecret>().is_err());
+        assert!("/secrets/key:/app/key:hex".parse::<DecodeSecret>().is_err());
+        assert!("/secrets/key:/app/".parse::<DecodeSecret>().is_err());
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"").unwrap(), b"");
+        assert_eq!(decode_base64(b"Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64(b"Zm8").unwrap(), b"fo");
+        assert_eq!(decode_base64(b"Zm9v\nYmFy\n").unwrap(), b"foobar");
+        assert_eq!(decode_base64(b"-_8=").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode_base64(b"+/8=").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode_base64(b"Zg=a"), None);
+        assert_eq!(decode_base64(b"Z"), None);
+        assert_eq!(decode_base64(b"Zm9v!"), None);
+    }
+
+    #[test]
+    fn test_write() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("linkerd-await-secret-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("src"), "c2VjcmV0\n").unwrap();
+
+        let secret = DecodeSecret {
+            src: dir.join("src"),
+            dst: dir.join("dst"),
+            base64: true,
+        };
+        secret.write(0o640).unwrap();
+        assert_eq!(std::fs::read(dir.join("dst")).unwrap(), b"secret");
+        let mode = std::fs::metadata(dir.join("dst"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o640);
+
+        let missing = DecodeSecret {
+            src: dir.join("missing"),
+            ..secret
+        };
+        assert!(matches!(missing.write(0o600), Err(Error::SecretRead(..))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{client, proxy, Backoff};
+use std::{error, fmt};
+use tokio::time;
+
+/// Describes why a shutdown request failed.
+#[derive(Debug)]
+pub enum ShutdownError {
+    /// The request could not be constructed, e.g. from its headers.
+    Invalid(http::Error),
+
+    /// The request could not be sent or its response could not be read.
+    Request(client::Error),
+
+    /// The server responded with an unsuccessful status.
+    Status(http::StatusCode),
+
+    /// No request completed before the timeout elapsed.
+    Timeout(time::Duration),
+}
+
+/// Asks the proxy to shut down via its admin server, sending the headers with
+/// the request. Linkerd's proxy is sent a `POST`, but other sidecars may expect
+/// another method.
+pub async fn send_shutdown(
+    connector: &proxy::Connector,
+    method: http::Method,
+    uri: http::Uri,
+    headers: http::HeaderMap,
+) -> Result<(), ShutdownError> {
+    let mut req = http::Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Vec::new())
+        .map_err(ShutdownError::Invalid)?;
+    *req.headers_mut() = headers;
+    match client::request(connector, req).await {
+        Ok(status) if status.is_success() => Ok(()),
+        Ok(status) => Err(ShutdownError::Status(status)),
+        Err(e) => Err(ShutdownError::Request(e)),
+    }
+}
+
+/// Sends the shutdown request, as [`send_shutdown`] does, until it succeeds
+/// or the timeout elapses, waiting between attempts as the backoff dictates.
+/// The last failure is returned if no attempt succeeds.
+///
+/// A proxy may exit before it has responded, so a refused connection after
+/// an earlier attempt reached the server is taken to mean that it has shut
+/// down.
+pub async fn retry_shutdown(
+    connector: &proxy::Connector,
+    method: http::Method,
+    uri: http::Uri,
+    headers: http::HeaderMap,
+    backoff: &Backoff,
+    timeout: time::Duration,
+) -> Result<(), ShutdownError> {
+    let mut last = None;
+    let attempts = async {
+        let mut failures = 0;
+        let mut reached = false;
+        loop {
+            let result =
+                send_shutdown(connector, method.clone(), uri.clone(), headers.clone()).await;
+            let error = match result {
+                Ok(()) => return Ok(()),
+                Err(ShutdownError::Request(e)) if e.is_connect() && reached => return Ok(()),
+                // Retrying can't fix the request.
+                Err(error @ ShutdownError::Invalid(_)) => return Err(error),
+                Err(error) => error,
+            };
+            reached |= !matches!(&error, ShutdownError::Request(e) if e.is_connect());
+            last = Some(error);
+            failures += 1;
+            time::sleep(backoff.delay(failures)).await;
+        }
+    };
+    let result = time::timeout(timeout, attempts).await;
+    match result {
+        Ok(result) => result,
+        Err(_) => Err(last.unwrap_or(ShutdownError::Timeout(timeout))),
+    }
+}
+
+/// Waits until the server stops accepting connections, e.g. because the
+/// proxy has exited after being asked to shut down. Returns `false` if it's
+/// still accepting connections once the timeout elapses.
+pub async fn await_shutdown(
+    connector: &proxy::Connector,
+    uri: &http::Uri,
+    timeout: time::Duration,
+) -> bool {
+    const INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+    let stopped = async {
+        loop {
+            match client::connect(connector, uri).await {
+                Err(e) if e.is_connect() => return,
+                _ => time::sleep(INTERVAL).await,
+            }
+        }
+    };
+    time::timeout(timeout, stopped).await.is_ok()
+}
+
+// === impl ShutdownError ===
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "invalid request: {}", e),
+            Self::Request(e) => e.fmt(f),
+            Self::Status(status) => write!(f, "server responded with {}", status),
+            Self::Timeout(timeout) => write!(f, "no response within {:?}", timeout),
+        }
+    }
+}
+
+impl error::Error for ShutdownError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Invalid(e) => Some(e),
+            Self::Request(e) => Some(e),
+            Self::Status(_) | Self::Timeout(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_await_shutdown() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/ready", listener.local_addr().unwrap())
+            .parse::<http::Uri>()
+            .unwrap();
+        let connector = proxy::Connector::new(false);
+        assert!(!await_shutdown(&connector, &uri, time::Duration::from_millis(250)).await);
+
+        tokio::spawn(async move {
+            time::sleep(time::Duration::from_millis(150)).await;
+            drop(listener);
+        });
+        assert!(await_shutdown(&connector, &uri, time::Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn test_retry_shutdown() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/shutdown", listener.local_addr().unwrap())
+            .parse::<http::Uri>()
+            .unwrap();
+        tokio::spawn(async move {
+            for rsp in [
+                "503 Service Unavailable",
+                "200 OK",
+                "503 Service Unavailable",
+            ] {
+                let (mut io, _) = listener.accept().await.unwrap();
+                let _ = io.read(&mut [0; 1024]).await.unwrap();
+                let rsp = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", rsp);
+                io.write_all(rsp.as_bytes()).await.unwrap();
+            }
+            // Later requests are refused.
+        });
+
+        let connector = proxy::Connector::new(false);
+        let backoff = Backoff {
+            strategy: crate::BackoffStrategy::Constant,
+            base: time::Duration::from_millis(10),
+            max: None,
+            multiplier: Backoff::DEFAULT_MULTIPLIER,
+            jitter: 0,
+        };
+        let retry = |timeout| {
+            retry_shutdown(
+                &connector,
+                http::Method::POST,
+                uri.clone(),
+                http::HeaderMap::new(),
+                &backoff,
+                timeout,
+            )
+        };
+        assert!(retry(time::Duration::from_secs(5)).await.is_ok());
+        // Once the server has been reached, a refused connection means that
+        // it has shut down.
+        assert!(retry(time::Duration::from_secs(5)).await.is_ok());
+
+        let unreachable = "http://127.0.0.1:1/shutdown".parse::<http::Uri>().unwrap();
+        let error = retry_shutdown(
+            &connector,
+            http::Method::POST,
+            unreachable,
+            http::HeaderMap::new(),
+            &backoff,
+            time::Duration::from_millis(100),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, ShutdownError::Request(e) if e.is_connect()));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    process::{self, Signal},
+    Error,
+};
+use std::{future::Future, io, path::PathBuf, pin::Pin, process::ExitStatus};
+
+/// Describes how a command running in `--background` mode is notified once
+/// the proxy becomes ready.
+pub struct Notify {
+    /// Completes when the proxy is ready or fails if it can't become ready.
+    pub ready: Pin<Box<dyn Future<Output = Result<(), Error>>>>,
+
+    /// A signal sent to the process.
+    pub signal: Option<Signal>,
+
+    /// A file created once the proxy is ready.
+    pub file: Option<PathBuf>,
+}
+
+impl Notify {
+    /// Waits for the proxy to become ready and then notifies the process.
+    async fn notify(self, pid: Option<u32>) {
+        if let Err(error) = self.ready.await {
+            eprintln!("{}; CMD will not be notified", error);
+            return;
+        }
+
+        if let Some(path) = self.file {
+            if let Err(e) = std::fs::write(&path, b"") {
+                eprintln!("Failed to write notify file: {}: {}", path.display(), e);
+            }
+        }
+
+        if let (Some(signal), Some(pid)) = (self.signal, pid) {
+            if let Err(e) = process::signal(pid, signal) {
+                eprintln!("Failed to send {} to child process: {}", signal, e);
+            }
+        }
+    }
+}
+
+/// Forks the specified process, forwarding the given signals to it.
+///
+/// Requests to terminate that aren't forwarded, and the `ignore`d signals,
+/// are caught and dropped while the process runs, so that they don't
+/// terminate linkerd-await and leave the process without its supervisor. If `pid_file` is set, the process's PID is written to it while the
+/// process runs. If `notify` is set, the process is notified once the proxy
+/// becomes ready.
+pub async fn supervise(
+    cmd: String,
+    args: Vec<String>,
+    forward: &[Signal],
+    ignore: &[Signal],
+    pid_file: Option<PathBuf>,
+    notify: Option<Notify>,
+) -> Result<ExitStatus, Error> {
+    use tokio::process::Command;
+
+    // Register for termination requests before spawning the process so that a
+    // failure doesn't leave an orphaned child behind.
+    let mut termination = process::Termination::register().map_err(Error::Signal)?;
+    let handled = forward.iter().chain(ignore).copied().collect::<Vec<_>>();
+    let mut forwarding = process::Forwarding::register(&handled).map_err(Error::Signal)?;
+
+    // In an interactive shell, CMD is given the terminal so that job control
+    // reaches it.
+    let mut terminal = process::Terminal::foreground();
+    let mut command = std::process::Command::new(&cmd);
+    command.args(args);
+    if let Some(terminal) = terminal.as_ref() {
+        terminal.configure(&mut command);
+    }
+    let mut child = match Command::from(command).spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(Error::Spawn(cmd, e)),
+    };
+    let pid = child.id();
+    if let (Some(terminal), Some(pid)) = (terminal.as_mut(), pid) {
+        if let Err(e) = terminal.attach(pid) {
+            eprintln!("Failed to give the terminal to child process: {}", e);
+        }
+    }
+    let stops = async {
+        match terminal.as_mut() {
+            Some(terminal) => terminal.relay_stops().await,
+            None => futures::future::pending().await,
+        }
+    };
+    tokio::pin!(stops);
+    let mut is_relaying_stops = true;
+
+    // The process is already running, so failing to write the PID file
+    // shouldn't prevent it from completing.
+    let pid_file = pid_file.and_then(|path| match child.id() {
+        Some(pid) => match write_pid_file(&path, pid) {
+            Ok(()) => Some(path),
+            Err(e) => {
+                eprintln!("{}", Error::PidFile(path, e));
+                None
+            }
+        },
+        None => None,
+    });
+
+    let notified = async move {
+        match notify {
+            Some(notify) => notify.notify(pid).await,
+            None => futures::future::pending().await,
+        }
+    };
+    tokio::pin!(notified);
+    let mut is_notified = false;
+
+    // Wait for the process to exit on its own or, if linkerd-await is asked to
+    // terminate, relay the request so it begins shutdown.
+    let ex = loop {
+        tokio::select! {
+            ex = child.wait() => break ex,
+            signal = termination.recv() => {
+                if !forward.contains(&signal) {
+                    eprintln!("Ignoring {}, which is not forwarded to the child process", signal);
+                    continue;
+                }
+                // If the child hasn't already completed, ask it to terminate.
+                if let Some(pid) = child.id() {
+                    if let Err(e) = process::signal(pid, signal) {
+                        eprintln!("Failed to forward {} to child process: {}", signal, e);
+                    }
+                }
+                // Wait to get the child's exit code.
+                break child.wait().await;
+            }
+            signal = forwarding.recv() => {
+                if !forward.contains(&signal) {
+                    eprintln!("Ignoring {}, which is not forwarded to the child process", signal);
+                    continue;
+                }
+                if let Some(pid) = child.id() {
+                    if let Err(e) = process::signal(pid, signal) {
+                        eprintln!("Failed to forward {} to child process: {}", signal, e);
+                    }
+                }
+            }
+            () = &mut notified, if !is_notified => is_notified = true,
+            res = &mut stops, if is_relaying_stops => {
+                if let Err(e) = res {
+                    eprintln!("Failed to relay job control to child process: {}", e);
+                }
+                is_relaying_stops = false;
+            }
+        }
+    };
+    if let Some(path) = pid_file {
+        let _ = std::fs::remove_file(path);
+    }
+    ex.map_err(Error::Wait)
+}
+
+/// Opens the file and takes an exclusive lock on it, failing if the lock is
+/// already held by another process.
+pub fn lock_exclusive(path: PathBuf) -> Result<(PathBuf, std::fs::File), Error> {
+    use nix::{
+        errno::Errno,
+        fcntl::{flock, FlockArg},
+    };
+    use std::os::unix::io::AsRawFd;
+
+    let file = match std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(e) => return Err(Error::Lock(path, e)),
+    };
+    match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+        Ok(()) => Ok((path, file)),
+        Err(Errno::EWOULDBLOCK) => Err(Error::Locked(path)),
+        Err(e) => Err(Error::Lock(path, e.into())),
+    }
+}
+
+/// Clears the file's close-on-exec flag so that it remains open (and locked)
+/// in the exec'd process.
+pub fn inherit_on_exec(file: &std::fs::File) -> io::Result<()> {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    use std::os::unix::io::AsRawFd;
+
+    fcntl(file.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::empty()))?;
+    Ok(())
+}
+
+pub fn write_pid_file(path: &std::path::Path, pid: u32) -> io::Result<()> {
+    std::fs::write(path, format!("{}\n", pid))
+}
+
+/// Writes a file recording when the proxy became ready, e.g.
+/// `{"ready":true,"timestamp":"2024-05-01T12:00:00.000000Z"}`.
+///
+/// The file is written to a temporary file that is renamed into place so that
+/// other containers watching for it never read it partially written.
+pub fn write_ready_file(path: &std::path::Path) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    let contents = format!(
+        "{{\"ready\":true,\"timestamp\":\"{}\"}}\n",
+        crate::timestamp::format_time(std::time::SystemTime::now())
+    );
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ready_file() {
+        let dir = std::env::temp_dir().join(format!("linkerd-await-ready-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ready");
+        write_ready_file(&path).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("{\"ready\":true,\"timestamp\":\""));
+        assert!(contents.ends_with("Z\"}\n"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
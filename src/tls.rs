@@ -0,0 +1,287 @@
+//! TLS for `https` URLs.
+//!
+//! TLS is implemented with rustls, using ring for cryptography, rather than
+//! with a system library like OpenSSL. This keeps static musl builds possible
+//! so that linkerd-await can run in scratch and distroless images.
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+};
+use std::{fmt, io, sync::Arc};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+/// Establishes TLS sessions on connections to `https` URLs.
+#[derive(Clone)]
+pub struct TlsConnector {
+    /// Retained so that a client certificate can be configured after the
+    /// server's verification.
+    builder: rustls::ConfigBuilder<rustls::ClientConfig, rustls::client::WantsClientCert>,
+    connector: tokio_rustls::TlsConnector,
+}
+
+/// Accepts any server certificate, though handshake signatures are still
+/// verified so that the session's keys are bound to the certificate.
+#[derive(Debug)]
+struct SkipVerify(rustls::crypto::WebPkiSupportedAlgorithms);
+
+// === impl TlsConnector ===
+
+impl TlsConnector {
+    /// Creates a connector that verifies servers against the Mozilla root
+    /// certificates compiled into linkerd-await.
+    pub fn new() -> Self {
+        Self::from_roots(rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        })
+    }
+
+    /// Creates a connector that verifies servers against the given
+    /// PEM-encoded CA certificates.
+    pub fn from_pem(pem: &[u8]) -> io::Result<Self> {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in parse_certs(pem)? {
+            roots.add(cert).map_err(invalid)?;
+        }
+        Ok(Self::from_roots(roots))
+    }
+
+    /// Creates a connector that accepts any server certificate, e.g. for an
+    /// admin server with a self-signed certificate. Sessions are encrypted
+    /// but the server is not authenticated.
+    pub fn insecure() -> Self {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier = Arc::new(SkipVerify(provider.signature_verification_algorithms));
+        Self::from_builder(
+            Self::builder(provider)
+                .dangerous()
+                .with_custom_certificate_verifier(verifier),
+        )
+    }
+
+    /// Presents the PEM-encoded client certificate chain, signed by the
+    /// PEM-encoded private key, to servers that request one.
+    pub fn with_client_cert(self, cert_pem: &[u8], key_pem: &[u8]) -> io::Result<Self> {
+        use rustls::pki_types::{pem::PemObject, PrivateKeyDer};
+
+        let certs = parse_certs(cert_pem)?;
+        let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(invalid)?;
+        let config = self
+            .builder
+            .clone()
+            .with_client_auth_cert(certs, key)
+            .map_err(invalid)?;
+        Ok(Self {
+            connector: connector(config),
+            ..self
+        })
+    }
+
+    fn from_roots(roots: rustls::RootCertStore) -> Self {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        Self::from_builder(Self::builder(provider).with_root_certificates(roots))
+    }
+
+    fn builder(
+        provider: Arc<rustls::crypto::CryptoProvider>,
+    ) -> rustls::ConfigBuilder<rustls::ClientConfig, rustls::WantsVerifier> {
+        rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .expect("ring must support the default protocol versions")
+    }
+
+    fn from_builder(
+        builder: rustls::ConfigBuilder<rustls::ClientConfig, rustls::client::WantsClientCert>,
+    ) -> Self {
+        Self {
+            connector: connector(builder.clone().with_no_client_auth()),
+            builder,
+        }
+    }
+
+    /// Establishes a TLS session with the host over the connection.
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        io: TcpStream,
+    ) -> io::Result<TlsStream<TcpStream>> {
+        let name = ServerName::try_from(host.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.connector.connect(name, io).await
+    }
+}
+
+fn connector(mut config: rustls::ClientConfig) -> tokio_rustls::TlsConnector {
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Arc::new(config).into()
+}
+
+/// Parses PEM-encoded certificates, failing if there are none.
+fn parse_certs(pem: &[u8]) -> io::Result<Vec<CertificateDer<'static>>> {
+    use rustls::pki_types::pem::PemObject;
+
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    if certs.is_empty() {
+        return Err(invalid("no certificates found"));
+    }
+    Ok(certs)
+}
+
+fn invalid(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+impl Default for TlsConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TlsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector").finish_non_exhaustive()
+    }
+}
+
+// === impl SkipVerify ===
+
+impl ServerCertVerifier for SkipVerify {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::{pem::PemObject, PrivateKeyDer};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const CERT: &[u8] = include_bytes!("../tests/support/localhost.crt");
+    const KEY: &[u8] = include_bytes!("../tests/support/localhost.key");
+
+    /// Serves TLS sessions with a self-signed certificate for `localhost`,
+    /// echoing a byte on each. Clients must present the same certificate if
+    /// `mtls` is set.
+    async fn serve(mtls: bool) -> std::net::SocketAddr {
+        let certs = parse_certs(CERT).unwrap();
+        let key = PrivateKeyDer::from_pem_slice(KEY).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = if mtls {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(certs[0].clone()).unwrap();
+            let verifier =
+                rustls::server::WebPkiClientVerifier::builder_with_provider(roots.into(), provider)
+                    .build()
+                    .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        let config = builder.with_single_cert(certs, key).unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (io, _) = listener.accept().await.unwrap();
+                if let Ok(mut io) = acceptor.accept(io).await {
+                    let mut buf = [0; 1];
+                    if io.read_exact(&mut buf).await.is_ok() {
+                        let _ = io.write_all(&buf).await;
+                    }
+                }
+            }
+        });
+        addr
+    }
+
+    async fn handshake(tls: &TlsConnector, addr: std::net::SocketAddr) -> io::Result<()> {
+        let mut io = tls
+            .connect("localhost", TcpStream::connect(addr).await?)
+            .await?;
+        io.write_all(b"x").await?;
+        let mut buf = [0; 1];
+        io.read_exact(&mut buf).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verification() {
+        let addr = serve(false).await;
+
+        let error = handshake(&TlsConnector::new(), addr).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", error);
+
+        let ca = TlsConnector::from_pem(CERT).unwrap();
+        handshake(&ca, addr).await.unwrap();
+
+        handshake(&TlsConnector::insecure(), addr).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_cert() {
+        let addr = serve(true).await;
+
+        let ca = TlsConnector::from_pem(CERT).unwrap();
+        assert!(handshake(&ca, addr).await.is_err());
+
+        let mtls = ca.with_client_cert(CERT, KEY).unwrap();
+        handshake(&mtls, addr).await.unwrap();
+    }
+
+    #[test]
+    fn test_with_client_cert_invalid() {
+        let error = TlsConnector::new()
+            .with_client_cert(CERT, b"")
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(TlsConnector::new().with_client_cert(b"", KEY).is_err());
+    }
+
+    #[test]
+    fn test_from_pem_without_certificates() {
+        let error = TlsConnector::from_pem(b"").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,415 @@
+//! Runs the linkerd-await binary against a mock admin server.
+
+#![cfg(feature = "runtime")]
+
+mod support;
+
+use self::support::{linkerd_await, stderr, MockAdmin};
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use std::{
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn waits_for_ready() {
+    let admin = MockAdmin::start(&[503, 503, 200]);
+    let output = linkerd_await(&admin)
+        .args(["--backoff", "10ms", "--", "sh", "-c", "exit 0"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(admin.count("GET /ready"), 3);
+    assert_eq!(admin.count("POST /shutdown"), 0);
+}
+
+#[test]
+fn execs_cmd() {
+    let admin = MockAdmin::start(&[200]);
+    let output = linkerd_await(&admin)
+        .args(["--", "sh", "-c", "echo $0; exit 7", "ran"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ran\n");
+}
+
+#[test]
+fn timeout() {
+    let admin = MockAdmin::start(&[503]);
+    let output = linkerd_await(&admin)
+        .args(["--backoff", "10ms", "--timeout", "200ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(
+        stderr(&output).contains("within 200ms timeout"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn require_timeout() {
+    let admin = MockAdmin::start(&[200]);
+    let require = format!(
+        "retry(http=http://127.0.0.1:{}/missing, timeout=200ms)",
+        admin.port()
+    );
+    let output = linkerd_await(&admin)
+        .args(["--backoff", "10ms", "--require", &require, "--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(
+        stderr(&output).contains("Readiness check timed out"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn max_attempts() {
+    let admin = MockAdmin::start(&[503]);
+    let output = linkerd_await(&admin)
+        .args(["--backoff", "10ms", "--max-attempts", "3", "--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(76));
+    assert!(
+        stderr(&output).contains("after 3 checks"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(admin.count("GET /ready"), 3);
+}
+
+#[test]
+fn timeout_not_fatal() {
+    let admin = MockAdmin::start(&[503]);
+    let output = linkerd_await(&admin)
+        .args([
+            "--backoff",
+            "10ms",
+            "--timeout",
+            "100ms",
+            "--timeout-fatal=false",
+            "--",
+            "sh",
+            "-c",
+            "exit 5",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn backoff() {
+    let admin = MockAdmin::start(&[503]);
+    let start = Instant::now();
+    let output = linkerd_await(&admin)
+        .args([
+            "--backoff",
+            "100ms",
+            "--backoff-strategy",
+            "exponential",
+            "--timeout",
+            "1s",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    // Checks at 0, 100, 300, and 700ms; the next would be at 1500ms.
+    assert_eq!(admin.count("GET /ready"), 4);
+}
+
+#[test]
+fn unrecoverable() {
+    let admin = MockAdmin::start(&[404]);
+    let output = linkerd_await(&admin).args(["--", "true"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(78));
+    assert_eq!(admin.count("GET /ready"), 1);
+}
+
+#[test]
+fn not_found() {
+    // Linkerd's admin server always serves `/ready`, so a 404 means that it
+    // isn't the proxy.
+    let admin = MockAdmin::start(&[404, 200]);
+    let output = linkerd_await(&admin).args(["--", "true"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(78));
+    assert_eq!(admin.count("GET /ready"), 1);
+
+    // Other sidecars, e.g. Envoy, and dependencies may answer 404 while they
+    // start.
+    let admin = MockAdmin::start(&[404, 200]);
+    let output = linkerd_await(&admin)
+        .args(["--mesh=envoy", "--backoff=10ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(admin.count("GET /ready"), 2);
+
+    let admin = MockAdmin::start(&[200]);
+    let sidecar = MockAdmin::start(&[404, 200]);
+    let http = MockAdmin::start(&[404, 200]);
+    let output = linkerd_await(&admin)
+        .arg(format!(
+            "--sidecar=http://127.0.0.1:{}/ready",
+            sidecar.port()
+        ))
+        .arg(format!("--http=http://127.0.0.1:{}/ready", http.port()))
+        .args(["--backoff=10ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(sidecar.count("GET /ready") >= 2);
+    assert!(http.count("GET /ready") >= 2);
+}
+
+#[test]
+fn exec_probe_timeout() {
+    // A command may take longer than other targets' attempts, as long as it
+    // finishes within `--exec-probe-timeout`.
+    let admin = MockAdmin::start(&[200]);
+    let output = linkerd_await(&admin)
+        .args(["--exec-probe=sleep 5.2", "--exec-probe-timeout=10s"])
+        .args(["--max-attempts=1", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn verify_proxy() {
+    let admin = MockAdmin::start(&[503, 200]);
+    let output = linkerd_await(&admin)
+        .args(["--verify-proxy", "--backoff", "10ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    // The server is only verified once.
+    assert_eq!(
+        admin.requests(),
+        ["GET /metrics", "GET /ready", "GET /ready"]
+    );
+}
+
+#[test]
+fn shutdown() {
+    let admin = MockAdmin::start(&[200]);
+    let output = linkerd_await(&admin)
+        .args(["--shutdown", "--", "sh", "-c", "exit 3"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(admin.requests(), ["GET /ready", "POST /shutdown"]);
+}
+
+#[test]
+fn app_mesh_drains() {
+    let admin = MockAdmin::start(&[200]);
+    let start = Instant::now();
+    let output = linkerd_await(&admin)
+        .args(["--mesh", "app-mesh", "--shutdown-path", "/shutdown"])
+        .args(["--shutdown", "--drain-time", "300ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    // The listeners are given the drain time before the proxy is shut down.
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(
+        admin.requests(),
+        [
+            "GET /ready",
+            "POST /drain_listeners?graceful",
+            "POST /shutdown"
+        ]
+    );
+}
+
+#[test]
+fn verify_shutdown_timeout() {
+    // The mock admin server keeps answering after the shutdown request.
+    let admin = MockAdmin::start(&[200]);
+    let output = linkerd_await(&admin)
+        .args(["--shutdown", "--verify-shutdown=200ms", "--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(
+        stderr(&output).contains("may not have shut down"),
+        "{}",
+        stderr(&output)
+    );
+
+    // CMD's failure is reported instead.
+    let output = linkerd_await(&admin)
+        .args([
+            "--shutdown",
+            "--verify-shutdown=200ms",
+            "--",
+            "sh",
+            "-c",
+            "exit 3",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn check_command() {
+    let admin = MockAdmin::start(&[503, 200]);
+    let check = || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_linkerd-await"))
+            .env_remove("LINKERD_AWAIT_DISABLED")
+            .env_remove("LINKERD_DISABLED")
+            .args(["check", "--port", &admin.port().to_string()])
+            .output()
+            .unwrap()
+    };
+    let output = check();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Not ready"));
+    let output = check();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(admin.requests(), ["GET /ready", "GET /ready"]);
+
+    // The admin server is located by linkerd-await's flags and environment.
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_linkerd-await"))
+        .env_remove("LINKERD_AWAIT_DISABLED")
+        .env_remove("LINKERD_DISABLED")
+        .env("LINKERD_AWAIT_PORT", admin.port().to_string())
+        .args(["check", "--ready-path", "/metrics"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        admin.requests(),
+        ["GET /ready", "GET /ready", "GET /metrics"]
+    );
+}
+
+#[test]
+fn shutdown_command() {
+    let admin = MockAdmin::start(&[200]);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_linkerd-await"))
+        .env_remove("LINKERD_AWAIT_DISABLED")
+        .env_remove("LINKERD_DISABLED")
+        .args(["shutdown", "--port", &admin.port().to_string()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(admin.requests(), ["POST /shutdown"]);
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_linkerd-await"))
+        .env_remove("LINKERD_AWAIT_DISABLED")
+        .env_remove("LINKERD_DISABLED")
+        .args(["shutdown", "--port", &admin.port().to_string()])
+        .args([
+            "--shutdown-path",
+            "/quitquitquit",
+            "--shutdown-timeout",
+            "100ms",
+        ])
+        .args(["--backoff", "10ms"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(
+        stderr(&output).contains("Failed to shut down the sidecar"),
+        "{}",
+        stderr(&output)
+    );
+
+    // The shutdown is verified as it is once CMD completes.
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_linkerd-await"))
+        .env_remove("LINKERD_AWAIT_DISABLED")
+        .env_remove("LINKERD_DISABLED")
+        .env("LINKERD_AWAIT_PORT", admin.port().to_string())
+        .args(["shutdown", "--verify-shutdown=200ms"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(
+        stderr(&output).contains("may not have shut down"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn disabled() {
+    let admin = MockAdmin::start(&[503]);
+    let output = linkerd_await(&admin)
+        .env("LINKERD_AWAIT_DISABLED", "testing")
+        .args(["--shutdown", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(admin.requests().is_empty());
+}
+
+#[test]
+fn forwards_sigterm() {
+    let admin = MockAdmin::start(&[200]);
+    let child = linkerd_await(&admin)
+        .args([
+            "--shutdown",
+            "--",
+            "sh",
+            "-c",
+            "trap 'kill $!; exit 9' TERM; sleep 10 & wait",
+        ])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Wait for the command to start before asking linkerd-await to stop.
+    while admin.count("GET /ready") == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    thread::sleep(Duration::from_millis(500));
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(9), "{}", stderr(&output));
+    assert_eq!(admin.count("POST /shutdown"), 1);
+}
+
+#[test]
+fn blocks_sighup() {
+    let admin = MockAdmin::start(&[200]);
+    let child = linkerd_await(&admin)
+        .args([
+            "--shutdown",
+            "--block-signals=HUP",
+            "--",
+            "sh",
+            "-c",
+            "trap 'exit 9' HUP; sleep 1; exit 4",
+        ])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    while admin.count("GET /ready") == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    thread::sleep(Duration::from_millis(300));
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGHUP).unwrap();
+
+    // Neither linkerd-await nor CMD is terminated, so CMD's own exit code is
+    // reported after the proxy is shut down.
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Ignoring SIGHUP"));
+    assert_eq!(admin.count("POST /shutdown"), 1);
+}
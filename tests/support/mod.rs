@@ -0,0 +1,128 @@
+//! A mock Linkerd proxy admin server for integration tests.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Command, Output},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Serves `/ready`, `/shutdown`, and `/metrics`, recording every request it receives.
+#[derive(Clone)]
+pub struct MockAdmin {
+    port: u16,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The statuses returned by `/ready`, in order. The last status is repeated
+    /// once the others have been returned.
+    ready: Vec<u16>,
+    requests: Vec<String>,
+}
+
+impl MockAdmin {
+    /// Starts a server whose readiness endpoint returns each of the statuses
+    /// in turn, repeating the last one.
+    pub fn start(ready: &[u16]) -> Self {
+        assert!(!ready.is_empty());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let admin = Self {
+            port: listener.local_addr().unwrap().port(),
+            state: Arc::new(Mutex::new(State {
+                ready: ready.to_vec(),
+                requests: Vec::new(),
+            })),
+        };
+        let server = admin.clone();
+        thread::spawn(move || {
+            for io in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || server.serve(io));
+            }
+        });
+        admin
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the request lines received so far, e.g. `GET /ready`.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn count(&self, request: &str) -> usize {
+        self.requests().iter().filter(|r| *r == request).count()
+    }
+
+    /// Serves requests on a connection until the client closes it.
+    fn serve(&self, io: TcpStream) {
+        let mut reader = BufReader::new(io.try_clone().unwrap());
+        let mut io = io;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let mut parts = line.split_whitespace();
+            let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+            let request = format!("{} {}", method, path);
+
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let (status, body) = {
+                let mut state = self.state.lock().unwrap();
+                state.requests.push(request.clone());
+                match request.as_str() {
+                    "GET /ready" if state.ready.len() > 1 => (state.ready.remove(0), ""),
+                    "GET /ready" => (state.ready[0], ""),
+                    "GET /metrics" => (200, "proxy_build_info{version=\"mock\"} 1\n"),
+                    "POST /shutdown" => (202, ""),
+                    "POST /drain_listeners?graceful" => (200, ""),
+                    _ => (404, ""),
+                }
+            };
+            let rsp = format!(
+                "HTTP/1.1 {} Mock\r\ncontent-length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if io.write_all(rsp.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Builds a linkerd-await command that checks the mock admin server.
+pub fn linkerd_await(admin: &MockAdmin) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_linkerd-await"));
+    cmd.env_remove("LINKERD_AWAIT_DISABLED")
+        .env_remove("LINKERD_DISABLED")
+        .args(["--port", &admin.port().to_string()]);
+    cmd
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}